@@ -0,0 +1,3 @@
+fn main() {
+    sudo_rs::sudoreplay_main()
+}
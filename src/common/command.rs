@@ -1,10 +1,13 @@
 use std::{
     fmt::Display,
+    io,
     path::{Path, PathBuf},
 };
 
 use crate::system::escape_os_str_lossy;
 
+use super::Error;
+
 use super::resolve::{canonicalize, resolve_path};
 
 #[derive(Debug, Default)]
@@ -93,6 +96,40 @@ impl CommandAndArguments {
             arg0,
         }
     }
+
+    /// For sudoedit, the "command" is the pseudo-command `sudoedit` and its arguments are the
+    /// absolute paths of the files to be edited; the files themselves need not exist yet, but
+    /// the directories containing them must.
+    pub fn build_for_edit(files: Vec<String>) -> Result<Self, Error> {
+        let mut arguments = Vec::with_capacity(files.len());
+        for file in files {
+            let path = Path::new(&file);
+            let Some(file_name) = path.file_name() else {
+                return Err(Error::InvalidEditFile(
+                    file.into(),
+                    io::ErrorKind::InvalidInput.into(),
+                ));
+            };
+            let parent = match path.parent() {
+                Some(dir) if dir != Path::new("") => dir,
+                _ => Path::new("."),
+            };
+            let resolved = std::fs::canonicalize(parent)
+                .map_err(|err| Error::InvalidEditFile(file.clone().into(), err))?
+                .join(file_name);
+            let Some(resolved) = resolved.to_str() else {
+                return Err(Error::PathValidation(resolved));
+            };
+            arguments.push(resolved.to_string());
+        }
+
+        Ok(CommandAndArguments {
+            command: "sudoedit".into(),
+            arguments,
+            resolved: true,
+            arg0: None,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -115,6 +152,21 @@ mod test {
         test(&["! @ $"], "\\!\\ \\@\\ $");
     }
 
+    #[test]
+    fn test_build_for_edit() {
+        let cmd = CommandAndArguments::build_for_edit(vec![
+            "/etc/hosts".into(),
+            "/usr/bin/../lib/new_file".into(),
+        ])
+        .unwrap();
+        assert_eq!(cmd.command, std::path::Path::new("sudoedit"));
+        assert_eq!(cmd.arguments, ["/etc/hosts", "/usr/lib/new_file"]);
+
+        assert!(CommandAndArguments::build_for_edit(vec!["/".into()]).is_err());
+        assert!(CommandAndArguments::build_for_edit(vec!["/etc/..".into()]).is_err());
+        assert!(CommandAndArguments::build_for_edit(vec!["/no/such/dir/file".into()]).is_err());
+    }
+
     #[test]
     fn test_build_command_and_args() {
         assert_eq!(
@@ -1,15 +1,19 @@
+use std::{cell::OnceCell, path::PathBuf};
+
 use crate::common::{HARDENED_ENUM_VALUE_0, HARDENED_ENUM_VALUE_1, HARDENED_ENUM_VALUE_2};
-use crate::system::{Group, Hostname, Process, User};
+use crate::iolog::IoLogOptions;
+use crate::system::{file::Executable, Group, Hostname, Process, User};
 
 use super::resolve::CurrentUser;
 use super::{
     command::CommandAndArguments,
-    resolve::{resolve_launch_and_shell, resolve_target_user_and_group},
+    resolve::{resolve_askpass, resolve_launch_and_shell, resolve_target_user_and_group},
     Error, SudoPath, SudoString,
 };
 
 #[derive(Clone, Copy)]
 pub enum ContextAction {
+    Edit,
     List,
     Run,
     Validate,
@@ -17,11 +21,15 @@ pub enum ContextAction {
 
 // this is a bit of a hack to keep the existing `Context` API working
 pub struct OptionsForContext {
+    pub askpass: bool,
+    pub background: bool,
     pub chdir: Option<SudoPath>,
+    pub close_from: Option<i32>,
     pub group: Option<SudoString>,
     pub login: bool,
     pub non_interactive: bool,
     pub positional_args: Vec<String>,
+    pub prompt: Option<String>,
     pub reset_timestamp: bool,
     pub shell: bool,
     pub stdin: bool,
@@ -35,17 +43,25 @@ pub struct Context {
     pub launch: LaunchType,
     pub chdir: Option<SudoPath>,
     pub command: CommandAndArguments,
+    /// the executable of the command; only opened once the policy inspects its contents
+    pub executable: OnceCell<Option<Executable>>,
     pub target_user: User,
     pub target_group: Group,
     pub stdin: bool,
+    pub askpass: Option<PathBuf>,
+    pub prompt: Option<String>,
     pub non_interactive: bool,
     pub use_session_records: bool,
+    pub background: bool,
+    pub close_from: Option<i32>,
     // system
     pub hostname: Hostname,
     pub current_user: CurrentUser,
     pub process: Process,
     // policy
     pub use_pty: bool,
+    pub iolog: Option<IoLogOptions>,
+    pub noexec: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -57,12 +73,14 @@ pub enum LaunchType {
 }
 
 impl Context {
+    /// `path` is where the command is looked up; since this can depend on the `Defaults` that
+    /// apply to `current_user` on `hostname`, these are resolved by the caller
     pub fn build_from_options(
         sudo_options: OptionsForContext,
+        current_user: CurrentUser,
+        hostname: Hostname,
         path: String,
     ) -> Result<Context, Error> {
-        let hostname = Hostname::resolve();
-        let current_user = CurrentUser::resolve()?;
         let (target_user, target_group) =
             resolve_target_user_and_group(&sudo_options.user, &sudo_options.group, &current_user)?;
         let (launch, shell) = resolve_launch_and_shell(&sudo_options, &current_user, &target_user);
@@ -73,12 +91,26 @@ impl Context {
                 // FIXME `Default` is being used as `Option::None`
                 Default::default()
             }
+            ContextAction::Edit => {
+                CommandAndArguments::build_for_edit(sudo_options.positional_args)?
+            }
             _ => CommandAndArguments::build_from_args(shell, sudo_options.positional_args, &path),
         };
+        let askpass = if sudo_options.askpass {
+            Some(resolve_askpass()?)
+        } else {
+            None
+        };
+        let executable = match sudo_options.action {
+            ContextAction::Edit => OnceCell::from(None),
+            _ if command.resolved => OnceCell::new(),
+            _ => OnceCell::from(None),
+        };
 
         Ok(Context {
             hostname,
             command,
+            executable,
             current_user,
             target_user,
             target_group,
@@ -86,16 +118,30 @@ impl Context {
             launch,
             chdir: sudo_options.chdir,
             stdin: sudo_options.stdin,
+            askpass,
+            prompt: sudo_options.prompt,
             non_interactive: sudo_options.non_interactive,
+            background: sudo_options.background,
+            close_from: sudo_options.close_from,
             process: Process::new(),
             use_pty: true,
+            iolog: None,
+            noexec: false,
         })
     }
+
+    /// The executable of the command, which is opened when this is first called. This should only
+    /// happen when the policy needs to inspect its contents.
+    pub fn open_executable(&self) -> Option<&Executable> {
+        self.executable
+            .get_or_init(|| Executable::open(&self.command.command).ok())
+            .as_ref()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{sudo::SudoAction, system::Hostname};
+    use crate::{common::resolve::CurrentUser, sudo::SudoAction, system::Hostname};
     use std::collections::HashMap;
 
     use super::Context;
@@ -108,7 +154,13 @@ mod tests {
             .ok()
             .unwrap();
         let path = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
-        let context = Context::build_from_options(options.into(), path.to_string()).unwrap();
+        let context = Context::build_from_options(
+            options.into(),
+            CurrentUser::resolve().unwrap(),
+            Hostname::resolve(),
+            path.to_string(),
+        )
+        .unwrap();
 
         let mut target_environment = HashMap::new();
         target_environment.insert("SUDO_USER".to_string(), context.current_user.name.clone());
@@ -19,6 +19,10 @@ pub enum Error {
         chdir: SudoPath,
         command: PathBuf,
     },
+    CloseFromNotAllowed,
+    AskpassNotSpecified,
+    PreserveEnvNotAllowed,
+    EnvVarNotAllowed(Vec<String>),
     UserNotFound(String),
     GroupNotFound(String),
     Authentication(String),
@@ -26,6 +30,7 @@ pub enum Error {
     Options(String),
     Pam(PamError),
     Io(Option<PathBuf>, std::io::Error),
+    InvalidEditFile(PathBuf, std::io::Error),
     MaxAuthAttempts(usize),
     PathValidation(PathBuf),
     StringValidation(String),
@@ -63,6 +68,8 @@ impl fmt::Display for Error {
             Error::Authentication(e) => write!(f, "authentication failed: {e}"),
             Error::Configuration(e) => write!(f, "invalid configuration: {e}"),
             Error::Options(e) => write!(f, "{e}"),
+            // this is not a problem with PAM itself, but with how we talk to the user
+            Error::Pam(e @ (PamError::TtyRequired | PamError::TimedOut)) => write!(f, "{e}"),
             Error::Pam(e) => write!(f, "PAM error: {e}"),
             Error::Io(location, e) => {
                 if let Some(path) = location {
@@ -71,6 +78,9 @@ impl fmt::Display for Error {
                     write!(f, "IO error: {e}")
                 }
             }
+            Error::InvalidEditFile(path, e) => {
+                write!(f, "cannot edit '{}': {e}", path.display())
+            }
             Error::MaxAuthAttempts(num) => {
                 write!(f, "Maximum {num} incorrect authentication attempts")
             }
@@ -80,6 +90,18 @@ impl fmt::Display for Error {
                 chdir.display(),
                 command.display()
             ),
+            Error::AskpassNotSpecified => {
+                f.write_str("no askpass program specified, try setting SUDO_ASKPASS")
+            }
+            Error::CloseFromNotAllowed => f.write_str("you are not permitted to use the -C option"),
+            Error::PreserveEnvNotAllowed => {
+                f.write_str("sorry, you are not allowed to preserve the environment")
+            }
+            Error::EnvVarNotAllowed(vars) => write!(
+                f,
+                "sorry, you are not allowed to set the following environment variables: {}",
+                vars.join(" ")
+            ),
             Error::StringValidation(string) => {
                 write!(f, "invalid string: {string:?}")
             }
@@ -0,0 +1,192 @@
+//! A minimal JSON writer, for the machine-readable output of `visudo`.
+use std::fmt;
+
+/// A JSON value; objects keep their fields in the order they were given in.
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Build an object from (field name, value) pairs
+    pub fn object<'a>(fields: impl IntoIterator<Item = (&'a str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl Json {
+    /// With a nesting `level`, every element of an array or object goes on a line of its own
+    fn write(&self, f: &mut fmt::Formatter, level: Option<usize>) -> fmt::Result {
+        const INDENT: usize = 4;
+
+        let newline = |f: &mut fmt::Formatter, extra: usize| match level {
+            Some(level) => write!(f, "\n{:width$}", "", width = INDENT * (level + extra)),
+            None => Ok(()),
+        };
+        let nested = level.map(|level| level + 1);
+
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(text) => write_string(f, text),
+            Json::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    newline(f, 1)?;
+                    value.write(f, nested)?;
+                }
+                if !values.is_empty() {
+                    newline(f, 0)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    newline(f, 1)?;
+                    write_string(f, name)?;
+                    f.write_str(if level.is_some() { ": " } else { ":" })?;
+                    value.write(f, nested)?;
+                }
+                if !fields.is_empty() {
+                    newline(f, 0)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Writes the value on a single line, without any insignificant whitespace; or with `{:#}`,
+/// spread out over multiple indented lines.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, f.alternate().then_some(0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Json;
+
+    #[test]
+    fn display() {
+        let value = Json::object([
+            ("file", "/etc/sudo\"ers\n".into()),
+            ("line", 3i64.into()),
+            ("column", None::<i64>.into()),
+            ("ok", false.into()),
+            ("files", vec!["a", "b\\c\u{1}"].into()),
+            ("empty", Json::object([])),
+        ]);
+
+        assert_eq!(
+            value.to_string(),
+            r#"{"file":"/etc/sudo\"ers\n","line":3,"column":null,"ok":false,"files":["a","b\\c\u0001"],"empty":{}}"#
+        );
+    }
+
+    #[test]
+    fn display_pretty() {
+        let value = Json::object([
+            ("files", vec!["a", "b"].into()),
+            (
+                "rules",
+                Json::Array(vec![Json::object([("users", vec!["root"].into())])]),
+            ),
+            ("empty", Json::Array(vec![])),
+        ]);
+
+        assert_eq!(
+            format!("{value:#}"),
+            r#"{
+    "files": [
+        "a",
+        "b"
+    ],
+    "rules": [
+        {
+            "users": [
+                "root"
+            ]
+        }
+    ],
+    "empty": []
+}"#
+        );
+    }
+}
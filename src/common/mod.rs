@@ -10,7 +10,9 @@ pub use string::SudoString;
 pub mod bin_serde;
 pub mod command;
 pub mod context;
+pub mod digest;
 pub mod error;
+pub mod json;
 mod path;
 pub mod resolve;
 mod string;
@@ -172,6 +172,43 @@ pub(crate) fn is_valid_executable(path: &PathBuf) -> bool {
     }
 }
 
+/// The editor to use when the policy does not specify one
+pub(crate) fn editor_path_fallback() -> io::Result<PathBuf> {
+    let path = Path::new("/usr/bin/editor");
+    if crate::system::can_execute(path) {
+        return Ok(path.to_owned());
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "cannot find text editor",
+    ))
+}
+
+/// The program that asks for a password when `--askpass` is used: the one named in `SUDO_ASKPASS`
+/// or, failing that, the `Path askpass` entry in sudo.conf
+pub(super) fn resolve_askpass() -> Result<PathBuf, Error> {
+    env::var_os("SUDO_ASKPASS")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            let text = fs::read_to_string("/etc/sudo.conf").ok()?;
+            askpass_from_sudo_conf(&text)
+        })
+        .ok_or(Error::AskpassNotSpecified)
+}
+
+fn askpass_from_sudo_conf(text: &str) -> Option<PathBuf> {
+    // later entries take precedence
+    text.lines().rev().find_map(|line| {
+        let mut words = line.split_whitespace();
+        match (words.next()?, words.next()?, words.next()?) {
+            ("Path", "askpass", path) => Some(PathBuf::from(path)),
+            _ => None,
+        }
+    })
+}
+
 /// Resolve a executable name based in the PATH environment variable
 /// When resolving a path, this code checks whether the target file is
 /// a regular file and has any executable bits set. It does not specifically
@@ -224,7 +261,25 @@ mod tests {
 
     use crate::common::resolve::CurrentUser;
 
-    use super::{is_valid_executable, resolve_path, resolve_target_user_and_group, NameOrId};
+    use super::{
+        askpass_from_sudo_conf, is_valid_executable, resolve_path, resolve_target_user_and_group,
+        NameOrId,
+    };
+
+    #[test]
+    fn test_askpass_from_sudo_conf() {
+        assert_eq!(askpass_from_sudo_conf("Set disable_coredump false\n"), None);
+        assert_eq!(
+            askpass_from_sudo_conf("#Path askpass /usr/bin/ssh-askpass\n"),
+            None
+        );
+        assert_eq!(
+            askpass_from_sudo_conf(
+                "Path askpass /usr/bin/ssh-askpass\nPath  askpass\t/usr/local/bin/askpass\n"
+            ),
+            Some(PathBuf::from("/usr/local/bin/askpass"))
+        );
+    }
 
     #[test]
     fn test_resolve_path() {
@@ -0,0 +1,259 @@
+#[derive(Debug, PartialEq)]
+pub(crate) struct CvtsudoersOptions {
+    pub(crate) input: Option<String>,
+    pub(crate) output: Option<String>,
+    pub(crate) format: OutputFormat,
+    pub(crate) user: Option<String>,
+    pub(crate) host: Option<String>,
+    pub(crate) expand_aliases: bool,
+    pub(crate) action: CvtsudoersAction,
+}
+
+impl Default for CvtsudoersOptions {
+    fn default() -> Self {
+        Self {
+            input: None,
+            output: None,
+            format: OutputFormat::Sudoers,
+            user: None,
+            host: None,
+            expand_aliases: false,
+            action: CvtsudoersAction::Convert,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum CvtsudoersAction {
+    Help,
+    Version,
+    Convert,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum OutputFormat {
+    Sudoers,
+    Json,
+}
+
+type OptionSetter = fn(&mut CvtsudoersOptions, Option<String>) -> Result<(), String>;
+
+struct CvtsudoersOption {
+    short: char,
+    long: &'static str,
+    takes_argument: bool,
+    set: OptionSetter,
+}
+
+/// Parse a filter such as `user=alice,host=web1`; a later filter for the same key overrides an
+/// earlier one.
+fn parse_filter(options: &mut CvtsudoersOptions, filter: &str) -> Result<(), String> {
+    for item in filter.split(',') {
+        let Some((key, value)) = item.split_once('=').filter(|(_, value)| !value.is_empty()) else {
+            Err(format!("invalid filter: {item}"))?
+        };
+        match key {
+            "user" => options.user = Some(value.to_string()),
+            "host" => options.host = Some(value.to_string()),
+            _ => Err(format!("unsupported filter: {key}"))?,
+        }
+    }
+
+    Ok(())
+}
+
+impl CvtsudoersOptions {
+    const CVTSUDOERS_OPTIONS: &'static [CvtsudoersOption] = &[
+        CvtsudoersOption {
+            short: 'e',
+            long: "expand-aliases",
+            takes_argument: false,
+            set: |options, _| {
+                options.expand_aliases = true;
+                Ok(())
+            },
+        },
+        CvtsudoersOption {
+            short: 'f',
+            long: "output-format",
+            takes_argument: true,
+            set: |options, argument| {
+                let format = argument.ok_or("option requires an argument -- 'f'")?;
+                options.format = match format.to_ascii_lowercase().as_str() {
+                    "sudoers" => OutputFormat::Sudoers,
+                    "json" => OutputFormat::Json,
+                    _ => Err(format!("unsupported output format '{format}'"))?,
+                };
+                Ok(())
+            },
+        },
+        CvtsudoersOption {
+            short: 'h',
+            long: "help",
+            takes_argument: false,
+            set: |options, _| {
+                options.action = CvtsudoersAction::Help;
+                Ok(())
+            },
+        },
+        CvtsudoersOption {
+            short: 'm',
+            long: "match",
+            takes_argument: true,
+            set: |options, argument| {
+                parse_filter(
+                    options,
+                    &argument.ok_or("option requires an argument -- 'm'")?,
+                )
+            },
+        },
+        CvtsudoersOption {
+            short: 'o',
+            long: "output",
+            takes_argument: true,
+            set: |options, argument| {
+                options.output = Some(argument.ok_or("option requires an argument -- 'o'")?);
+                Ok(())
+            },
+        },
+        CvtsudoersOption {
+            short: 'V',
+            long: "version",
+            takes_argument: false,
+            set: |options, _| {
+                options.action = CvtsudoersAction::Version;
+                Ok(())
+            },
+        },
+    ];
+
+    pub(crate) fn from_env() -> Result<CvtsudoersOptions, String> {
+        let args = std::env::args().collect();
+
+        Self::parse_arguments(args)
+    }
+
+    /// parse cvtsudoers arguments into CvtsudoersOptions struct
+    pub(crate) fn parse_arguments(arguments: Vec<String>) -> Result<CvtsudoersOptions, String> {
+        let mut options: CvtsudoersOptions = CvtsudoersOptions::default();
+        let mut arg_iter = arguments.into_iter().skip(1);
+
+        while let Some(arg) = arg_iter.next() {
+            // if the argument starts with -- it must be a full length option name
+            if arg.starts_with("--") {
+                // parse assignments like '--match=host=web1'
+                let (key, value) = match arg.split_once('=') {
+                    Some((key, value)) => (key, Some(value.to_string())),
+                    None => (arg.as_str(), None),
+                };
+                // lookup the option by name
+                let Some(option) = Self::CVTSUDOERS_OPTIONS
+                    .iter()
+                    .find(|o| o.long == &key[2..])
+                else {
+                    Err(format!("unrecognized option '{}'", arg))?
+                };
+                if option.takes_argument {
+                    // the value is either part of the assignment or the next argument
+                    let value = value.or_else(|| arg_iter.next());
+                    (option.set)(&mut options, value)?;
+                } else if value.is_some() {
+                    Err(format!("'--{}' does not take any arguments", option.long))?;
+                } else {
+                    (option.set)(&mut options, None)?;
+                }
+            } else if arg.starts_with('-') && arg != "-" {
+                // flags can be grouped, so we loop over the characters
+                for (n, char) in arg.trim_start_matches('-').chars().enumerate() {
+                    // lookup the option
+                    let Some(option) = Self::CVTSUDOERS_OPTIONS.iter().find(|o| o.short == char)
+                    else {
+                        Err(format!("unrecognized option '{}'", char))?
+                    };
+                    // try to parse an argument when one is necessary, either the rest of the
+                    // current flag group or the next argument
+                    if option.takes_argument {
+                        let rest = arg[(n + 2)..].trim().to_string();
+                        let next_arg = if rest.is_empty() {
+                            arg_iter.next()
+                        } else {
+                            Some(rest)
+                        };
+                        (option.set)(&mut options, next_arg)?;
+                        // stop looping over flags if the current flag takes an argument
+                        break;
+                    } else {
+                        (option.set)(&mut options, None)?;
+                    }
+                }
+            } else if options.input.is_none() {
+                options.input = Some(arg);
+            } else {
+                Err(format!("unexpected argument '{arg}'"))?;
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CvtsudoersAction, CvtsudoersOptions, OutputFormat};
+
+    fn parse(args: &[&str]) -> Result<CvtsudoersOptions, String> {
+        CvtsudoersOptions::parse_arguments(
+            std::iter::once("cvtsudoers")
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn convert() {
+        assert_eq!(
+            parse(&[
+                "-ef",
+                "JSON",
+                "--match",
+                "user=alice,host=web1",
+                "-o-",
+                "/tmp/sudoers"
+            ]),
+            Ok(CvtsudoersOptions {
+                input: Some("/tmp/sudoers".to_string()),
+                output: Some("-".to_string()),
+                format: OutputFormat::Json,
+                user: Some("alice".to_string()),
+                host: Some("web1".to_string()),
+                expand_aliases: true,
+                action: CvtsudoersAction::Convert,
+            })
+        );
+        assert_eq!(
+            parse(&[
+                "-m",
+                "user=alice",
+                "--match=user=bob",
+                "--output-format=sudoers"
+            ]),
+            Ok(CvtsudoersOptions {
+                user: Some("bob".to_string()),
+                ..Default::default()
+            })
+        );
+        assert!(parse(&["-f", "ldif"]).is_err());
+        assert!(parse(&["a", "b"]).is_err());
+        assert!(parse(&["--expand-aliases=yes"]).is_err());
+    }
+
+    #[test]
+    fn invalid_filters() {
+        assert!(parse(&["-m", "group=wheel"]).is_err());
+        assert!(parse(&["-m", "user"]).is_err());
+        assert!(parse(&["-m", "user="]).is_err());
+        assert!(parse(&["-m", "user=alice,"]).is_err());
+        assert!(parse(&["-m"]).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+pub(crate) const USAGE_MSG: &str =
+    "usage: cvtsudoers [-ehV] [-f output_format] [-m filter] [-o output_file] [input_file]";
+
+const DESCRIPTOR: &str = "cvtsudoers - convert between sudoers file formats";
+
+const HELP_MSG: &str = "Options:
+  -e, --expand-aliases     expand aliases when converting
+  -f, --output-format=format
+                           set output format: JSON or sudoers
+  -h, --help               display help message and exit
+  -m, --match=filter       only convert entries that match the filter
+  -o, --output=output_file write converted sudoers to output_file
+  -V, --version            display version information and exit
+";
+
+pub(crate) fn long_help_message() -> String {
+    format!("{USAGE_MSG}\n\n{DESCRIPTOR}\n\n{HELP_MSG}")
+}
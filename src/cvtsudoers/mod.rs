@@ -0,0 +1,114 @@
+#![forbid(unsafe_code)]
+mod cli;
+mod help;
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    common::{Error, SudoString},
+    sudo::diagnostic,
+    sudoers::Sudoers,
+    system::{interface::UnixUser, Hostname, User},
+};
+
+use self::cli::{CvtsudoersAction, CvtsudoersOptions, OutputFormat};
+use self::help::{long_help_message, USAGE_MSG};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+macro_rules! io_msg {
+    ($err:expr, $($tt:tt)*) => {
+        io::Error::new($err.kind(), format!("{}: {}", format_args!($($tt)*), $err))
+    };
+}
+
+pub fn main() {
+    let options = match CvtsudoersOptions::from_env() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln_ignore_io_error!("cvtsudoers: {error}\n{USAGE_MSG}");
+            std::process::exit(1);
+        }
+    };
+
+    let result = match options.action {
+        CvtsudoersAction::Help => {
+            println_ignore_io_error!("{}", long_help_message());
+            std::process::exit(0);
+        }
+        CvtsudoersAction::Version => {
+            println_ignore_io_error!("cvtsudoers version {VERSION}");
+            std::process::exit(0);
+        }
+        CvtsudoersAction::Convert => convert(&options),
+    };
+
+    if let Err(error) = result {
+        eprintln_ignore_io_error!("cvtsudoers: {error}");
+        std::process::exit(1);
+    }
+}
+
+/// A user that does not exist on this system can still be matched by name
+#[derive(PartialEq)]
+struct UnknownUser(String);
+
+impl UnixUser for UnknownUser {
+    fn has_name(&self, name: &str) -> bool {
+        self.0 == name
+    }
+}
+
+fn convert(options: &CvtsudoersOptions) -> io::Result<()> {
+    let sudoers_path = Path::new(options.input.as_deref().unwrap_or("/etc/sudoers"));
+
+    let (mut sudoers, errors) = Sudoers::open(sudoers_path)
+        .map_err(|err| io_msg!(err, "unable to open {}", sudoers_path.display()))?;
+
+    if !errors.is_empty() {
+        for crate::sudoers::Error {
+            message,
+            source,
+            location,
+        } in errors
+        {
+            let path = source.as_deref().unwrap_or(sudoers_path);
+            diagnostic::diagnostic!("syntax error: {message}", path @ location);
+        }
+
+        return Err(io::Error::new(io::ErrorKind::Other, "invalid sudoers file"));
+    }
+
+    let host = options.host.as_deref().map(Hostname::from_name);
+    match &options.user {
+        Some(name) => {
+            let to_io = |err: Error| io::Error::new(io::ErrorKind::Other, err.to_string());
+            let name = SudoString::new(name.clone()).map_err(to_io)?;
+            match User::from_name(name.as_cstr()).map_err(to_io)? {
+                Some(user) => sudoers.retain_matching(Some(&user), host.as_ref()),
+                None => sudoers.retain_matching(Some(&UnknownUser(name.into())), host.as_ref()),
+            }
+        }
+        None => sudoers.retain_matching(None::<&User>, host.as_ref()),
+    }
+
+    if options.expand_aliases {
+        sudoers.expand_aliases();
+    }
+
+    let converted = match options.format {
+        OutputFormat::Sudoers => sudoers.to_sudoers(),
+        OutputFormat::Json => format!("{:#}\n", sudoers.to_json()),
+    };
+
+    match options.output.as_deref() {
+        None | Some("-") => io::stdout().write_all(converted.as_bytes()),
+        Some(output) => {
+            fs::write(output, converted).map_err(|err| io_msg!(err, "unable to write {output}"))
+        }
+    }
+}
@@ -25,18 +25,36 @@ use settings_dsl::*;
 defaults! {
     always_query_group_plugin = false
     always_set_home           = false
+    closefrom_override        = false
     env_reset                 = true
-    mail_badpass              = true
+    log_input                 = false
+    log_output                = false
+    mail_badpass              = false
+    mail_no_perms             = false
+    mail_no_user              = true
     match_group_by_gid        = false
+    noexec                    = false
+    passprompt_override       = false
+    pwfeedback                = false
     use_pty                   = true
     visiblepw                 = false
     env_editor                = true
+    setenv                    = false
 
     passwd_tries              = 3 [0..=1000]
 
     secure_path               = None (!= None)
+    group_file                = None (!= None)
+    passprompt                = None (!= None)
+    iolog_dir                 = "/var/log/sudo-io"
+    mailerflags               = "-t"
+    mailerpath                = "/usr/sbin/sendmail"
+    mailto                    = "root"
     verifypw                  = "all" (!= "never") [all, always, any, never]
+    listpw                    = "any" (!= "never") [all, always, any, never]
+    timestamp_type            = "tty" [global, ppid, tty, kernel]
 
+    passwd_timeout            = (5*60) (!= 0) {fractional_minutes}
     timestamp_timeout         = (15*60) (!= 0) {fractional_minutes}
 
     env_keep                  = ["COLORS", "DISPLAY", "HOSTNAME", "KRB5CCNAME", "LS_COLORS", "PATH",
@@ -89,17 +107,34 @@ mod test {
 
         test! { always_query_group_plugin => Flag(false) };
         test! { always_set_home => Flag(false) };
+        test! { closefrom_override => Flag(false) };
         test! { env_reset => Flag(true) };
-        test! { mail_badpass => Flag(true) };
+        test! { log_input => Flag(false) };
+        test! { log_output => Flag(false) };
+        test! { mail_badpass => Flag(false) };
+        test! { mail_no_perms => Flag(false) };
+        test! { mail_no_user => Flag(true) };
         test! { match_group_by_gid => Flag(false) };
+        test! { noexec => Flag(false) };
+        test! { passprompt_override => Flag(false) };
+        test! { pwfeedback => Flag(false) };
         test! { use_pty => Flag(true) };
         test! { visiblepw => Flag(false) };
         test! { env_editor => Flag(true) };
+        test! { setenv => Flag(false) };
         test! { passwd_tries => Integer(OptTuple { default: 3, negated: None }, _) };
+        test! { passwd_timeout => Integer(OptTuple { default: 300, negated: Some(0) }, _) };
         test! { secure_path => Text(OptTuple { default: None, negated: Some(None) }) };
+        test! { group_file => Text(OptTuple { default: None, negated: Some(None) }) };
+        test! { passprompt => Text(OptTuple { default: None, negated: Some(None) }) };
+        test! { iolog_dir => Text(OptTuple { default: Some("/var/log/sudo-io"), negated: None }) };
+        test! { mailerpath => Text(OptTuple { default: Some("/usr/sbin/sendmail"), negated: None }) };
+        test! { mailto => Text(OptTuple { default: Some("root"), negated: None }) };
         test! { env_keep => List(_) };
         test! { env_check => List(["COLORTERM", "LANG", "LANGUAGE", "LC_*", "LINGUAS", "TERM", "TZ"]) };
         test! { env_delete => List(_) };
+        test! { listpw => Enum(OptTuple { default: StrEnum { value: "any", possible_values: [_, "always", "any", _] }, negated: Some(StrEnum { value: "never", .. }) }) };
+        test! { timestamp_type => Enum(OptTuple { default: StrEnum { value: "tty", possible_values: [_, "ppid", "tty", _] }, negated: None }) };
         test! { verifypw => Enum(OptTuple { default: StrEnum { value: "all", possible_values: [_, "always", "any", _] }, negated: Some(StrEnum { value: "never", .. }) }) };
 
         let myenum = StrEnum::new("hello", &["hello", "goodbye"]).unwrap();
@@ -4,7 +4,7 @@ use std::{
     os::fd::{AsRawFd, RawFd},
 };
 
-use libc::{c_short, pollfd, POLLIN, POLLOUT};
+use libc::{c_short, pollfd, POLLERR, POLLHUP, POLLIN, POLLOUT};
 
 use crate::common::{HARDENED_ENUM_VALUE_0, HARDENED_ENUM_VALUE_1};
 use crate::{cutils::cerr, log::dev_debug};
@@ -181,10 +181,15 @@ impl<T: Process> EventRegistry<T> {
         // FIXME: we should set either a timeout or use ppoll when available.
         cerr(unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) })?;
 
-        // Remove the ids that correspond to file descriptors that were not ready.
+        // Remove the ids that correspond to file descriptors that were not ready. A hang up or an
+        // error is reported regardless of the requested events; those are ready as well, as the
+        // next read or write will return immediately.
         for (i, fd) in fds.iter().enumerate().rev() {
             let events = fd.events & fd.revents;
-            if !((events & POLLIN != 0) || (events & POLLOUT != 0)) {
+            if !((events & POLLIN != 0)
+                || (events & POLLOUT != 0)
+                || (fd.revents & (POLLHUP | POLLERR) != 0))
+            {
                 ids.remove(i);
             }
         }
@@ -2,13 +2,15 @@ use std::io::{self, ErrorKind};
 use std::path::PathBuf;
 
 use crate::common::SudoPath;
+use crate::iolog::IoLogOptions;
 use crate::{
     common::{context::LaunchType, Context},
-    system::{Group, User},
+    system::{file::Executable, Group, User},
 };
 
 pub trait RunOptions {
     fn command(&self) -> io::Result<&PathBuf>;
+    fn executable(&self) -> Option<&Executable>;
     fn arguments(&self) -> &Vec<String>;
     fn arg0(&self) -> Option<&PathBuf>;
     fn chdir(&self) -> Option<&SudoPath>;
@@ -18,6 +20,10 @@ pub trait RunOptions {
     fn group(&self) -> &Group;
     fn pid(&self) -> i32;
     fn use_pty(&self) -> bool;
+    fn iolog(&self) -> Option<&IoLogOptions>;
+    fn noexec(&self) -> bool;
+    fn background(&self) -> bool;
+    fn close_from(&self) -> Option<i32>;
 }
 
 impl RunOptions for Context {
@@ -29,6 +35,10 @@ impl RunOptions for Context {
         }
     }
 
+    fn executable(&self) -> Option<&Executable> {
+        self.executable.get()?.as_ref()
+    }
+
     fn arguments(&self) -> &Vec<String> {
         &self.command.arguments
     }
@@ -64,4 +74,20 @@ impl RunOptions for Context {
     fn use_pty(&self) -> bool {
         self.use_pty
     }
+
+    fn iolog(&self) -> Option<&IoLogOptions> {
+        self.iolog.as_ref()
+    }
+
+    fn noexec(&self) -> bool {
+        self.noexec
+    }
+
+    fn background(&self) -> bool {
+        self.background
+    }
+
+    fn close_from(&self) -> Option<i32> {
+        self.close_from
+    }
 }
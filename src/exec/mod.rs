@@ -2,34 +2,46 @@ mod event;
 mod interface;
 mod io_util;
 mod no_pty;
+mod noexec;
+mod pipe;
 mod use_pty;
 
 use std::{
     borrow::Cow,
+    cell::RefCell,
     env,
-    ffi::{c_int, OsStr},
+    ffi::{c_int, c_uint, OsStr},
     io,
     os::unix::ffi::OsStrExt,
     os::unix::process::CommandExt,
-    process::Command,
-    time::Duration,
+    path::Path,
+    process::{Command, Stdio},
+    rc::Rc,
+    time::{Duration, SystemTime},
 };
 
 use crate::{
     common::Environment,
-    log::dev_warn,
+    exec::pipe::SharedLog,
+    iolog::{IoLogOptions, IoLogWriter, SessionInfo},
+    log::{dev_error, dev_warn},
     system::{
-        _exit,
+        _exit, fork,
         interface::ProcessId,
-        killpg,
+        killpg, setpgid,
         signal::{consts::*, signal_name},
         wait::{Wait, WaitError, WaitOptions},
+        FileCloser, ForkResult, Process as SystemProcess,
     },
 };
 use crate::{
     exec::no_pty::exec_no_pty,
     log::dev_info,
-    system::{set_target_user, signal::SignalNumber, term::UserTerm},
+    system::{
+        set_target_user,
+        signal::SignalNumber,
+        term::{current_tty_name, UserTerm},
+    },
 };
 use crate::{log::user_error, system::kill};
 
@@ -38,6 +50,7 @@ pub use interface::RunOptions;
 use self::{
     event::{EventRegistry, Process},
     io_util::was_interrupted,
+    noexec::add_noexec_filter,
     use_pty::{exec_pty, SIGCONT_BG, SIGCONT_FG},
 };
 
@@ -56,7 +69,19 @@ pub fn run_command(options: &impl RunOptions, env: Environment) -> io::Result<Ex
 fn run_command_internal(options: &impl RunOptions, env: Environment) -> io::Result<ProcessOutput> {
     // FIXME: should we pipe the stdio streams?
     let qualified_path = options.command()?;
-    let mut command = Command::new(qualified_path);
+    let mut file_closer = FileCloser::new();
+    let mut command = match options.executable() {
+        // if the policy has inspected the contents of the executable, make sure that it is
+        // exactly that file which gets executed
+        #[cfg(target_os = "linux")]
+        Some(executable) if executable.is_digested() => {
+            file_closer.except(executable);
+            let mut command = executable.command();
+            command.arg0(qualified_path);
+            command
+        }
+        _ => Command::new(qualified_path),
+    };
     // reset env and set filtered environment
     command.args(options.arguments()).env_clear().envs(env);
     // set the arg0 to the requested string
@@ -106,17 +131,96 @@ fn run_command_internal(options: &impl RunOptions, env: Environment) -> io::Resu
         }
     }
 
-    if options.use_pty() {
+    // Prevent the command from executing other programs.
+    let noexec = if options.noexec() {
+        Some(add_noexec_filter(&mut command, &mut file_closer)?)
+    } else {
+        None
+    };
+
+    if let Some(fd) = options.close_from() {
+        file_closer.close_from(fd as c_uint);
+    }
+
+    // The I/O log records the terminal of the session as seen through a pty, so logging implies
+    // `use_pty`; if there is no terminal only the IO streams are recorded.
+    let iolog = options
+        .iolog()
+        .map(|iolog_options| (iolog_options, session_info(options, qualified_path)));
+
+    if options.background() {
+        // A command in the background has no business with the terminal, so it gets no pty and
+        // cannot read from it either.
+        command.stdin(Stdio::null());
+
+        // Based on `ogsudo`s handling of `CD_BACKGROUND` in `sudo_execute`: the parent exits
+        // right away and the child carries on in a process group of its own.
+        match fork()? {
+            ForkResult::Parent(_) => _exit(0),
+            ForkResult::Child => {
+                if let Err(err) = setpgid(0, 0) {
+                    dev_warn!("cannot create a new process group: {err}");
+                }
+            }
+        }
+
+        exec_no_pty(
+            SystemProcess::process_id(),
+            command,
+            file_closer,
+            iolog,
+            noexec,
+        )
+    } else if options.use_pty() || iolog.is_some() {
         match UserTerm::open() {
-            Ok(user_tty) => exec_pty(options.pid(), command, user_tty),
+            Ok(user_tty) => exec_pty(options.pid(), command, user_tty, file_closer, iolog, noexec),
             Err(err) => {
                 dev_info!("Could not open user's terminal, not allocating a pty: {err}");
-                exec_no_pty(options.pid(), command)
+                exec_no_pty(options.pid(), command, file_closer, iolog, noexec)
             }
         }
     } else {
-        exec_no_pty(options.pid(), command)
+        exec_no_pty(options.pid(), command, file_closer, None, noexec)
+    }
+}
+
+/// Describe the session of the command for the I/O log; the terminal size is filled in once the
+/// terminal has been opened (if there is one).
+fn session_info(options: &impl RunOptions, qualified_path: &Path) -> SessionInfo {
+    let mut command = qualified_path.display().to_string();
+    for arg in options.arguments() {
+        command.push(' ');
+        command.push_str(arg);
     }
+
+    SessionInfo {
+        start_time: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs() as i64),
+        user: options.requesting_user().name.to_string(),
+        runas_user: options.user().name.to_string(),
+        runas_group: options.group().name.to_string(),
+        tty: current_tty_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "unknown".to_string()),
+        rows: 0,
+        cols: 0,
+        cwd: env::current_dir()
+            .map(|cwd| cwd.display().to_string())
+            .unwrap_or_else(|_| "unknown".to_string()),
+        command,
+    }
+}
+
+/// Start recording the session in the I/O log.
+fn start_iolog(options: &IoLogOptions, info: &SessionInfo) -> io::Result<SharedLog> {
+    let log = IoLogWriter::create(options, info).map_err(|err| {
+        dev_error!("cannot create I/O log in {}: {err}", options.dir.display());
+        io::Error::new(err.kind(), format!("unable to create I/O log: {err}"))
+    })?;
+    dev_info!("recording session {} in the I/O log", log.id());
+
+    Ok(Rc::new(RefCell::new(log)))
 }
 
 /// The output of a command's execution.
@@ -4,10 +4,13 @@ use super::{
     event::PollEvent,
     event::{EventRegistry, Process, StopReason},
     io_util::was_interrupted,
-    terminate_process, ExitReason, HandleSigchld, ProcessOutput,
+    noexec::NoexecHandler,
+    pipe::StdioRelays,
+    start_iolog, terminate_process, ExitReason, HandleSigchld, ProcessOutput,
 };
 use crate::{
     common::bin_serde::BinPipe,
+    iolog::{IoLogOptions, IoStream, SessionInfo},
     system::signal::{
         consts::*, register_handlers, SignalHandler, SignalHandlerBehavior, SignalNumber,
         SignalSet, SignalStream,
@@ -26,7 +29,15 @@ use crate::{
     },
 };
 
-pub(super) fn exec_no_pty(sudo_pid: ProcessId, mut command: Command) -> io::Result<ProcessOutput> {
+/// Run `command`; the file descriptors which are not marked as exceptions in `file_closer` are
+/// closed before it is executed.
+pub(super) fn exec_no_pty(
+    sudo_pid: ProcessId,
+    mut command: Command,
+    mut file_closer: FileCloser,
+    iolog: Option<(&IoLogOptions, SessionInfo)>,
+    noexec: Option<NoexecHandler>,
+) -> io::Result<ProcessOutput> {
     // FIXME (ogsudo): Initialize the policy plugin's session here.
 
     // Block all the signals until we are done setting up the signal handlers so we don't miss
@@ -39,8 +50,6 @@ pub(super) fn exec_no_pty(sudo_pid: ProcessId, mut command: Command) -> io::Resu
         }
     };
 
-    let mut file_closer = FileCloser::new();
-
     // FIXME (ogsudo): Some extra config happens here if selinux is available.
 
     // Use a pipe to get the IO error if `exec` fails.
@@ -50,11 +59,32 @@ pub(super) fn exec_no_pty(sudo_pid: ProcessId, mut command: Command) -> io::Resu
     // fails.
     file_closer.except(&errpipe_tx);
 
+    let mut registry = EventRegistry::new();
+
+    // Relay the IO streams to the command through pipes if they have to be recorded.
+    let mut stdio_relays = StdioRelays::default();
+    if let Some((options, info)) = iolog {
+        let log = start_iolog(options, &info)?;
+        for stream in [IoStream::Stdin, IoStream::Stdout, IoStream::Stderr] {
+            if options.records(stream) {
+                stdio_relays.relay(
+                    stream,
+                    &log,
+                    &mut command,
+                    &mut file_closer,
+                    &mut registry,
+                    move |poll_event| ExecEvent::Stdio(stream, poll_event),
+                )?;
+            }
+        }
+    }
+
     let ForkResult::Parent(command_pid) = fork().map_err(|err| {
         dev_warn!("unable to fork command process: {err}");
         err
     })?
     else {
+        drop(noexec);
         file_closer.close_the_universe()?;
 
         // Restore the signal mask now that the handlers have been setup.
@@ -78,9 +108,20 @@ pub(super) fn exec_no_pty(sudo_pid: ProcessId, mut command: Command) -> io::Resu
 
     dev_info!("executed command with pid {command_pid}");
 
-    let mut registry = EventRegistry::new();
+    if let Some(noexec) = noexec {
+        noexec.spawn();
+    }
 
-    let mut closure = ExecClosure::new(command_pid, sudo_pid, errpipe_rx, &mut registry)?;
+    // Close the ends of the pipes that belong to the command.
+    drop(command);
+
+    let mut closure = ExecClosure::new(
+        command_pid,
+        sudo_pid,
+        errpipe_rx,
+        stdio_relays,
+        &mut registry,
+    )?;
 
     // Restore the signal mask now that the handlers have been setup.
     if let Some(set) = original_set {
@@ -94,6 +135,9 @@ pub(super) fn exec_no_pty(sudo_pid: ProcessId, mut command: Command) -> io::Resu
         StopReason::Exit(reason) => reason,
     };
 
+    // Pass on the output that the command wrote just before exiting.
+    closure.stdio_relays.drain();
+
     Ok(ProcessOutput::SudoExit {
         output: crate::exec::ExecOutput {
             command_exit_reason,
@@ -107,6 +151,7 @@ struct ExecClosure {
     sudo_pid: ProcessId,
     parent_pgrp: ProcessId,
     errpipe_rx: BinPipe<i32>,
+    stdio_relays: StdioRelays,
     signal_stream: &'static SignalStream,
     signal_handlers: [SignalHandler; ExecClosure::SIGNALS.len()],
 }
@@ -121,6 +166,7 @@ impl ExecClosure {
         command_pid: ProcessId,
         sudo_pid: ProcessId,
         errpipe_rx: BinPipe<i32>,
+        stdio_relays: StdioRelays,
         registry: &mut EventRegistry<Self>,
     ) -> io::Result<Self> {
         registry.register_event(&errpipe_rx, PollEvent::Readable, |_| ExecEvent::ErrPipe);
@@ -134,6 +180,7 @@ impl ExecClosure {
         Ok(Self {
             command_pid: Some(command_pid),
             errpipe_rx,
+            stdio_relays,
             sudo_pid,
             parent_pgrp: getpgrp(),
             signal_stream,
@@ -274,6 +321,7 @@ impl ExecClosure {
 enum ExecEvent {
     Signal,
     ErrPipe,
+    Stdio(IoStream, PollEvent),
 }
 
 impl Process for ExecClosure {
@@ -284,6 +332,9 @@ impl Process for ExecClosure {
     fn on_event(&mut self, event: Self::Event, registry: &mut EventRegistry<Self>) {
         match event {
             ExecEvent::Signal => self.on_signal(registry),
+            ExecEvent::Stdio(stream, poll_event) => {
+                self.stdio_relays.on_event(stream, poll_event, registry);
+            }
             ExecEvent::ErrPipe => {
                 match self.errpipe_rx.read() {
                     Err(err) if was_interrupted(&err) => { /* Retry later */ }
@@ -0,0 +1,98 @@
+use std::{
+    io,
+    os::{fd::AsFd, unix::net::UnixStream, unix::process::CommandExt},
+    process::Command,
+    thread,
+};
+
+use crate::{
+    log::{dev_info, dev_warn},
+    system::{
+        receive_fd,
+        seccomp::{install_exec_filter, receive_exec_request, respond_to_exec_request},
+        send_fd,
+        signal::SignalSet,
+        FileCloser,
+    },
+};
+
+/// Answers the requests of a command to execute other programs, which are all denied except for
+/// the execution of the command itself.
+pub(super) struct NoexecHandler {
+    socket: UnixStream,
+}
+
+/// Make `command` install a seccomp filter right before it is executed, so that any attempt of it
+/// to execute another program fails with `EACCES`. The returned handler has to be spawned in the
+/// parent process once the command process has been forked.
+pub(super) fn add_noexec_filter(
+    command: &mut Command,
+    file_closer: &mut FileCloser,
+) -> io::Result<NoexecHandler> {
+    let (socket, command_socket) = UnixStream::pair()?;
+    // The command process sends the file descriptor of the filter over this socket, after the
+    // other file descriptors have been closed.
+    file_closer.except(&command_socket);
+
+    // SAFETY: the closure only installs the filter and sends a file descriptor to the parent.
+    unsafe {
+        command.pre_exec(move || {
+            let listener = install_exec_filter()?;
+            send_fd(&command_socket, listener.as_fd())
+        });
+    }
+
+    Ok(NoexecHandler { socket })
+}
+
+impl NoexecHandler {
+    /// Start answering requests in a separate thread. This must be done after the last `fork`.
+    pub(super) fn spawn(self) {
+        thread::spawn(move || {
+            if let Err(err) = self.handle_requests() {
+                dev_warn!("noexec: stopped handling requests: {err}");
+            }
+        });
+    }
+
+    fn handle_requests(self) -> io::Result<()> {
+        // Signals are handled by the main thread.
+        SignalSet::full()?.block()?;
+
+        let listener = receive_fd(&self.socket)?;
+        drop(self.socket);
+
+        // The first request comes from the command process executing the command itself. It is
+        // let through with `SECCOMP_USER_NOTIF_FLAG_CONTINUE`, which the kernel does not consider
+        // a security boundary: that is acceptable only because no code of the command has run at
+        // that point. Every later request fails with EACCES, which the kernel enforces.
+        let mut is_command = true;
+        loop {
+            let id = match receive_exec_request(&listener) {
+                Ok(id) => id,
+                // The process making the request was interrupted or has exited already.
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::Interrupted | io::ErrorKind::NotFound
+                    ) =>
+                {
+                    continue
+                }
+                Err(err) => return Err(err),
+            };
+
+            if !is_command {
+                dev_info!("noexec: denying a request to execute a program");
+            }
+
+            let result = respond_to_exec_request(&listener, id, is_command);
+            is_command = false;
+            match result {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
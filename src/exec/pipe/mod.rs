@@ -0,0 +1,469 @@
+mod ring_buffer;
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, IoSliceMut, Read, Write},
+    marker::PhantomData,
+    os::fd::{AsFd, AsRawFd},
+    process::Command,
+    rc::Rc,
+};
+
+use crate::exec::event::{EventHandle, EventRegistry, PollEvent, Process};
+use crate::exec::io_util::was_interrupted;
+use crate::iolog::{IoLogWriter, IoStream};
+use crate::log::{dev_error, dev_warn};
+use crate::system::{pipe, set_nonblocking, FileCloser};
+
+use self::ring_buffer::RingBuffer;
+
+/// An I/O log shared by all the pipes of a session.
+pub(super) type SharedLog = Rc<RefCell<IoLogWriter>>;
+
+// A pipe able to stream data bidirectionally between two read-write types.
+pub(super) struct Pipe<L, R> {
+    left: L,
+    right: R,
+    buffer_lr: Buffer<L, R>,
+    buffer_rl: Buffer<R, L>,
+}
+
+impl<L: Read + Write + AsRawFd, R: Read + Write + AsRawFd> Pipe<L, R> {
+    /// Create a new pipe between two read-write types and register them to be polled.
+    pub fn new<T: Process>(
+        left: L,
+        right: R,
+        registry: &mut EventRegistry<T>,
+        f_left: fn(PollEvent) -> T::Event,
+        f_right: fn(PollEvent) -> T::Event,
+    ) -> Self {
+        Self {
+            buffer_lr: Buffer::new(
+                registry.register_event(&left, PollEvent::Readable, f_left),
+                registry.register_event(&right, PollEvent::Writable, f_right),
+                registry,
+            ),
+            buffer_rl: Buffer::new(
+                registry.register_event(&right, PollEvent::Readable, f_right),
+                registry.register_event(&left, PollEvent::Writable, f_left),
+                registry,
+            ),
+            left,
+            right,
+        }
+    }
+
+    /// Get a reference to the left side of the pipe.
+    pub(super) fn left(&self) -> &L {
+        &self.left
+    }
+
+    /// Get a mutable reference to the left side of the pipe.
+    pub(super) fn left_mut(&mut self) -> &mut L {
+        &mut self.left
+    }
+
+    /// Get a reference to the right side of the pipe.
+    pub(super) fn right(&self) -> &R {
+        &self.right
+    }
+
+    /// Record the bytes read from the left and right side of the pipe in `log`, as the `left` and
+    /// `right` stream respectively.
+    pub(super) fn record_in(&mut self, log: &SharedLog, left: IoStream, right: IoStream) {
+        self.buffer_lr.log = Some((log.clone(), left));
+        self.buffer_rl.log = Some((log.clone(), right));
+    }
+
+    /// Stop the poll events of this pipe.
+    pub(super) fn ignore_events<T: Process>(&mut self, registry: &mut EventRegistry<T>) {
+        self.buffer_lr.read_handle.ignore(registry);
+        self.buffer_lr.write_handle.ignore(registry);
+        self.buffer_rl.read_handle.ignore(registry);
+        self.buffer_rl.write_handle.ignore(registry);
+    }
+
+    /// Resume the poll events of this pipe
+    pub(super) fn resume_events<T: Process>(&mut self, registry: &mut EventRegistry<T>) {
+        self.buffer_lr.read_handle.resume(registry);
+        self.buffer_lr.write_handle.resume(registry);
+        self.buffer_rl.read_handle.resume(registry);
+        self.buffer_rl.write_handle.resume(registry);
+    }
+
+    /// Handle a poll event for the left side of the pipe.
+    pub(super) fn on_left_event<T: Process>(
+        &mut self,
+        poll_event: PollEvent,
+        registry: &mut EventRegistry<T>,
+    ) -> io::Result<()> {
+        match poll_event {
+            PollEvent::Readable => self.buffer_lr.read(&mut self.left, registry).map(|_| ()),
+            PollEvent::Writable => self.buffer_rl.write(&mut self.left, registry),
+        }
+    }
+
+    /// Handle a poll event for the right side of the pipe.
+    pub(super) fn on_right_event<T: Process>(
+        &mut self,
+        poll_event: PollEvent,
+        registry: &mut EventRegistry<T>,
+    ) -> io::Result<()> {
+        match poll_event {
+            PollEvent::Readable => self.buffer_rl.read(&mut self.right, registry).map(|_| ()),
+            PollEvent::Writable => self.buffer_lr.write(&mut self.right, registry),
+        }
+    }
+
+    /// Ensure that all the contents of the pipe's internal buffer are written to the left side.
+    pub(super) fn flush_left(&mut self) -> io::Result<()> {
+        self.buffer_rl.flush(&mut self.left)
+    }
+}
+
+/// A pipe that streams data in one direction, from a reader to a writer. The writer is closed
+/// once the reader reaches its end and all the data has been written.
+struct Relay<R, W> {
+    read: R,
+    write: Option<W>,
+    buffer: Buffer<R, W>,
+    eof: bool,
+}
+
+impl<R: Read + AsRawFd, W: Write + AsRawFd> Relay<R, W> {
+    /// Create a new relay between a reader and a writer and register them to be polled.
+    fn new<T: Process>(
+        read: R,
+        write: W,
+        registry: &mut EventRegistry<T>,
+        f: impl Fn(PollEvent) -> T::Event + Copy,
+    ) -> Self {
+        Self {
+            buffer: Buffer::new(
+                registry.register_event(&read, PollEvent::Readable, f),
+                registry.register_event(&write, PollEvent::Writable, f),
+                registry,
+            ),
+            read,
+            write: Some(write),
+            eof: false,
+        }
+    }
+
+    /// Record the bytes read from the reader in `log` as the `stream` stream.
+    fn record_in(&mut self, log: &SharedLog, stream: IoStream) {
+        self.buffer.log = Some((log.clone(), stream));
+    }
+
+    /// Handle a poll event for either side of the relay.
+    fn on_event<T: Process>(
+        &mut self,
+        poll_event: PollEvent,
+        registry: &mut EventRegistry<T>,
+    ) -> io::Result<()> {
+        match poll_event {
+            PollEvent::Readable => {
+                let was_full = self.buffer.internal.is_full();
+                match self.buffer.read(&mut self.read, registry) {
+                    // Reading nothing into a buffer with free space means that we reached the end.
+                    Ok(0) if !was_full => self.stop_reading(registry),
+                    Ok(_) => {}
+                    Err(err) if was_interrupted(&err) => {}
+                    Err(err) => {
+                        self.stop_reading(registry);
+                        return Err(err);
+                    }
+                }
+            }
+            PollEvent::Writable => {
+                if let Some(write) = &mut self.write {
+                    if let Err(err) = self.buffer.write(write, registry) {
+                        // The data cannot be written anymore (e.g. because the reading end of a
+                        // pipe was closed), so there's no point in reading more of it.
+                        if !was_interrupted(&err) {
+                            self.stop_reading(registry);
+                            self.close(registry);
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        if self.eof && self.buffer.internal.is_empty() {
+            self.close(registry);
+        }
+
+        Ok(())
+    }
+
+    fn stop_reading<T: Process>(&mut self, registry: &mut EventRegistry<T>) {
+        self.eof = true;
+        self.buffer.read_handle.ignore(registry);
+    }
+
+    /// Close the writer, which signals the end of the data to its reader.
+    fn close<T: Process>(&mut self, registry: &mut EventRegistry<T>) {
+        if self.write.take().is_some() {
+            self.buffer.write_handle.ignore(registry);
+        }
+    }
+
+    /// Move all the data that can be read without blocking to the writer.
+    fn drain(&mut self) -> io::Result<()> {
+        match &mut self.write {
+            Some(write) => self.buffer.drain(&mut self.read, write),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The pipes that relay the IO streams of sudo to and from the command, so that they can be
+/// recorded in the I/O log.
+#[derive(Default)]
+pub(super) struct StdioRelays {
+    stdin: Option<Relay<File, File>>,
+    stdout: Option<Relay<File, File>>,
+    stderr: Option<Relay<File, File>>,
+}
+
+impl StdioRelays {
+    /// Connect `command` to one of the IO streams of sudo through a pipe, recording the data that
+    /// passes through it in `log`.
+    pub(super) fn relay<T: Process>(
+        &mut self,
+        stream: IoStream,
+        log: &SharedLog,
+        command: &mut Command,
+        file_closer: &mut FileCloser,
+        registry: &mut EventRegistry<T>,
+        event_fn: impl Fn(PollEvent) -> T::Event + Copy,
+    ) -> io::Result<()> {
+        let sudo_fd = match stream {
+            IoStream::Stdin => io::stdin().as_fd().try_clone_to_owned()?,
+            IoStream::Stdout => io::stdout().as_fd().try_clone_to_owned()?,
+            IoStream::Stderr => io::stderr().as_fd().try_clone_to_owned()?,
+            IoStream::TtyIn | IoStream::TtyOut => return Err(io::ErrorKind::InvalidInput.into()),
+        };
+        let sudo_end = File::from(sudo_fd);
+
+        let (rx, tx) = pipe().map_err(|err| {
+            dev_error!("cannot create pipe for {}: {err}", stream.file_name());
+            err
+        })?;
+
+        // Only the end of the pipe that belongs to sudo is non-blocking, as the command might not
+        // expect that.
+        let (mut relay, command_end) = if stream.is_input() {
+            set_nonblocking(&tx)?;
+            (Relay::new(sudo_end, tx, registry, event_fn), rx)
+        } else {
+            set_nonblocking(&rx)?;
+            (Relay::new(rx, sudo_end, registry, event_fn), tx)
+        };
+        relay.record_in(log, stream);
+        // Don't close this as it will be dupped inside `Command::exec`.
+        file_closer.except(&command_end);
+
+        match stream {
+            IoStream::Stdin => command.stdin(command_end),
+            IoStream::Stdout => command.stdout(command_end),
+            _ => command.stderr(command_end),
+        };
+        *self.get_mut(stream) = Some(relay);
+
+        Ok(())
+    }
+
+    fn get_mut(&mut self, stream: IoStream) -> &mut Option<Relay<File, File>> {
+        match stream {
+            IoStream::Stdin => &mut self.stdin,
+            IoStream::Stdout => &mut self.stdout,
+            _ => &mut self.stderr,
+        }
+    }
+
+    /// Handle a poll event for the relay of `stream`.
+    pub(super) fn on_event<T: Process>(
+        &mut self,
+        stream: IoStream,
+        poll_event: PollEvent,
+        registry: &mut EventRegistry<T>,
+    ) {
+        if let Some(relay) = self.get_mut(stream) {
+            relay.on_event(poll_event, registry).ok();
+        }
+    }
+
+    /// Pass on the output of the command that is still in the pipes.
+    pub(super) fn drain(&mut self) {
+        for relay in [&mut self.stdout, &mut self.stderr].into_iter().flatten() {
+            if let Err(err) = relay.drain() {
+                dev_warn!("cannot relay output of command: {err}");
+            }
+        }
+    }
+}
+
+/// A buffer that stores the bytes read from `R` before they are written to `W`.
+struct Buffer<R, W> {
+    internal: RingBuffer,
+    /// The handle for the event of the reader.
+    read_handle: EventHandle,
+    /// The handle for the event of the writer.
+    write_handle: EventHandle,
+    /// The I/O log in which the bytes that are read are recorded, if any.
+    log: Option<(SharedLog, IoStream)>,
+    marker: PhantomData<(R, W)>,
+}
+
+impl<R: Read, W: Write> Buffer<R, W> {
+    /// Create a new, empty buffer
+    fn new<T: Process>(
+        read_handle: EventHandle,
+        mut write_handle: EventHandle,
+        registry: &mut EventRegistry<T>,
+    ) -> Self {
+        // The buffer is empty, don't write
+        write_handle.ignore(registry);
+
+        Self {
+            internal: RingBuffer::new(),
+            read_handle,
+            write_handle,
+            log: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Read bytes into the buffer, recording them if necessary.
+    fn insert(&mut self, read: &mut R) -> io::Result<usize> {
+        match &self.log {
+            Some((log, stream)) => self.internal.insert(&mut Recorder {
+                read,
+                log,
+                stream: *stream,
+            }),
+            None => self.internal.insert(read),
+        }
+    }
+
+    /// Read bytes into the buffer, returning how many bytes were read.
+    ///
+    /// Calling this function will block until `read` is ready to be read.
+    fn read<T: Process>(
+        &mut self,
+        read: &mut R,
+        registry: &mut EventRegistry<T>,
+    ) -> io::Result<usize> {
+        // If the buffer is full, there is nothing to be read.
+        if self.internal.is_full() {
+            self.read_handle.ignore(registry);
+            return Ok(0);
+        }
+
+        // Read bytes and insert them into the buffer.
+        let inserted_len = self.insert(read)?;
+
+        // If we inserted something, the buffer is not empty anymore and we can resume writing.
+        if inserted_len > 0 {
+            self.write_handle.resume(registry);
+        }
+
+        Ok(inserted_len)
+    }
+
+    /// Write bytes from the buffer.
+    ///
+    /// Calling this function will block until `write` is ready to be written.
+    fn write<T: Process>(
+        &mut self,
+        write: &mut W,
+        registry: &mut EventRegistry<T>,
+    ) -> io::Result<()> {
+        // If the buffer is empty, there is nothing to be written.
+        if self.internal.is_empty() {
+            self.write_handle.ignore(registry);
+            return Ok(());
+        }
+
+        // Remove bytes from the buffer and write them.
+        let removed_len = self.internal.remove(write)?;
+
+        // If we removed something, the buffer is not full anymore and we can resume reading.
+        if removed_len > 0 {
+            self.read_handle.resume(registry);
+        }
+
+        Ok(())
+    }
+
+    /// Flush this buffer, ensuring that all the contents of its internal buffer are written.
+    fn flush(&mut self, write: &mut W) -> io::Result<()> {
+        // Remove bytes from the buffer and write them.
+        self.internal.remove(write)?;
+
+        write.flush()
+    }
+
+    /// Move bytes from `read` to `write` until `read` has no more bytes available (either because
+    /// it reached its end or because reading from it would block).
+    fn drain(&mut self, read: &mut R, write: &mut W) -> io::Result<()> {
+        loop {
+            while !self.internal.is_empty() {
+                if self.internal.remove(write)? == 0 {
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+            }
+
+            match self.insert(read) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        write.flush()
+    }
+}
+
+/// A reader that records all the bytes read from it in an I/O log.
+struct Recorder<'a, R> {
+    read: &'a mut R,
+    log: &'a SharedLog,
+    stream: IoStream,
+}
+
+impl<R> Recorder<'_, R> {
+    fn record(&self, bytes: &[u8]) {
+        if let Err(err) = self.log.borrow_mut().record(self.stream, bytes) {
+            dev_warn!("cannot write to I/O log: {err}");
+        }
+    }
+}
+
+impl<R: Read> Read for Recorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.read.read(buf)?;
+        self.record(&buf[..len]);
+        Ok(len)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        let len = self.read.read_vectored(bufs)?;
+
+        let mut remaining = len;
+        for buf in bufs.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let filled = remaining.min(buf.len());
+            self.record(&buf[..filled]);
+            remaining -= filled;
+        }
+
+        Ok(len)
+    }
+}
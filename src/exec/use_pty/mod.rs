@@ -1,7 +1,6 @@
 mod backchannel;
 mod monitor;
 mod parent;
-mod pipe;
 
 use std::ffi::c_int;
 
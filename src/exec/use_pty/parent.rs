@@ -7,14 +7,15 @@ use crate::exec::event::{EventHandle, EventRegistry, PollEvent, Process, StopRea
 use crate::exec::use_pty::monitor::exec_monitor;
 use crate::exec::use_pty::SIGCONT_FG;
 use crate::exec::{
-    cond_fmt, handle_sigchld, opt_fmt, signal_fmt, terminate_process, ExecOutput, HandleSigchld,
-    ProcessOutput,
+    cond_fmt, handle_sigchld, opt_fmt, signal_fmt, start_iolog, terminate_process, ExecOutput,
+    HandleSigchld, ProcessOutput,
 };
 use crate::exec::{
     io_util::retry_while_interrupted,
     use_pty::backchannel::{BackchannelPair, MonitorMessage, ParentBackchannel, ParentMessage},
     ExitReason,
 };
+use crate::iolog::{IoLogOptions, IoStream, SessionInfo};
 use crate::log::{dev_error, dev_info, dev_warn};
 use crate::system::signal::{
     consts::*, register_handlers, SignalHandler, SignalHandlerBehavior, SignalNumber, SignalSet,
@@ -25,13 +26,17 @@ use crate::system::wait::WaitOptions;
 use crate::system::{chown, fork, getpgrp, kill, killpg, FileCloser, ForkResult, Group, User};
 use crate::system::{getpgid, interface::ProcessId};
 
-use super::pipe::Pipe;
 use super::{CommandStatus, SIGCONT_BG};
+use crate::exec::noexec::NoexecHandler;
+use crate::exec::pipe::{Pipe, SharedLog, StdioRelays};
 
 pub(in crate::exec) fn exec_pty(
     sudo_pid: ProcessId,
     mut command: Command,
     user_tty: UserTerm,
+    mut file_closer: FileCloser,
+    iolog: Option<(&IoLogOptions, SessionInfo)>,
+    noexec: Option<NoexecHandler>,
 ) -> io::Result<ProcessOutput> {
     // Allocate a pseudoterminal.
     let pty = get_pty()?;
@@ -59,8 +64,6 @@ pub(in crate::exec) fn exec_pty(
     // Fetch the parent process group so we can signals to it.
     let parent_pgrp = getpgrp();
 
-    let mut file_closer = FileCloser::new();
-
     // Set all the IO streams for the command to the follower side of the pty.
     let mut clone_follower = || -> io::Result<PtyFollower> {
         let follower = pty.follower.try_clone().map_err(|err| {
@@ -88,6 +91,24 @@ pub(in crate::exec) fn exec_pty(
         ParentEvent::Pty,
     );
 
+    let tty_size = tty_pipe.left().get_size().map_err(|err| {
+        dev_error!("cannot get terminal size: {err}");
+        err
+    })?;
+
+    // Start recording the session in the I/O log.
+    let iolog = match iolog {
+        Some((options, mut info)) => {
+            info.rows = tty_size.rows();
+            info.cols = tty_size.cols();
+            let log = start_iolog(options, &info)?;
+            tty_pipe.record_in(&log, IoStream::TtyIn, IoStream::TtyOut);
+            Some(log)
+        }
+        None => None,
+    };
+    let mut stdio_relays = StdioRelays::default();
+
     let user_tty = tty_pipe.left_mut();
 
     // Check if we are the foreground process
@@ -108,14 +129,27 @@ pub(in crate::exec) fn exec_pty(
     // Whether the user's terminal is in raw mode or not.
     let mut term_raw = false;
 
-    // Check if we are part of a pipeline.
-    // FIXME: Here's where we should intercept the IO streams if we want to implement IO logging.
-    // FIXME: ogsudo creates pipes for the IO streams and uses events to read from the strams to
-    // the pipes. Investigate why.
+    // Check if we are part of a pipeline. The IO streams that are not terminals are inherited by
+    // the command, unless they have to be recorded in the I/O log, in which case they are relayed
+    // to the command through pipes.
+    let recorded = |stream| iolog.as_ref().filter(|log| log.borrow().records(stream));
+
     if !io::stdin().is_terminal() {
-        dev_info!("stdin is not a terminal, command will inherit it");
         pipeline = true;
-        command.stdin(Stdio::inherit());
+        if let Some(log) = recorded(IoStream::Stdin) {
+            dev_info!("stdin is not a terminal, command will read it through a pipe");
+            stdio_relays.relay(
+                IoStream::Stdin,
+                log,
+                &mut command,
+                &mut file_closer,
+                &mut registry,
+                |poll_event| ParentEvent::Stdio(IoStream::Stdin, poll_event),
+            )?;
+        } else {
+            dev_info!("stdin is not a terminal, command will inherit it");
+            command.stdin(Stdio::inherit());
+        }
 
         if foreground && parent_pgrp != sudo_pid {
             // If sudo is not the process group leader and stdin is not a terminal we might be
@@ -126,14 +160,38 @@ pub(in crate::exec) fn exec_pty(
     }
 
     if !io::stdout().is_terminal() {
-        dev_info!("stdout is not a terminal, command will inherit it");
         pipeline = true;
-        command.stdout(Stdio::inherit());
+        if let Some(log) = recorded(IoStream::Stdout) {
+            dev_info!("stdout is not a terminal, command will write it through a pipe");
+            stdio_relays.relay(
+                IoStream::Stdout,
+                log,
+                &mut command,
+                &mut file_closer,
+                &mut registry,
+                |poll_event| ParentEvent::Stdio(IoStream::Stdout, poll_event),
+            )?;
+        } else {
+            dev_info!("stdout is not a terminal, command will inherit it");
+            command.stdout(Stdio::inherit());
+        }
     }
 
     if !io::stderr().is_terminal() {
-        dev_info!("stderr is not a terminal, command will inherit it");
-        command.stderr(Stdio::inherit());
+        if let Some(log) = recorded(IoStream::Stderr) {
+            dev_info!("stderr is not a terminal, command will write it through a pipe");
+            stdio_relays.relay(
+                IoStream::Stderr,
+                log,
+                &mut command,
+                &mut file_closer,
+                &mut registry,
+                |poll_event| ParentEvent::Stdio(IoStream::Stderr, poll_event),
+            )?;
+        } else {
+            dev_info!("stderr is not a terminal, command will inherit it");
+            command.stderr(Stdio::inherit());
+        }
     }
 
     // Copy terminal settings from `/dev/tty` to the pty.
@@ -147,11 +205,6 @@ pub(in crate::exec) fn exec_pty(
         term_raw = true;
     }
 
-    let tty_size = tty_pipe.left().get_size().map_err(|err| {
-        dev_error!("cannot get terminal size: {err}");
-        err
-    })?;
-
     // Block all the signals until we are done setting up the signal handlers so we don't miss
     // SIGCHLD.
     let original_set = match SignalSet::full().and_then(|set| set.block()) {
@@ -169,6 +222,9 @@ pub(in crate::exec) fn exec_pty(
     else {
         // Close the file descriptors that we don't access
         drop(tty_pipe);
+        drop(stdio_relays);
+        drop(iolog);
+        drop(noexec);
         drop(backchannels.parent);
 
         // If `exec_monitor` returns, it means we failed to execute the command somehow.
@@ -203,8 +259,14 @@ pub(in crate::exec) fn exec_pty(
 
     // Close the file descriptors that we don't access
     drop(pty.follower);
+    drop(command);
     drop(backchannels.monitor);
 
+    // The monitor forks the command, but the requests of the command can be answered from here.
+    if let Some(noexec) = noexec {
+        noexec.spawn();
+    }
+
     // Send green light to the monitor after closing the follower.
     retry_while_interrupted(|| backchannels.parent.send(&MonitorMessage::ExecCommand)).map_err(
         |err| {
@@ -219,6 +281,8 @@ pub(in crate::exec) fn exec_pty(
         parent_pgrp,
         backchannels.parent,
         tty_pipe,
+        stdio_relays,
+        iolog,
         tty_size,
         foreground,
         term_raw,
@@ -237,6 +301,8 @@ pub(in crate::exec) fn exec_pty(
 
     // Flush the terminal
     closure.tty_pipe.flush_left().ok();
+    // Pass on the output that the command wrote just before exiting.
+    closure.stdio_relays.drain();
 
     // Restore the terminal settings
     if closure.term_raw {
@@ -288,6 +354,8 @@ struct ParentClosure {
     parent_pgrp: ProcessId,
     command_pid: Option<ProcessId>,
     tty_pipe: Pipe<UserTerm, PtyLeader>,
+    stdio_relays: StdioRelays,
+    iolog: Option<SharedLog>,
     tty_size: TermSize,
     foreground: bool,
     term_raw: bool,
@@ -311,6 +379,8 @@ impl ParentClosure {
         parent_pgrp: ProcessId,
         mut backchannel: ParentBackchannel,
         tty_pipe: Pipe<UserTerm, PtyLeader>,
+        stdio_relays: StdioRelays,
+        iolog: Option<SharedLog>,
         tty_size: TermSize,
         foreground: bool,
         term_raw: bool,
@@ -338,6 +408,8 @@ impl ParentClosure {
             parent_pgrp,
             command_pid: None,
             tty_pipe,
+            stdio_relays,
+            iolog,
             tty_size,
             foreground,
             term_raw,
@@ -656,6 +728,12 @@ impl ParentClosure {
             if let Some(command_pid) = self.command_pid {
                 killpg(command_pid, SIGWINCH).ok();
             }
+            // Record the new size in the I/O log.
+            if let Some(log) = &self.iolog {
+                if let Err(err) = log.borrow_mut().resize(new_size.rows(), new_size.cols()) {
+                    dev_warn!("cannot write to I/O log: {err}");
+                }
+            }
             // Update the terminal size.
             self.tty_size = new_size;
         }
@@ -688,6 +766,7 @@ enum ParentEvent {
     Signal,
     Tty(PollEvent),
     Pty(PollEvent),
+    Stdio(IoStream, PollEvent),
     Backchannel(PollEvent),
 }
 
@@ -711,6 +790,9 @@ impl Process for ParentClosure {
             ParentEvent::Pty(poll_event) => {
                 self.tty_pipe.on_right_event(poll_event, registry).ok();
             }
+            ParentEvent::Stdio(stream, poll_event) => {
+                self.stdio_relays.on_event(stream, poll_event, registry);
+            }
             ParentEvent::Backchannel(poll_event) => match poll_event {
                 PollEvent::Readable => self.on_message_received(registry),
                 PollEvent::Writable => self.check_message_queue(registry),
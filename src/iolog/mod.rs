@@ -0,0 +1,591 @@
+#![forbid(unsafe_code)]
+//! Session I/O logs, in the directory layout and file format used by the original sudo.
+//!
+//! Every session gets its own directory (named after a sequence number) that contains a `log`
+//! file describing the session, a `timing` file recording when data was transferred, and one file
+//! for every stream that is recorded.
+
+use std::{
+    fs::{self, DirBuilder, File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::system::file::FileLock;
+
+/// The streams of a session that can be recorded; the discriminants are the event numbers used
+/// in the timing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoStream {
+    Stdin = 0,
+    Stdout = 1,
+    Stderr = 2,
+    TtyIn = 3,
+    TtyOut = 4,
+}
+
+/// The event number used in the timing file for a change of the terminal size.
+const WINDOW_SIZE_EVENT: u32 = 5;
+
+impl IoStream {
+    pub const ALL: [IoStream; 5] = [
+        IoStream::Stdin,
+        IoStream::Stdout,
+        IoStream::Stderr,
+        IoStream::TtyIn,
+        IoStream::TtyOut,
+    ];
+
+    /// The name of the file in the session directory that holds the data of this stream.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            IoStream::Stdin => "stdin",
+            IoStream::Stdout => "stdout",
+            IoStream::Stderr => "stderr",
+            IoStream::TtyIn => "ttyin",
+            IoStream::TtyOut => "ttyout",
+        }
+    }
+
+    pub fn is_input(self) -> bool {
+        matches!(self, IoStream::Stdin | IoStream::TtyIn)
+    }
+
+    fn from_event(event: u32) -> Option<Self> {
+        IoStream::ALL
+            .into_iter()
+            .find(|stream| *stream as u32 == event)
+    }
+}
+
+/// Where sessions are logged, and which of their streams are recorded (as decided by the
+/// `log_input` and `log_output` settings and tags).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoLogOptions {
+    pub dir: PathBuf,
+    pub log_input: bool,
+    pub log_output: bool,
+}
+
+impl IoLogOptions {
+    pub fn records(&self, stream: IoStream) -> bool {
+        if stream.is_input() {
+            self.log_input
+        } else {
+            self.log_output
+        }
+    }
+}
+
+/// The description of a session, as stored in its `log` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Seconds since the Unix epoch.
+    pub start_time: i64,
+    pub user: String,
+    pub runas_user: String,
+    pub runas_group: String,
+    pub tty: String,
+    pub rows: u16,
+    pub cols: u16,
+    pub cwd: String,
+    pub command: String,
+}
+
+impl SessionInfo {
+    fn write_to(&self, target: &mut impl Write) -> io::Result<()> {
+        let SessionInfo {
+            start_time,
+            user,
+            runas_user,
+            runas_group,
+            tty,
+            rows,
+            cols,
+            cwd,
+            command,
+        } = self;
+        writeln!(
+            target,
+            "{start_time}:{user}:{runas_user}:{runas_group}:{tty}:{rows}:{cols}"
+        )?;
+        writeln!(target, "{}", escape(cwd))?;
+        writeln!(target, "{}", escape(command))
+    }
+
+    fn parse(text: &str) -> Option<SessionInfo> {
+        let mut lines = text.lines();
+        let mut fields = lines.next()?.split(':');
+        let mut field = || fields.next().map(str::to_string);
+
+        Some(SessionInfo {
+            start_time: field()?.parse().ok()?,
+            user: field()?,
+            runas_user: field()?,
+            runas_group: field()?,
+            tty: field()?,
+            // older versions of the format do not include the terminal size
+            rows: field().and_then(|rows| rows.parse().ok()).unwrap_or(24),
+            cols: field().and_then(|cols| cols.parse().ok()).unwrap_or(80),
+            cwd: unescape(lines.next()?),
+            command: unescape(lines.next().unwrap_or_default()),
+        })
+    }
+
+    /// Read the description of the session that is stored in `session_dir`.
+    pub fn read(session_dir: &Path) -> io::Result<SessionInfo> {
+        let text = fs::read_to_string(session_dir.join("log"))?;
+
+        SessionInfo::parse(&text)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid session log"))
+    }
+}
+
+/// Escape the backslash and control characters, so that `text` fits on a single line of the
+/// `log` file; a newline is written as `\n`, other control characters in octal as `\ooo`.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            ch if ch.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", ch as u8)),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// Undo the escaping done by [`escape`]; an invalid escape sequence is kept as it is.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('\\') {
+        unescaped.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let octal = rest
+            .get(..3)
+            .filter(|digits| digits.bytes().all(|b| matches!(b, b'0'..=b'7')))
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (rest.chars().next(), octal) {
+            (_, Some(byte)) if byte.is_ascii() => {
+                unescaped.push(byte as char);
+                rest = &rest[3..];
+            }
+            (Some('\\'), _) => {
+                unescaped.push('\\');
+                rest = &rest[1..];
+            }
+            (Some('n'), _) => {
+                unescaped.push('\n');
+                rest = &rest[1..];
+            }
+            _ => unescaped.push('\\'),
+        }
+    }
+    unescaped.push_str(rest);
+
+    unescaped
+}
+
+/// Records the I/O of a single session.
+pub struct IoLogWriter {
+    id: String,
+    timing: File,
+    streams: [Option<File>; IoStream::ALL.len()],
+    last_event: Instant,
+}
+
+impl IoLogWriter {
+    /// Start a new session in the I/O log directory; only the streams that are selected by
+    /// `options` will be recorded.
+    pub fn create(options: &IoLogOptions, info: &SessionInfo) -> io::Result<IoLogWriter> {
+        create_dir(&options.dir)?;
+        let id = next_session_id(&options.dir)?;
+        let path = sequence_dir(&options.dir, &id);
+        // once the sequence number has wrapped around, the oldest sessions are replaced
+        match fs::remove_dir_all(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        create_dir(&path)?;
+
+        info.write_to(&mut create_file(&path.join("log"))?)?;
+        let timing = create_file(&path.join("timing"))?;
+
+        let mut streams: [Option<File>; IoStream::ALL.len()] = Default::default();
+        for stream in IoStream::ALL {
+            if options.records(stream) {
+                streams[stream as usize] = Some(create_file(&path.join(stream.file_name()))?);
+            }
+        }
+
+        Ok(IoLogWriter {
+            id,
+            timing,
+            streams,
+            last_event: Instant::now(),
+        })
+    }
+
+    /// The identifier of the session, which can be passed to `sudoreplay`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn records(&self, stream: IoStream) -> bool {
+        self.streams[stream as usize].is_some()
+    }
+
+    /// Record that `bytes` were transferred over `stream`; this does nothing if the stream is not
+    /// being recorded.
+    pub fn record(&mut self, stream: IoStream, bytes: &[u8]) -> io::Result<()> {
+        let Some(file) = &mut self.streams[stream as usize] else {
+            return Ok(());
+        };
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        file.write_all(bytes)?;
+
+        let delay = self.elapsed();
+        writeln!(
+            self.timing,
+            "{} {} {}",
+            stream as u32,
+            format_delay(delay),
+            bytes.len()
+        )
+    }
+
+    /// Record that the terminal of the session was resized.
+    pub fn resize(&mut self, rows: u16, cols: u16) -> io::Result<()> {
+        let delay = self.elapsed();
+        writeln!(
+            self.timing,
+            "{WINDOW_SIZE_EVENT} {} {rows} {cols}",
+            format_delay(delay)
+        )
+    }
+
+    fn elapsed(&mut self) -> Duration {
+        let now = Instant::now();
+        let delay = now.duration_since(self.last_event);
+        self.last_event = now;
+        delay
+    }
+}
+
+fn format_delay(delay: Duration) -> String {
+    format!("{}.{:09}", delay.as_secs(), delay.subsec_nanos())
+}
+
+fn create_dir(path: &Path) -> io::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(path)
+}
+
+fn create_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+const SEQUENCE_DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const SEQUENCE_LEN: usize = 6;
+
+/// Allocate the identifier of a new session, using the sequence number stored in the `seq` file
+/// of the I/O log directory.
+fn next_session_id(dir: &Path) -> io::Result<String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(dir.join("seq"))?;
+    let lock = FileLock::exclusive(&file, false)?;
+
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let previous = u64::from_str_radix(text.trim(), 36).unwrap_or(0);
+
+    let mut seq = (previous + 1) % 36u64.pow(SEQUENCE_LEN as u32);
+    let mut id = [b'0'; SEQUENCE_LEN];
+    for digit in id.iter_mut().rev() {
+        *digit = SEQUENCE_DIGITS[(seq % 36) as usize];
+        seq /= 36;
+    }
+    let id = String::from_utf8_lossy(&id).into_owned();
+
+    file.rewind()?;
+    file.set_len(0)?;
+    writeln!(file, "{id}")?;
+    lock.unlock()?;
+
+    Ok(id)
+}
+
+fn is_sequence(id: &str) -> bool {
+    id.len() == SEQUENCE_LEN && id.bytes().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn sequence_dir(dir: &Path, id: &str) -> PathBuf {
+    dir.join(&id[0..2]).join(&id[2..4]).join(&id[4..6])
+}
+
+/// The directory in which the session with the given identifier is stored; an identifier that is
+/// not a sequence number names a directory in the I/O log directory itself. Identifiers that could
+/// refer to anything outside of the I/O log directory are rejected.
+pub fn session_dir(dir: &Path, id: &str) -> Option<PathBuf> {
+    if is_sequence(id) {
+        Some(sequence_dir(dir, id))
+    } else if id.is_empty() || id == "." || id.contains('/') || id.contains("..") {
+        None
+    } else {
+        Some(dir.join(id))
+    }
+}
+
+/// Find all sessions in the I/O log directory that were allocated a sequence number, sorted by
+/// that number.
+pub fn list_sessions(dir: &Path) -> io::Result<Vec<(String, SessionInfo)>> {
+    fn subdirs(path: &Path) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if let Ok(name) = entry.file_name().into_string() {
+                if name.len() == 2 && entry.file_type()?.is_dir() {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    let mut sessions = Vec::new();
+    for first in subdirs(dir)? {
+        for second in subdirs(&dir.join(&first))? {
+            for third in subdirs(&dir.join(&first).join(&second))? {
+                let id = format!("{first}{second}{third}");
+                if let Ok(info) = SessionInfo::read(&sequence_dir(dir, &id)) {
+                    sessions.push((id, info));
+                }
+            }
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// An event that was recorded in the timing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingEvent {
+    Io(IoStream, usize),
+    WindowSize { rows: u16, cols: u16 },
+}
+
+/// A line of the timing file: an event, and how long after the previous event it happened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingRecord {
+    pub delay: Duration,
+    pub event: TimingEvent,
+}
+
+impl TimingRecord {
+    /// Parse a line of the timing file; events that are not understood (such as the suspension
+    /// of the command) result in `None`.
+    pub fn parse(line: &str) -> Option<TimingRecord> {
+        let mut fields = line.split_ascii_whitespace();
+        let event: u32 = fields.next()?.parse().ok()?;
+        let delay = Duration::try_from_secs_f64(fields.next()?.parse().ok()?).ok()?;
+
+        let event = if event == WINDOW_SIZE_EVENT {
+            TimingEvent::WindowSize {
+                rows: fields.next()?.parse().ok()?,
+                cols: fields.next()?.parse().ok()?,
+            }
+        } else {
+            TimingEvent::Io(IoStream::from_event(event)?, fields.next()?.parse().ok()?)
+        };
+
+        Some(TimingRecord { delay, event })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> SessionInfo {
+        SessionInfo {
+            start_time: 1700000000,
+            user: "ferris".to_string(),
+            runas_user: "root".to_string(),
+            runas_group: "root".to_string(),
+            tty: "/dev/pts/3".to_string(),
+            rows: 50,
+            cols: 132,
+            cwd: "/home/ferris".to_string(),
+            command: "/usr/bin/ls -l /root".to_string(),
+        }
+    }
+
+    #[test]
+    fn session_info_roundtrip() {
+        let mut text = Vec::new();
+        info().write_to(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(
+            text,
+            "1700000000:ferris:root:root:/dev/pts/3:50:132\n/home/ferris\n/usr/bin/ls -l /root\n"
+        );
+        assert_eq!(SessionInfo::parse(&text), Some(info()));
+
+        let legacy = SessionInfo::parse("1700000000:ferris:root::unknown\n/\n/bin/true\n").unwrap();
+        assert_eq!((legacy.rows, legacy.cols), (24, 80));
+        assert_eq!(legacy.runas_group, "");
+        assert!(SessionInfo::parse("garbage").is_none());
+    }
+
+    #[test]
+    fn session_info_escapes_control_characters() {
+        let info = SessionInfo {
+            cwd: "/tmp/a\nb".to_string(),
+            command: "/bin/echo 1700000000:root:root\n\\n\t\x1b[0m".to_string(),
+            ..info()
+        };
+        let mut text = Vec::new();
+        info.write_to(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.ends_with("/tmp/a\\nb\n/bin/echo 1700000000:root:root\\n\\\\n\\011\\033[0m\n"));
+        assert_eq!(SessionInfo::parse(&text), Some(info));
+
+        assert_eq!(unescape("a\\b\\"), "a\\b\\");
+        assert_eq!(unescape("\\400"), "\\400");
+    }
+
+    #[test]
+    fn parse_timing() {
+        let parse = TimingRecord::parse;
+        assert_eq!(
+            parse("4 0.250000000 12"),
+            Some(TimingRecord {
+                delay: Duration::from_millis(250),
+                event: TimingEvent::Io(IoStream::TtyOut, 12)
+            })
+        );
+        assert_eq!(
+            parse("5 1.000000000 24 80"),
+            Some(TimingRecord {
+                delay: Duration::from_secs(1),
+                event: TimingEvent::WindowSize { rows: 24, cols: 80 }
+            })
+        );
+        assert_eq!(parse("7 0.5 SIGTSTP"), None);
+        assert_eq!(parse("1 -1 5"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn session_dir_layout() {
+        let dir = Path::new("/var/log/sudo-io");
+        assert_eq!(
+            session_dir(dir, "00A01Z").unwrap(),
+            Path::new("/var/log/sudo-io/00/A0/1Z")
+        );
+        assert_eq!(
+            session_dir(dir, "ferris-ls").unwrap(),
+            Path::new("/var/log/sudo-io/ferris-ls")
+        );
+        for id in ["", ".", "..", "../../etc", "ferris/..", "ferris/ls", "/etc"] {
+            assert_eq!(session_dir(dir, id), None);
+        }
+    }
+
+    #[test]
+    fn write_sessions() {
+        let dir = crate::system::file::create_temporary_dir("/tmp/sudo-rs-test").unwrap();
+        let options = IoLogOptions {
+            dir: dir.join("iolog"),
+            log_input: false,
+            log_output: true,
+        };
+
+        let mut writer = IoLogWriter::create(&options, &info()).unwrap();
+        assert_eq!(writer.id(), "000001");
+        assert!(writer.records(IoStream::TtyOut) && !writer.records(IoStream::TtyIn));
+        writer.record(IoStream::TtyOut, b"hello ").unwrap();
+        writer.record(IoStream::TtyIn, b"secret").unwrap();
+        writer.resize(10, 20).unwrap();
+        writer.record(IoStream::Stdout, b"world").unwrap();
+
+        let session = session_dir(&options.dir, "000001").unwrap();
+        assert_eq!(fs::read(session.join("ttyout")).unwrap(), b"hello ");
+        assert_eq!(fs::read(session.join("stdout")).unwrap(), b"world");
+        assert!(!session.join("ttyin").exists());
+
+        let timing = fs::read_to_string(session.join("timing")).unwrap();
+        let events = timing
+            .lines()
+            .map(|line| TimingRecord::parse(line).unwrap().event)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                TimingEvent::Io(IoStream::TtyOut, 6),
+                TimingEvent::WindowSize { rows: 10, cols: 20 },
+                TimingEvent::Io(IoStream::Stdout, 5),
+            ]
+        );
+
+        // the sequence number is incremented for every session
+        fs::write(options.dir.join("seq"), "00000Z\n").unwrap();
+        let writer = IoLogWriter::create(&options, &info()).unwrap();
+        assert_eq!(writer.id(), "000010");
+
+        let sessions = list_sessions(&options.dir).unwrap();
+        assert_eq!(
+            sessions,
+            [
+                ("000001".to_string(), info()),
+                ("000010".to_string(), info())
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sequence_wraps_around() {
+        let dir = crate::system::file::create_temporary_dir("/tmp/sudo-rs-test").unwrap();
+        let options = IoLogOptions {
+            dir: dir.join("iolog"),
+            log_input: false,
+            log_output: true,
+        };
+
+        let mut writer = IoLogWriter::create(&options, &info()).unwrap();
+        writer.record(IoStream::TtyOut, b"old").unwrap();
+        let session = session_dir(&options.dir, "000001").unwrap();
+        assert_eq!(fs::read(session.join("ttyout")).unwrap(), b"old");
+
+        fs::write(options.dir.join("seq"), "ZZZZZZ\n").unwrap();
+        let writer = IoLogWriter::create(&options, &info()).unwrap();
+        assert_eq!(writer.id(), "000000");
+
+        // the session that was stored under the same sequence number is replaced
+        let mut writer = IoLogWriter::create(&options, &info()).unwrap();
+        assert_eq!(writer.id(), "000001");
+        writer.record(IoStream::TtyOut, b"new").unwrap();
+        assert_eq!(fs::read(session.join("ttyout")).unwrap(), b"new");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,15 +4,20 @@ pub(crate) mod common;
 pub(crate) mod cutils;
 pub(crate) mod defaults;
 pub(crate) mod exec;
+pub(crate) mod iolog;
 pub(crate) mod log;
 pub(crate) mod pam;
 pub(crate) mod sudoers;
 pub(crate) mod system;
 
+mod cvtsudoers;
 mod su;
 mod sudo;
+mod sudoreplay;
 mod visudo;
 
+pub use cvtsudoers::main as cvtsudoers_main;
 pub use su::main as su_main;
 pub use sudo::main as sudo_main;
+pub use sudoreplay::main as sudoreplay_main;
 pub use visudo::main as visudo_main;
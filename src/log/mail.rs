@@ -0,0 +1,129 @@
+//! Mail to the administrator about security incidents, sent through an external mailer in the
+//! same way as the original sudo does (by default: `/usr/sbin/sendmail -t`).
+
+use std::fmt;
+use std::io::{self, Write};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use super::auth_warn;
+
+/// The kinds of incidents that can be reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Incident {
+    /// The user failed to authenticate.
+    AuthFailure,
+    /// The user is not mentioned in any rule that applies to this host.
+    NotInSudoers,
+    /// The user is mentioned in the rules, but may not run the requested command.
+    CommandDenied,
+}
+
+impl fmt::Display for Incident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incident::AuthFailure => f.write_str("authentication failure"),
+            Incident::NotInSudoers => f.write_str("user NOT in sudoers"),
+            Incident::CommandDenied => f.write_str("command not allowed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MailOptions {
+    pub mailer: PathBuf,
+    pub flags: Vec<String>,
+    pub mailto: String,
+}
+
+/// Send a mail reporting `incident` caused by `user` on `hostname`; `details` describe the
+/// command that was requested. Failures are logged, but otherwise ignored.
+pub fn send_mail(
+    options: &MailOptions,
+    hostname: &str,
+    user: &str,
+    incident: Incident,
+    details: &str,
+) {
+    let message = compose(&options.mailto, hostname, user, incident, details);
+
+    if let Err(e) = deliver(options, &message) {
+        auth_warn!(
+            "unable to send mail through {}: {e}",
+            options.mailer.display()
+        );
+    }
+}
+
+fn compose(mailto: &str, hostname: &str, user: &str, incident: Incident, details: &str) -> String {
+    format!(
+        "To: {mailto}\n\
+         Auto-Submitted: auto-generated\n\
+         Subject: *** SECURITY information for {hostname} ***\n\
+         \n\
+         {hostname} : {user} : {incident} ; {details}\n"
+    )
+}
+
+fn deliver(options: &MailOptions, message: &str) -> io::Result<()> {
+    // the mailer runs as root, so it must not inherit anything from the invoking user
+    let mut child = Command::new(&options.mailer)
+        .args(&options.flags)
+        .env_clear()
+        .env("PATH", "/usr/sbin:/usr/bin:/sbin:/bin")
+        .current_dir("/")
+        .uid(0)
+        .gid(0)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let written = child
+        .stdin
+        .take()
+        .expect("stdin of the mailer is piped")
+        .write_all(message.as_bytes());
+    let status = child.wait()?;
+    written?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("mailer exited with {status}"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incident_descriptions() {
+        assert_eq!(Incident::AuthFailure.to_string(), "authentication failure");
+        assert_eq!(Incident::NotInSudoers.to_string(), "user NOT in sudoers");
+        assert_eq!(Incident::CommandDenied.to_string(), "command not allowed");
+    }
+
+    #[test]
+    fn composed_message() {
+        assert_eq!(
+            compose(
+                "root",
+                "vm",
+                "ferris",
+                Incident::CommandDenied,
+                "USER=root ; COMMAND=/usr/bin/true"
+            ),
+            "To: root\n\
+             Auto-Submitted: auto-generated\n\
+             Subject: *** SECURITY information for vm ***\n\
+             \n\
+             vm : ferris : command not allowed ; USER=root ; COMMAND=/usr/bin/true\n"
+        );
+    }
+}
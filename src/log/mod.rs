@@ -4,6 +4,7 @@ use self::syslog::Syslog;
 pub use log::Level;
 use std::ops::Deref;
 
+pub mod mail;
 mod simple_logger;
 mod syslog;
 
@@ -0,0 +1,67 @@
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::system::{cloexec_the_universe, set_target_user, Group, User};
+
+use super::{rpassword::read_unbuffered, securemem::PamBuffer};
+
+/// A program that asks the user for input on behalf of sudo, e.g. using a graphical dialog; it is
+/// passed the prompt as its only argument and writes the response to its standard output.
+pub struct Askpass {
+    program: PathBuf,
+    user: User,
+    group: Group,
+}
+
+impl Askpass {
+    /// The program will be run as `user` and `group`, which should be the user that invoked sudo
+    /// and their primary group.
+    pub fn new(program: PathBuf, user: User, group: Group) -> Askpass {
+        Askpass {
+            program,
+            user,
+            group,
+        }
+    }
+
+    /// Run the program and read the first line of its output
+    pub(super) fn ask(&self, prompt: &str) -> io::Result<PamBuffer> {
+        let mut command = Command::new(&self.program);
+        command
+            .arg(prompt)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped());
+
+        set_target_user(&mut command, self.user.clone(), self.group.clone());
+        // the program should not get hold of anything that sudo has open besides the standard
+        // streams; these are marked close-on-exec instead of being closed, since `Command` uses
+        // one of them to report a failure to execute the program
+        // SAFETY: the closure only calls close_range, which is async-signal-safe
+        unsafe {
+            command.pre_exec(cloexec_the_universe);
+        }
+
+        let mut child = command.spawn().map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("unable to run {}: {err}", self.program.display()),
+            )
+        })?;
+
+        let mut output = child.stdout.take().expect("stdout of askpass is piped");
+        let response = read_unbuffered(&mut output, None);
+        drop(output);
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} exited with {status}", self.program.display()),
+            ));
+        }
+
+        response
+    }
+}
@@ -1,8 +1,12 @@
+use std::time::Duration;
+
 use crate::cutils::string_from_ptr;
 
 use super::sys::*;
 
-use super::{error::PamResult, rpassword, securemem::PamBuffer, PamError, PamErrorType};
+use super::{
+    askpass::Askpass, error::PamResult, rpassword, securemem::PamBuffer, PamError, PamErrorType,
+};
 
 /// Each message in a PAM conversation will have a message style. Each of these
 /// styles must be handled separately.
@@ -121,22 +125,46 @@ where
     }
 }
 
+/// How a [`CLIConverser`] asks the user for input
+#[derive(Default)]
+pub struct PromptOptions {
+    /// A program that asks for input, instead of the terminal
+    pub askpass: Option<Askpass>,
+    /// The prompt to show instead of the standard password prompt of PAM
+    pub prompt: Option<String>,
+    /// Whether `prompt` also replaces password prompts of PAM that are not the standard one
+    pub prompt_override: bool,
+    /// Whether an asterisk is shown for every character of a password that is typed
+    pub pwfeedback: bool,
+    /// Whether to ask for a password even if there is no terminal to hide it on
+    pub visiblepw: bool,
+    /// How long to wait for the user to answer a prompt on the terminal
+    pub timeout: Option<Duration>,
+}
+
 /// A converser that uses stdin/stdout/stderr to display messages and to request
-/// input from the user.
+/// input from the user; or, if an askpass program is given, uses that to request input.
 pub struct CLIConverser {
     pub(super) name: String,
     pub(super) use_stdin: bool,
     pub(super) no_interact: bool,
+    pub(super) options: PromptOptions,
 }
 
 use rpassword::Terminal;
 
 impl CLIConverser {
-    fn open(&self) -> std::io::Result<Terminal> {
-        if self.use_stdin {
-            Terminal::open_stdie()
+    fn open(&self) -> PamResult<Terminal> {
+        // an askpass program is typically used when there is no terminal to talk to
+        if self.use_stdin || self.options.askpass.is_some() {
+            Ok(Terminal::open_stdie()?)
         } else {
-            Terminal::open_tty()
+            match Terminal::open_tty() {
+                Ok(tty) => Ok(tty),
+                // without a terminal, whatever the user types may be visible
+                Err(_) if self.options.visiblepw => Ok(Terminal::open_stdie()?),
+                Err(_) => Err(PamError::TtyRequired),
+            }
         }
     }
 }
@@ -146,18 +174,31 @@ impl SequentialConverser for CLIConverser {
         if self.no_interact {
             return Err(PamError::InteractionRequired);
         }
+        let prompt = format!("[{}: input needed] {msg} ", self.name);
+        if let Some(askpass) = &self.options.askpass {
+            return Ok(askpass.ask(&prompt)?);
+        }
         let mut tty = self.open()?;
-        tty.prompt(&format!("[{}: input needed] {msg} ", self.name))?;
-        Ok(tty.read_cleartext()?)
+        tty.prompt(&prompt)?;
+        Ok(tty.read_cleartext(self.options.timeout)?)
     }
 
     fn handle_hidden_prompt(&self, msg: &str) -> PamResult<PamBuffer> {
         if self.no_interact {
             return Err(PamError::InteractionRequired);
         }
+        let prompt = match &self.options.prompt {
+            Some(prompt) if self.options.prompt_override || is_password_prompt(msg) => {
+                prompt.clone()
+            }
+            _ => format!("[{}: authenticate] {msg}", self.name),
+        };
+        if let Some(askpass) = &self.options.askpass {
+            return Ok(askpass.ask(&prompt)?);
+        }
         let mut tty = self.open()?;
-        tty.prompt(&format!("[{}: authenticate] {msg}", self.name))?;
-        Ok(tty.read_password()?)
+        tty.prompt(&prompt)?;
+        Ok(tty.read_password(self.options.pwfeedback, self.options.timeout)?)
     }
 
     fn handle_error(&self, msg: &str) -> PamResult<()> {
@@ -171,10 +212,17 @@ impl SequentialConverser for CLIConverser {
     }
 }
 
+/// Whether `msg` is the prompt that PAM uses when it simply asks for a password
+fn is_password_prompt(msg: &str) -> bool {
+    matches!(msg, "Password:" | "Password: ")
+}
+
 /// Helper struct that contains the converser as well as panic boolean
 pub(super) struct ConverserData<C> {
     pub(super) converser: C,
     pub(super) panicked: bool,
+    /// The last error of the converser, which PAM only gets to see as a conversation error
+    pub(super) error: Option<PamError>,
 }
 
 /// This function implements the conversation function of `pam_conv`.
@@ -222,11 +270,8 @@ pub(super) unsafe extern "C" fn converse<C: Converser>(
 
         // send the conversation of to the Rust part
         let app_data = unsafe { &mut *(appdata_ptr as *mut ConverserData<C>) };
-        if app_data
-            .converser
-            .handle_conversation(&mut conversation)
-            .is_err()
-        {
+        if let Err(err) = app_data.converser.handle_conversation(&mut conversation) {
+            app_data.error = Some(err);
             return PamErrorType::ConversationError;
         }
 
@@ -395,6 +440,7 @@ mod test {
         let mut hello = Box::pin(ConverserData {
             converser: "tux".to_string(),
             panicked: false,
+            error: None,
         });
         let cookie = PamConvBorrow::new(hello.as_mut());
         let pam_conv = cookie.borrow();
@@ -177,11 +177,18 @@ pub enum PamError {
     SessionNotOpen,
     EnvListFailure,
     InteractionRequired,
+    TtyRequired,
+    TimedOut,
 }
 
 impl From<std::io::Error> for PamError {
     fn from(err: std::io::Error) -> Self {
-        PamError::IoError(err)
+        // the user did not respond in time
+        if err.kind() == std::io::ErrorKind::TimedOut {
+            PamError::TimedOut
+        } else {
+            PamError::IoError(err)
+        }
     }
 }
 
@@ -221,6 +228,12 @@ impl fmt::Display for PamError {
                 )
             }
             PamError::InteractionRequired => write!(f, "Interaction is required"),
+            PamError::TtyRequired => write!(
+                f,
+                "a terminal is required to read the password; either use the -S option to \
+                 read from standard input or configure an askpass helper"
+            ),
+            PamError::TimedOut => write!(f, "timed out reading password"),
         }
     }
 }
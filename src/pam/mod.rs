@@ -11,6 +11,7 @@ use error::pam_err;
 pub use error::{PamError, PamErrorType, PamResult};
 use sys::*;
 
+mod askpass;
 mod converse;
 mod error;
 mod rpassword;
@@ -19,7 +20,8 @@ mod securemem;
 #[allow(nonstandard_style)]
 pub mod sys;
 
-pub use converse::{CLIConverser, Converser};
+pub use askpass::Askpass;
+pub use converse::{CLIConverser, Converser, PromptOptions};
 
 pub struct PamContext<C: Converser> {
     data_ptr: *mut ConverserData<C>,
@@ -54,6 +56,7 @@ impl<C: Converser> PamContextBuilder<C> {
             let data_ptr = Box::into_raw(Box::new(ConverserData {
                 converser,
                 panicked: false,
+                error: None,
             }));
 
             let mut pamh = std::ptr::null_mut();
@@ -161,12 +164,18 @@ impl<C: Converser> PamContext<C> {
         flags |= self.silent_flag();
         flags |= self.disallow_null_auth_token_flag();
 
-        pam_err(unsafe { pam_authenticate(self.pamh, flags) })?;
+        unsafe { (*self.data_ptr).error = None };
+        let result = pam_err(unsafe { pam_authenticate(self.pamh, flags) });
 
         if self.has_panicked() {
             panic!("Panic during pam authentication");
         }
-        Ok(())
+
+        // what went wrong in the conversation is more informative than what PAM makes of it
+        match (result, self.take_conversation_error()) {
+            (Err(_), Some(err)) => Err(err),
+            (result, _) => result,
+        }
     }
 
     /// Check that the account is valid
@@ -312,6 +321,11 @@ impl<C: Converser> PamContext<C> {
         Ok(res)
     }
 
+    /// Take the error that made the last conversation fail, if there was one.
+    fn take_conversation_error(&mut self) -> Option<PamError> {
+        unsafe { (*self.data_ptr).error.take() }
+    }
+
     /// Check if anything panicked since the last call.
     pub fn has_panicked(&self) -> bool {
         unsafe { (*self.data_ptr).panicked }
@@ -324,11 +338,13 @@ impl PamContext<CLIConverser> {
         name: &str,
         use_stdin: bool,
         no_interact: bool,
+        options: PromptOptions,
     ) -> PamContextBuilder<CLIConverser> {
         PamContextBuilder::default().converser(CLIConverser {
             name: name.to_owned(),
             use_stdin,
             no_interact,
+            options,
         })
     }
 }
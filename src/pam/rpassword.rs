@@ -15,9 +15,12 @@
 ///
 use std::io::{self, Error, ErrorKind, Read};
 use std::os::fd::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
 use std::{fs, mem};
 
-use libc::{tcsetattr, termios, ECHO, ECHONL, TCSANOW};
+use libc::{
+    tcsetattr, termios, ECHO, ECHONL, ICANON, POLLIN, TCSANOW, VEOF, VERASE, VKILL, VMIN, VTIME,
+};
 
 use crate::cutils::cerr;
 
@@ -29,7 +32,9 @@ pub struct HiddenInput {
 }
 
 impl HiddenInput {
-    fn new() -> io::Result<Option<HiddenInput>> {
+    /// Turn off echo; with `feedback`, also turn off canonical mode so that every keystroke can
+    /// be acknowledged as it is typed.
+    fn new(feedback: bool) -> io::Result<Option<HiddenInput>> {
         // control ourselves that we are really talking to a TTY
         // mitigates: https://marc.info/?l=oss-security&m=168164424404224
         let Ok(tty) = fs::File::open("/dev/tty") else {
@@ -50,6 +55,13 @@ impl HiddenInput {
         // But don't hide the NL character when the user hits ENTER.
         term.c_lflag |= ECHONL;
 
+        // Deliver every byte as soon as it is typed; line editing is then up to us.
+        if feedback {
+            term.c_lflag &= !ICANON;
+            term.c_cc[VMIN] = 1;
+            term.c_cc[VTIME] = 0;
+        }
+
         // Save the settings for now.
         cerr(unsafe { tcsetattr(fd, TCSANOW, &term) })?;
 
@@ -57,6 +69,20 @@ impl HiddenInput {
     }
 }
 
+impl HiddenInput {
+    /// The line editing characters of the terminal, as they were before its input was hidden
+    fn control_chars(&self) -> ControlChars {
+        // a control character is disabled by setting it to `_POSIX_VDISABLE`, which is 0
+        let control_char = |index: usize| Some(self.term_orig.c_cc[index]).filter(|&c| c != 0);
+
+        ControlChars {
+            erase: control_char(VERASE),
+            kill: control_char(VKILL),
+            eof: control_char(VEOF),
+        }
+    }
+}
+
 impl Drop for HiddenInput {
     fn drop(&mut self) {
         // Set the the mode back to normal
@@ -72,17 +98,99 @@ fn safe_tcgetattr(fd: RawFd) -> io::Result<termios> {
     Ok(unsafe { term.assume_init() })
 }
 
-/// Reads a password from the given file descriptor
-fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
-    let mut password = PamBuffer::default();
-    let mut pwd_iter = password.iter_mut();
+/// A reader for the TTY that gives up with a `TimedOut` error if no input arrived before the
+/// deadline
+struct TimeoutRead<'a> {
+    tty: &'a fs::File,
+    deadline: Instant,
+}
+
+impl io::Read for TimeoutRead<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        let mut pollfd = libc::pollfd {
+            fd: self.tty.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        };
+        let timeout = remaining.as_millis().try_into().unwrap_or(libc::c_int::MAX);
+        if cerr(unsafe { libc::poll(&mut pollfd, 1, timeout) })? == 0 {
+            // the user never pressed enter, so end the line of the prompt ourselves
+            write_unbuffered(&mut &*self.tty, "\n")?;
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "timed out reading password",
+            ));
+        }
+        self.tty.read(buf)
+    }
+}
 
+/// The characters that erase the last character, erase the whole line and end the input; when
+/// the terminal is not in canonical mode, these have to be handled by us
+#[derive(Clone, Copy)]
+pub(super) struct ControlChars {
+    erase: Option<u8>,
+    kill: Option<u8>,
+    eof: Option<u8>,
+}
+
+impl Default for ControlChars {
+    /// The control characters that a terminal uses unless it is configured otherwise
+    fn default() -> Self {
+        ControlChars {
+            erase: Some(0x7F),
+            kill: Some(0x15),
+            eof: Some(0x04),
+        }
+    }
+}
+
+/// Reads a password from the given file descriptor; if a `feedback` sink is given, an asterisk is
+/// written to it for every character that is read, and the control characters are handled (since
+/// the terminal does not do that when it is not in canonical mode).
+pub(super) fn read_unbuffered(
+    source: &mut dyn io::Read,
+    mut feedback: Option<(&mut dyn io::Write, ControlChars)>,
+) -> io::Result<PamBuffer> {
     const EOL: u8 = 0x0A;
-    let input = source.bytes().take_while(|x| x.as_ref().ok() != Some(&EOL));
+    const ERASE_FEEDBACK: &str = "\x08 \x08";
+
+    let mut password = PamBuffer::default();
+    let mut len = 0;
 
-    for read_byte in input {
-        if let Some(dest) = pwd_iter.next() {
-            *dest = read_byte?
+    for read_byte in source.bytes() {
+        let read_byte = read_byte?;
+        if read_byte == EOL {
+            break;
+        }
+
+        if let Some((sink, keys)) = feedback.as_mut() {
+            match Some(read_byte) {
+                key if key == keys.eof => break,
+                key if key == keys.erase => {
+                    if len > 0 {
+                        len -= 1;
+                        password[len] = 0;
+                        write_unbuffered(sink, ERASE_FEEDBACK)?;
+                    }
+                    continue;
+                }
+                key if key == keys.kill => {
+                    while len > 0 {
+                        len -= 1;
+                        password[len] = 0;
+                        write_unbuffered(sink, ERASE_FEEDBACK)?;
+                    }
+                    continue;
+                }
+                _ => write_unbuffered(sink, "*")?,
+            }
+        }
+
+        if let Some(dest) = password.get_mut(len) {
+            *dest = read_byte;
+            len += 1;
         } else {
             return Err(Error::new(
                 ErrorKind::OutOfMemory,
@@ -95,7 +203,7 @@ fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
 }
 
 /// Write something and immediately flush
-fn write_unbuffered(sink: &mut impl io::Write, text: &str) -> io::Result<()> {
+fn write_unbuffered(sink: &mut (impl io::Write + ?Sized), text: &str) -> io::Result<()> {
     sink.write_all(text.as_bytes())?;
     sink.flush()
 }
@@ -122,16 +230,39 @@ impl Terminal<'_> {
         Ok(Terminal::StdIE(io::stdin().lock(), io::stderr().lock()))
     }
 
-    /// Reads input with TTY echo disabled
-    pub fn read_password(&mut self) -> io::Result<PamBuffer> {
-        let mut input = self.source();
-        let _hide_input = HiddenInput::new()?;
-        read_unbuffered(&mut input)
+    /// Reads input with TTY echo disabled; with `feedback`, an asterisk is shown for every
+    /// character that is typed on the TTY. Input from the TTY is abandoned after `timeout`.
+    pub fn read_password(
+        &mut self,
+        feedback: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<PamBuffer> {
+        // dropping this on every return path restores the terminal, also after a timeout
+        let hide_input = HiddenInput::new(feedback && matches!(self, Terminal::Tty(_)))?;
+        match self {
+            Terminal::Tty(tty) if feedback => {
+                let keys = hide_input
+                    .as_ref()
+                    .map_or_else(ControlChars::default, HiddenInput::control_chars);
+                let password = read_unbuffered(
+                    &mut Self::tty_source(tty, timeout),
+                    Some((&mut &*tty, keys)),
+                )?;
+                // the terminal no longer echoes the newline by itself
+                write_unbuffered(&mut &*tty, "\n")?;
+                Ok(password)
+            }
+            Terminal::Tty(tty) => read_unbuffered(&mut Self::tty_source(tty, timeout), None),
+            Terminal::StdIE(stdin, _) => read_unbuffered(stdin, None),
+        }
     }
 
-    /// Reads input with TTY echo enabled
-    pub fn read_cleartext(&mut self) -> io::Result<PamBuffer> {
-        read_unbuffered(&mut self.source())
+    /// Reads input with TTY echo enabled; input from the TTY is abandoned after `timeout`
+    pub fn read_cleartext(&mut self, timeout: Option<Duration>) -> io::Result<PamBuffer> {
+        match self {
+            Terminal::Tty(tty) => read_unbuffered(&mut Self::tty_source(tty, timeout), None),
+            Terminal::StdIE(stdin, _) => read_unbuffered(stdin, None),
+        }
     }
 
     /// Display information
@@ -140,10 +271,13 @@ impl Terminal<'_> {
     }
 
     // boilerplate reduction functions
-    fn source(&mut self) -> &mut dyn io::Read {
-        match self {
-            Terminal::StdIE(x, _) => x,
-            Terminal::Tty(x) => x,
+    fn tty_source(tty: &fs::File, timeout: Option<Duration>) -> Box<dyn io::Read + '_> {
+        match timeout {
+            Some(timeout) => Box::new(TimeoutRead {
+                tty,
+                deadline: Instant::now() + timeout,
+            }),
+            None => Box::new(tty),
         }
     }
 
@@ -157,12 +291,16 @@ impl Terminal<'_> {
 
 #[cfg(test)]
 mod test {
-    use super::{read_unbuffered, write_unbuffered};
+    use std::io::{ErrorKind, Write};
+    use std::os::{fd::OwnedFd, unix::net::UnixStream};
+    use std::time::{Duration, Instant};
+
+    use super::{read_unbuffered, write_unbuffered, ControlChars, TimeoutRead};
 
     #[test]
     fn miri_test_read() {
         let mut data = "password123\nhello world".as_bytes();
-        let buf = read_unbuffered(&mut data).unwrap();
+        let buf = read_unbuffered(&mut data, None).unwrap();
         // check that the \n is not part of input
         assert_eq!(
             buf.iter()
@@ -177,8 +315,76 @@ mod test {
 
     #[test]
     fn miri_test_longpwd() {
-        assert!(read_unbuffered(&mut "a".repeat(511).as_bytes()).is_ok());
-        assert!(read_unbuffered(&mut "a".repeat(512).as_bytes()).is_err());
+        assert!(read_unbuffered(&mut "a".repeat(511).as_bytes(), None).is_ok());
+        assert!(read_unbuffered(&mut "a".repeat(512).as_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn miri_test_feedback() {
+        let keys = ControlChars {
+            erase: Some(0x08),
+            kill: Some(0x15),
+            eof: Some(0x04),
+        };
+        let mut data = "ab\x08c\x15xy\x08z\nrest".as_bytes();
+        let mut feedback = Vec::new();
+        let buf = read_unbuffered(&mut data, Some((&mut feedback, keys))).unwrap();
+        assert_eq!(
+            buf.iter()
+                .map(|&b| b as char)
+                .take_while(|&x| x != '\0')
+                .collect::<String>(),
+            "xz"
+        );
+        assert_eq!(
+            std::str::from_utf8(&feedback).unwrap(),
+            "**\x08 \x08*\x08 \x08\x08 \x08**\x08 \x08*"
+        );
+        assert_eq!(std::str::from_utf8(data).unwrap(), "rest");
+    }
+
+    #[test]
+    fn miri_test_feedback_eof() {
+        // the end of input ends the password; a disabled control character is an ordinary one
+        let keys = ControlChars {
+            erase: Some(0x7F),
+            kill: None,
+            eof: Some(0x04),
+        };
+        let mut data = "a\x15b\x7F\x08\x04rest".as_bytes();
+        let mut feedback = Vec::new();
+        let buf = read_unbuffered(&mut data, Some((&mut feedback, keys))).unwrap();
+        assert_eq!(
+            buf.iter()
+                .map(|&b| b as char)
+                .take_while(|&x| x != '\0')
+                .collect::<String>(),
+            "a\x15\x08"
+        );
+        assert_eq!(std::str::from_utf8(data).unwrap(), "rest");
+    }
+
+    #[test]
+    fn read_times_out() {
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        let tty = std::fs::File::from(OwnedFd::from(reader));
+
+        writer.write_all(b"password123\n").unwrap();
+        let mut source = TimeoutRead {
+            tty: &tty,
+            deadline: Instant::now() + Duration::from_secs(10),
+        };
+        assert!(read_unbuffered(&mut source, None).is_ok());
+
+        writer.write_all(b"incompl").unwrap();
+        let mut source = TimeoutRead {
+            tty: &tty,
+            deadline: Instant::now() + Duration::from_millis(50),
+        };
+        let Err(err) = read_unbuffered(&mut source, None) else {
+            panic!("reading an incomplete password did not time out");
+        };
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
     }
 
     #[test]
@@ -8,8 +8,9 @@ use std::{
 use crate::common::{error::Error, resolve::CurrentUser, Environment};
 use crate::common::{resolve::is_valid_executable, SudoPath};
 use crate::exec::RunOptions;
+use crate::iolog::IoLogOptions;
 use crate::log::user_warn;
-use crate::system::{Group, Process, User};
+use crate::system::{file::Executable, Group, Process, User};
 
 use super::cli::SuRunOptions;
 
@@ -209,6 +210,10 @@ impl RunOptions for SuContext {
         Ok(&self.command)
     }
 
+    fn executable(&self) -> Option<&Executable> {
+        None
+    }
+
     fn arguments(&self) -> &Vec<String> {
         &self.arguments
     }
@@ -244,6 +249,22 @@ impl RunOptions for SuContext {
     fn use_pty(&self) -> bool {
         true
     }
+
+    fn iolog(&self) -> Option<&IoLogOptions> {
+        None
+    }
+
+    fn noexec(&self) -> bool {
+        false
+    }
+
+    fn background(&self) -> bool {
+        false
+    }
+
+    fn close_from(&self) -> Option<i32> {
+        None
+    }
 }
 
 #[cfg(test)]
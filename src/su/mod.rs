@@ -26,7 +26,7 @@ fn authenticate(
 ) -> Result<PamContext<CLIConverser>, Error> {
     let context = if login { "su-l" } else { "su" };
     let use_stdin = true;
-    let mut pam = PamContext::builder_cli("su", use_stdin, Default::default())
+    let mut pam = PamContext::builder_cli("su", use_stdin, Default::default(), Default::default())
         .target_user(user)
         .service_name(context)
         .build()?;
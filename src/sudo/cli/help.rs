@@ -1,14 +1,20 @@
 pub const USAGE_MSG: &str = "\
 usage: sudo -h | -K | -k | -V
-usage: sudo -v [-knS] [-g group] [-u user]
-usage: sudo -l [-knS] [-g group] [-U user] [-u user] [command [arg ...]]
-usage: sudo [-knS] [-D directory] [-g group] [-u user] [-i | -s] [command [arg ...]]
-usage: sudo -e [-knS] [-D directory] [-g group] [-u user] file ...";
+usage: sudo -v [-AknS] [-g group] [-p prompt] [-u user]
+usage: sudo -l [-AknS] [-g group] [-p prompt] [-U user] [-u user] [command [arg ...]]
+usage: sudo [-AbEknS] [-C num] [-D directory] [-g group] [-p prompt] [-u user] [-i | -s] [VAR=value] [command [arg ...]]
+usage: sudo -e [-AknS] [-D directory] [-g group] [-p prompt] [-u user] file ...";
 
 const DESCRIPTOR: &str = "sudo - run commands as another user";
 
 const HELP_MSG: &str = "Options:
+  -A, --askpass                 use a helper program for password prompting
+  -b, --background              run command in the background
+  -C, --close-from=num          close all file descriptors >= num
   -D, --chdir=directory         change the working directory before running command
+  -E, --preserve-env            preserve user environment when running command
+      --preserve-env=list       preserve specific environment variables
+  -e, --edit                    edit files instead of running a command
   -g, --group=group             run command as the specified group name or ID
   -h, --help                    display help message and exit
   -i, --login                   run login shell as the target user; a command may also be specified
@@ -16,6 +22,7 @@ const HELP_MSG: &str = "Options:
   -k, --reset-timestamp         invalidate timestamp file
   -l, --list                    list user's privileges or check a specific command; use twice for longer format
   -n, --non-interactive         non-interactive mode, no prompts are used
+  -p, --prompt=prompt           use the specified password prompt
   -S, --stdin                   read password from standard input
   -s, --shell                   run shell as the target user; a command may also be specified
   -U, --other-user=user         in list mode, display privileges for user
@@ -10,8 +10,6 @@ pub mod help;
 #[cfg(test)]
 mod tests;
 
-// remove dead_code when sudoedit has been implemented
-#[allow(dead_code)]
 pub enum SudoAction {
     Edit(SudoEditOptions),
     Help(SudoHelpOptions),
@@ -168,6 +166,10 @@ impl TryFrom<SudoOptions> for SudoResetTimestampOptions {
 
 // sudo -v [-ABkNnS] [-g group] [-h host] [-p prompt] [-u user]
 pub struct SudoValidateOptions {
+    // -A
+    pub askpass: bool,
+    // -p
+    pub prompt: Option<String>,
     // -k
     pub reset_timestamp: bool,
     // -n
@@ -188,6 +190,8 @@ impl TryFrom<SudoOptions> for SudoValidateOptions {
         let validate = mem::take(&mut opts.validate);
         debug_assert!(validate);
 
+        let askpass = mem::take(&mut opts.askpass);
+        let prompt = mem::take(&mut opts.prompt);
         let reset_timestamp = mem::take(&mut opts.reset_timestamp);
         let non_interactive = mem::take(&mut opts.non_interactive);
         let stdin = mem::take(&mut opts.stdin);
@@ -197,6 +201,8 @@ impl TryFrom<SudoOptions> for SudoValidateOptions {
         reject_all("--validate", opts)?;
 
         Ok(Self {
+            askpass,
+            prompt,
             reset_timestamp,
             non_interactive,
             stdin,
@@ -207,8 +213,11 @@ impl TryFrom<SudoOptions> for SudoValidateOptions {
 }
 
 // sudo -e [-ABkNnS] [-r role] [-t type] [-C num] [-D directory] [-g group] [-h host] [-p prompt] [-R directory] [-T timeout] [-u user] file ...
-#[allow(dead_code)]
 pub struct SudoEditOptions {
+    // -A
+    pub askpass: bool,
+    // -p
+    pub prompt: Option<String>,
     // -k
     pub reset_timestamp: bool,
     // -n
@@ -232,6 +241,8 @@ impl TryFrom<SudoOptions> for SudoEditOptions {
         let edit = mem::take(&mut opts.edit);
         debug_assert!(edit);
 
+        let askpass = mem::take(&mut opts.askpass);
+        let prompt = mem::take(&mut opts.prompt);
         let reset_timestamp = mem::take(&mut opts.reset_timestamp);
         let non_interactive = mem::take(&mut opts.non_interactive);
         let stdin = mem::take(&mut opts.stdin);
@@ -247,6 +258,8 @@ impl TryFrom<SudoOptions> for SudoEditOptions {
         }
 
         Ok(Self {
+            askpass,
+            prompt,
             reset_timestamp,
             non_interactive,
             stdin,
@@ -263,6 +276,10 @@ pub struct SudoListOptions {
     // -l OR -l -l
     pub list: List,
 
+    // -A
+    pub askpass: bool,
+    // -p
+    pub prompt: Option<String>,
     // -k
     pub reset_timestamp: bool,
     // -n
@@ -284,6 +301,8 @@ impl TryFrom<SudoOptions> for SudoListOptions {
 
     fn try_from(mut opts: SudoOptions) -> Result<Self, Self::Error> {
         let list = opts.list.take().unwrap();
+        let askpass = mem::take(&mut opts.askpass);
+        let prompt = mem::take(&mut opts.prompt);
         let reset_timestamp = mem::take(&mut opts.reset_timestamp);
         let non_interactive = mem::take(&mut opts.non_interactive);
         let stdin = mem::take(&mut opts.stdin);
@@ -304,6 +323,8 @@ impl TryFrom<SudoOptions> for SudoListOptions {
 
         Ok(Self {
             list,
+            askpass,
+            prompt,
             reset_timestamp,
             non_interactive,
             stdin,
@@ -317,8 +338,16 @@ impl TryFrom<SudoOptions> for SudoListOptions {
 
 // sudo [-ABbEHnPS] [-C num] [-D directory] [-g group] [-h host] [-p prompt] [-R directory] [-T timeout] [-u user] [VAR=value] [-i | -s] [command [arg ...]]
 pub struct SudoRunOptions {
+    // -b
+    pub background: bool,
+    // -C
+    pub close_from: Option<i32>,
     // -E
     pub preserve_env: PreserveEnv,
+    // -A
+    pub askpass: bool,
+    // -p
+    pub prompt: Option<String>,
     // -k
     pub reset_timestamp: bool,
     // -n
@@ -344,7 +373,11 @@ impl TryFrom<SudoOptions> for SudoRunOptions {
     type Error = String;
 
     fn try_from(mut opts: SudoOptions) -> Result<Self, Self::Error> {
+        let background = mem::take(&mut opts.background);
+        let close_from = mem::take(&mut opts.close_from);
         let preserve_env = mem::take(&mut opts.preserve_env);
+        let askpass = mem::take(&mut opts.askpass);
+        let prompt = mem::take(&mut opts.prompt);
         let reset_timestamp = mem::take(&mut opts.reset_timestamp);
         let non_interactive = mem::take(&mut opts.non_interactive);
         let stdin = mem::take(&mut opts.stdin);
@@ -378,7 +411,11 @@ impl TryFrom<SudoOptions> for SudoRunOptions {
         reject_all(context, opts)?;
 
         Ok(Self {
+            background,
+            close_from,
             preserve_env,
+            askpass,
+            prompt,
             reset_timestamp,
             non_interactive,
             stdin,
@@ -395,8 +432,14 @@ impl TryFrom<SudoOptions> for SudoRunOptions {
 
 #[derive(Default)]
 struct SudoOptions {
+    // -A
+    askpass: bool,
+    // -b
+    background: bool,
     // -D
     chdir: Option<SudoPath>,
+    // -C
+    close_from: Option<i32>,
     // -g
     group: Option<SudoString>,
     // -i
@@ -407,6 +450,8 @@ struct SudoOptions {
     other_user: Option<SudoString>,
     // -E
     preserve_env: PreserveEnv,
+    // -p
+    prompt: Option<String>,
     // -s
     shell: bool,
     // -S
@@ -481,9 +526,17 @@ enum SudoArg {
 }
 
 impl SudoArg {
-    const TAKES_ARGUMENT_SHORT: &'static [char] = &['D', 'g', 'h', 'R', 'U', 'u'];
-    const TAKES_ARGUMENT: &'static [&'static str] =
-        &["chdir", "group", "host", "chroot", "other-user", "user"];
+    const TAKES_ARGUMENT_SHORT: &'static [char] = &['C', 'D', 'g', 'h', 'p', 'R', 'U', 'u'];
+    const TAKES_ARGUMENT: &'static [&'static str] = &[
+        "close-from",
+        "chdir",
+        "group",
+        "host",
+        "prompt",
+        "chroot",
+        "other-user",
+        "user",
+    ];
 
     /// argument assignments and shorthand options preprocessing
     fn normalize_arguments<I>(iter: I) -> Result<Vec<Self>, String>
@@ -597,13 +650,26 @@ impl SudoOptions {
         T: Into<String> + Clone,
     {
         let mut options = Self::default();
-        let arg_iter = SudoArg::normalize_arguments(iter.into_iter().map(Into::into))?
-            .into_iter()
-            .peekable();
+        let args = iter.into_iter().map(Into::into).collect::<Vec<String>>();
+
+        // when invoked as `sudoedit`, behave as `sudo -e`
+        if let Some(invoked_as) = args.first() {
+            if std::path::Path::new(invoked_as).file_name() == Some("sudoedit".as_ref()) {
+                options.edit = true;
+            }
+        }
+
+        let arg_iter = SudoArg::normalize_arguments(args)?.into_iter().peekable();
 
         for arg in arg_iter {
             match arg {
                 SudoArg::Flag(flag) => match flag.as_str() {
+                    "-A" | "--askpass" => {
+                        options.askpass = true;
+                    }
+                    "-b" | "--background" => {
+                        options.background = true;
+                    }
                     "-E" | "--preserve-env" => {
                         options.preserve_env = PreserveEnv::Everything;
                     }
@@ -651,6 +717,12 @@ impl SudoOptions {
                     }
                 },
                 SudoArg::Argument(option, value) => match option.as_str() {
+                    "-C" | "--close-from" => {
+                        let fd = value.parse().ok().filter(|fd| *fd >= 3).ok_or(
+                            "the argument to --close-from must be a number greater than or equal to 3",
+                        )?;
+                        options.close_from = Some(fd);
+                    }
                     "-D" | "--chdir" => {
                         options.chdir = Some(SudoPath::from_cli_string(value));
                     }
@@ -668,6 +740,9 @@ impl SudoOptions {
                     "-g" | "--group" => {
                         options.group = Some(SudoString::from_cli_string(value));
                     }
+                    "-p" | "--prompt" => {
+                        options.prompt = Some(value);
+                    }
                     "-U" | "--other-user" => {
                         options.other_user = Some(SudoString::from_cli_string(value));
                     }
@@ -752,12 +827,16 @@ fn reject_all(context: &str, opts: SudoOptions) -> Result<(), String> {
     }
 
     let SudoOptions {
+        askpass,
+        background,
         chdir,
+        close_from,
         group,
         login,
         non_interactive,
         other_user,
         preserve_env,
+        prompt,
         shell,
         stdin,
         user,
@@ -773,7 +852,10 @@ fn reject_all(context: &str, opts: SudoOptions) -> Result<(), String> {
     } = opts;
 
     let flags = [
+        tuple!(askpass),
+        tuple!(background),
         tuple!(chdir),
+        tuple!(close_from),
         tuple!(edit),
         tuple!(group),
         tuple!(help),
@@ -782,6 +864,7 @@ fn reject_all(context: &str, opts: SudoOptions) -> Result<(), String> {
         tuple!(non_interactive),
         tuple!(other_user),
         tuple!(preserve_env),
+        tuple!(prompt),
         tuple!(remove_timestamp),
         tuple!(reset_timestamp),
         tuple!(shell),
@@ -800,9 +883,46 @@ fn reject_all(context: &str, opts: SudoOptions) -> Result<(), String> {
     Ok(())
 }
 
+impl From<SudoEditOptions> for OptionsForContext {
+    fn from(opts: SudoEditOptions) -> Self {
+        let SudoEditOptions {
+            askpass,
+            prompt,
+            chdir,
+            group,
+            non_interactive,
+            positional_args,
+            reset_timestamp,
+            stdin,
+            user,
+        } = opts;
+
+        Self {
+            action: ContextAction::Edit,
+
+            askpass,
+            prompt,
+            chdir,
+            group,
+            non_interactive,
+            positional_args,
+            reset_timestamp,
+            stdin,
+            user,
+
+            login: false,
+            shell: false,
+            background: false,
+            close_from: None,
+        }
+    }
+}
+
 impl From<SudoListOptions> for OptionsForContext {
     fn from(opts: SudoListOptions) -> Self {
         let SudoListOptions {
+            askpass,
+            prompt,
             group,
             non_interactive,
             positional_args,
@@ -817,6 +937,8 @@ impl From<SudoListOptions> for OptionsForContext {
         Self {
             action: ContextAction::List,
 
+            askpass,
+            prompt,
             group,
             non_interactive,
             positional_args,
@@ -827,6 +949,8 @@ impl From<SudoListOptions> for OptionsForContext {
             chdir: None,
             login: false,
             shell: false,
+            background: false,
+            close_from: None,
         }
     }
 }
@@ -834,6 +958,8 @@ impl From<SudoListOptions> for OptionsForContext {
 impl From<SudoValidateOptions> for OptionsForContext {
     fn from(opts: SudoValidateOptions) -> Self {
         let SudoValidateOptions {
+            askpass,
+            prompt,
             group,
             non_interactive,
             reset_timestamp,
@@ -844,6 +970,8 @@ impl From<SudoValidateOptions> for OptionsForContext {
         Self {
             action: ContextAction::Validate,
 
+            askpass,
+            prompt,
             group,
             non_interactive,
             reset_timestamp,
@@ -854,6 +982,8 @@ impl From<SudoValidateOptions> for OptionsForContext {
             login: false,
             positional_args: vec![],
             shell: false,
+            background: false,
+            close_from: None,
         }
     }
 }
@@ -861,7 +991,11 @@ impl From<SudoValidateOptions> for OptionsForContext {
 impl From<SudoRunOptions> for OptionsForContext {
     fn from(opts: SudoRunOptions) -> Self {
         let SudoRunOptions {
+            askpass,
+            prompt,
+            background,
             chdir,
+            close_from,
             group,
             login,
             non_interactive,
@@ -878,7 +1012,11 @@ impl From<SudoRunOptions> for OptionsForContext {
         Self {
             action: ContextAction::Run,
 
+            askpass,
+            prompt,
+            background,
             chdir,
+            close_from,
             group,
             login,
             non_interactive,
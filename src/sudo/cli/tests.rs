@@ -274,6 +274,68 @@ fn directory() {
     assert_eq!(cmd.chdir, Some(SudoPath::from("/some/path")));
 }
 
+#[test]
+fn askpass() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-A", "true"]).unwrap();
+    assert!(cmd.askpass);
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--askpass", "true"]).unwrap();
+    assert!(cmd.askpass);
+
+    let cmd = SudoAction::try_parse_from(["sudo", "-A", "-v"]).unwrap();
+    assert!(cmd.is_validate());
+
+    let cmd = SudoAction::try_parse_from(["sudo", "-A", "-K"]);
+    assert!(cmd.is_err());
+}
+
+#[test]
+fn prompt() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-p", "%u's password: ", "true"]).unwrap();
+    assert_eq!(cmd.prompt.as_deref(), Some("%u's password: "));
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--prompt=secret? ", "true"]).unwrap();
+    assert_eq!(cmd.prompt.as_deref(), Some("secret? "));
+
+    let cmd = SudoAction::try_parse_from(["sudo", "-l", "-p", "secret? "]).unwrap();
+    assert!(cmd.is_list());
+
+    let cmd = SudoAction::try_parse_from(["sudo", "-K", "-p", "secret? "]);
+    assert!(cmd.is_err());
+}
+
+#[test]
+fn background() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-b", "true"]).unwrap();
+    assert!(cmd.background);
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--background", "true"]).unwrap();
+    assert!(cmd.background);
+
+    let cmd = SudoAction::try_parse_from(["sudo", "-l", "-b"]);
+    assert!(cmd.is_err());
+}
+
+#[test]
+fn close_from() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-C5", "true"]).unwrap();
+    assert_eq!(cmd.close_from, Some(5));
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--close-from", "3", "true"]).unwrap();
+    assert_eq!(cmd.close_from, Some(3));
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--close-from=10", "true"]).unwrap();
+    assert_eq!(cmd.close_from, Some(10));
+
+    for invalid in ["2", "-1", "three"] {
+        let cmd = SudoOptions::try_parse_from(["sudo", "-C", invalid, "true"]);
+        assert!(cmd.is_err());
+    }
+
+    let cmd = SudoAction::try_parse_from(["sudo", "-v", "-C", "5"]);
+    assert!(cmd.is_err());
+}
+
 #[test]
 fn group() {
     let cmd = SudoOptions::try_parse_from(["sudo", "-grustaceans"]).unwrap();
@@ -337,6 +399,22 @@ fn edit() {
     assert!(res.is_err());
 }
 
+#[test]
+fn invoked_as_sudoedit() {
+    let cmd = SudoAction::try_parse_from(["sudoedit", "filepath"]).unwrap();
+    assert!(cmd.is_edit());
+
+    let cmd =
+        SudoAction::try_parse_from(["/usr/bin/sudoedit", "-u", "ferris", "filepath"]).unwrap();
+    assert!(cmd.is_edit());
+
+    let res = SudoAction::try_parse_from(["sudoedit"]);
+    assert!(res.is_err());
+
+    let res = SudoAction::try_parse_from(["sudoedit", "-l"]);
+    assert!(res.is_err());
+}
+
 #[test]
 fn help() {
     let cmd = SudoAction::try_parse_from(["sudo", "-h"]).unwrap();
@@ -0,0 +1,309 @@
+#![forbid(unsafe_code)]
+
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::common::{Context, Error};
+use crate::system::file::{create_temporary_dir, Chown, Directory};
+use crate::system::interface::{GroupId, UserId};
+use crate::system::{set_target_user, with_identity, Group, User};
+
+/// The mode, owner and group of a file
+type FileAttributes = (u32, UserId, GroupId);
+
+/// A file that is being edited, as it was found on disk before the edit session
+struct EditFile {
+    path: PathBuf,
+    // the directory that contains the file, and the name of the file in it
+    dir: Directory,
+    name: OsString,
+    contents: Vec<u8>,
+    // `None` if the file does not exist yet
+    metadata: Option<FileAttributes>,
+    tmp_path: PathBuf,
+}
+
+/// Edit the files listed in the command of `context` (see `CommandAndArguments::build_for_edit`):
+/// they are copied to a temporary location, edited with `editor` by the invoking user, and
+/// then copied back with the privileges of the target user.
+pub(super) fn edit_files(context: &Context, editor: &Path) -> Result<(), Error> {
+    let target_user = &context.target_user;
+    let target_gid = context.target_group.gid;
+    let user: User = context.current_user.clone().into();
+
+    // read the original files as the target user
+    let mut files = Vec::with_capacity(context.command.arguments.len());
+    for path in &context.command.arguments {
+        let path = PathBuf::from(path);
+        let (dir, name, contents, metadata) =
+            as_user(target_user, target_gid, || read_original(&path, &user))
+                .map_err(|err| Error::InvalidEditFile(path.clone(), err))?;
+        files.push(EditFile {
+            path,
+            dir,
+            name,
+            contents,
+            metadata,
+            tmp_path: PathBuf::new(),
+        });
+    }
+
+    // make copies that the invoking user can edit
+    let tmp_dir = as_user(&user, user.gid, || {
+        let tmp_dir = create_temporary_dir("/var/tmp/sudoedit")?;
+        for (i, file) in files.iter_mut().enumerate() {
+            file.tmp_path = tmp_dir.join(temporary_name(i, &file.path, &tmp_dir));
+            write_new(&file.tmp_path, &file.contents, 0o600)?;
+        }
+        Ok(tmp_dir)
+    })?;
+
+    let remove_tmp_dir = || {
+        let _ = as_user(&user, user.gid, || fs::remove_dir_all(&tmp_dir));
+    };
+
+    if let Err(err) = run_editor(context, editor, &files, &user) {
+        remove_tmp_dir();
+        return Err(err);
+    }
+
+    if save_files(context, &files, &user)? {
+        remove_tmp_dir();
+        Ok(())
+    } else {
+        eprintln_ignore_io_error!(
+            "sudo-rs: contents of edit session left in {}",
+            tmp_dir.display()
+        );
+        Err(Error::Silent)
+    }
+}
+
+fn as_user<T>(user: &User, gid: GroupId, func: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    with_identity(user, gid, func)?
+}
+
+/// Pick a file name in the temporary directory that resembles the original one, so that an
+/// editor can still use it to determine e.g. syntax highlighting
+fn temporary_name(index: usize, path: &Path, tmp_dir: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or(OsStr::new("file"));
+    if tmp_dir.join(name).exists() {
+        let mut prefixed = OsString::from(format!("{index}-"));
+        prefixed.push(name);
+        PathBuf::from(prefixed)
+    } else {
+        PathBuf::from(name)
+    }
+}
+
+/// Whether `user` can change the contents of the directory described by `meta`, or can give
+/// themselves the permission to do so
+fn writable_by(meta: &fs::Metadata, user: &User) -> bool {
+    let mode = meta.mode();
+    let in_group = meta.gid() == user.gid || user.groups.contains(&meta.gid());
+
+    meta.uid() == user.uid || mode & 0o002 != 0 || (in_group && mode & 0o020 != 0)
+}
+
+/// Read a file to be edited, together with the directory that contains it. Symbolic links are
+/// not followed in any component of the path, and files in directories that the invoking `user`
+/// can write to are refused, since they could replace the file with a link while it is edited.
+fn read_original(
+    path: &Path,
+    user: &User,
+) -> io::Result<(Directory, OsString, Vec<u8>, Option<FileAttributes>)> {
+    let symlink_error = |err: io::Error| {
+        if err.raw_os_error() == Some(libc::ELOOP) {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "editing symbolic links is not permitted",
+            )
+        } else {
+            err
+        }
+    };
+
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a regular file",
+        ));
+    };
+
+    let dir = Directory::open_nofollow(parent, |dir, meta| {
+        if user.uid != 0 && writable_by(meta, user) {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "editing files in a writable directory is not permitted: {}",
+                    dir.display()
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    })
+    .map_err(symlink_error)?;
+
+    let mut file = match dir.open_file(
+        name,
+        libc::O_RDONLY | libc::O_NOFOLLOW | libc::O_NONBLOCK,
+        0,
+    ) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok((dir, name.to_owned(), Vec::new(), None))
+        }
+        Err(err) => return Err(symlink_error(err)),
+    };
+
+    let meta = file.metadata()?;
+    if !meta.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a regular file",
+        ));
+    }
+
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+
+    Ok((
+        dir,
+        name.to_owned(),
+        contents,
+        Some((meta.mode() & 0o7777, meta.uid(), meta.gid())),
+    ))
+}
+
+fn write_new(path: &Path, contents: &[u8], mode: u32) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(path)?;
+    file.write_all(contents)
+}
+
+fn run_editor(
+    context: &Context,
+    editor: &Path,
+    files: &[EditFile],
+    user: &User,
+) -> Result<(), Error> {
+    let group = Group::from_gid(user.gid)?.unwrap_or_else(|| Group {
+        gid: user.gid,
+        name: user.gid.to_string(),
+    });
+
+    let mut command = Command::new(editor);
+    command
+        .arg("--")
+        .args(files.iter().map(|file| &file.tmp_path));
+    if let Some(dir) = &context.chdir {
+        command.current_dir(dir);
+    }
+    set_target_user(&mut command, user.clone(), group);
+
+    let status = command
+        .status()
+        .map_err(|err| Error::Io(Some(editor.to_owned()), err))?;
+
+    if !status.success() {
+        eprintln_ignore_io_error!(
+            "sudo-rs: {} unexpectedly terminated, files left unchanged",
+            editor.display()
+        );
+        return Err(Error::Silent);
+    }
+
+    Ok(())
+}
+
+/// Copy the edited files back to their original location; returns whether this succeeded for
+/// every file that was changed
+fn save_files(context: &Context, files: &[EditFile], user: &User) -> Result<bool, Error> {
+    let mut success = true;
+
+    for file in files {
+        let contents = as_user(user, user.gid, || {
+            let mut contents = Vec::new();
+            OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NOFOLLOW)
+                .open(&file.tmp_path)?
+                .read_to_end(&mut contents)?;
+            Ok(contents)
+        })
+        .map_err(|err| Error::Io(Some(file.tmp_path.clone()), err))?;
+
+        if contents == file.contents {
+            eprintln_ignore_io_error!("sudo-rs: {} unchanged", file.path.display());
+            continue;
+        }
+
+        let result = as_user(&context.target_user, context.target_group.gid, || {
+            write_back(file, &contents)
+        });
+        if let Err(err) = result {
+            eprintln_ignore_io_error!("sudo-rs: {}: {err}", file.path.display());
+            success = false;
+        }
+    }
+
+    Ok(success)
+}
+
+/// Replace the original file with new contents, preserving its mode and ownership; this is
+/// done by renaming a new file over the original one, so that the original is never left in
+/// a partially written state. All of this happens relative to the directory that was checked
+/// when the file was read.
+fn write_back(file: &EditFile, contents: &[u8]) -> io::Result<()> {
+    let (mode, owner) = match file.metadata {
+        Some((mode, uid, gid)) => (mode, Some((uid, gid))),
+        None => (0o644, None),
+    };
+    let dir = &file.dir;
+
+    let mut staged_name = OsString::from(".");
+    staged_name.push(&file.name);
+    staged_name.push(".sudoedit~");
+
+    let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_NOFOLLOW;
+    if let Ok(mut new) = dir.open_file(&staged_name, flags, 0o600) {
+        let replaced = (|| -> io::Result<()> {
+            new.write_all(contents)?;
+            if let Some((uid, gid)) = owner {
+                let meta = new.metadata()?;
+                if (meta.uid(), meta.gid()) != (uid, gid) {
+                    new.chown(uid, gid)?;
+                }
+            }
+            new.set_permissions(fs::Permissions::from_mode(mode))?;
+            new.sync_all()?;
+            dir.rename(&staged_name, &file.name)
+        })();
+
+        if replaced.is_ok() {
+            return Ok(());
+        }
+        let _ = dir.remove_file(&staged_name);
+    }
+
+    // we may not be allowed to create files in the directory; overwrite the file in place, and
+    // only cut off what remains of the old contents afterwards
+    let create = if file.metadata.is_none() {
+        libc::O_CREAT
+    } else {
+        0
+    };
+    let mut original =
+        dir.open_file(&file.name, libc::O_WRONLY | libc::O_NOFOLLOW | create, mode)?;
+    original.write_all(contents)?;
+    original.set_len(contents.len() as u64)?;
+    original.sync_all()
+}
@@ -4,7 +4,8 @@ use std::{
     os::unix::prelude::OsStrExt,
 };
 
-use crate::common::{CommandAndArguments, Context, Environment};
+use crate::common::context::LaunchType;
+use crate::common::{CommandAndArguments, Context, Environment, Error};
 use crate::sudoers::Policy;
 use crate::system::PATH_MAX;
 
@@ -42,6 +43,7 @@ fn add_extra_env(
     context: &Context,
     cfg: &impl Policy,
     sudo_ps1: Option<OsString>,
+    preserve_env: bool,
     environment: &mut Environment,
 ) {
     // current user
@@ -60,11 +62,15 @@ fn add_extra_env(
         entry.insert(format!("{PATH_MAILDIR}/{}", context.target_user.name).into());
     }
     // The current SHELL variable should determine the shell to run when -s is passed, if none set use passwd entry
-    environment.insert("SHELL".into(), context.target_user.shell.clone().into());
+    if !preserve_env || !environment.contains_key(OsStr::new("SHELL")) {
+        environment.insert("SHELL".into(), context.target_user.shell.clone().into());
+    }
     // HOME' Set to the home directory of the target user if -i or -H are specified, env_reset or always_set_home are
     // set in sudoers, or when the -s option is specified and set_home is set in sudoers.
-    // Since we always want to do env_reset -> always set HOME
-    if let Entry::Vacant(entry) = environment.entry("HOME".into()) {
+    // Unless always_set_home is set, a HOME preserved from the invoking user's environment is kept
+    if cfg.always_set_home() {
+        environment.insert("HOME".into(), context.target_user.home.clone().into());
+    } else if let Entry::Vacant(entry) = environment.entry("HOME".into()) {
         entry.insert(context.target_user.home.clone().into());
     }
 
@@ -152,29 +158,97 @@ fn is_safe_tz(value: &[u8]) -> bool {
         && check_value.len() < PATH_MAX as usize
 }
 
-/// Check whether the needle exists in a haystack, in which the haystack is a list of patterns, possibly containing wildcards
-fn in_table(needle: &OsStr, haystack: &HashSet<String>) -> bool {
-    haystack
-        .iter()
-        .any(|pattern| wildcard_match(needle.as_bytes(), pattern.as_bytes()))
+/// Check whether a variable matches a haystack, in which the haystack is a list of patterns,
+/// possibly containing wildcards. A pattern of the form `NAME=value` only matches if the value
+/// matches as well; `Some(true)` is returned if such a pattern matched, and `Some(false)` if only
+/// the name of the variable was matched.
+fn find_in_table(key: &OsStr, value: &OsStr, haystack: &HashSet<String>) -> Option<bool> {
+    let mut result = None;
+    for pattern in haystack {
+        match pattern.split_once('=') {
+            Some((key_pattern, value_pattern)) => {
+                if wildcard_match(key.as_bytes(), key_pattern.as_bytes())
+                    && wildcard_match(value.as_bytes(), value_pattern.as_bytes())
+                {
+                    return Some(true);
+                }
+            }
+            None => {
+                if wildcard_match(key.as_bytes(), pattern.as_bytes()) {
+                    result = Some(false);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+fn in_table(key: &OsStr, value: &OsStr, haystack: &HashSet<String>) -> bool {
+    find_in_table(key, value, haystack).is_some()
 }
 
 /// Determine whether a specific environment variable should be kept
 fn should_keep(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
-    if value.as_bytes().starts_with("()".as_bytes()) {
+    let keep = find_in_table(key, value, cfg.env_keep());
+    let check = find_in_table(key, value, cfg.env_check());
+
+    // values that look like shell functions are only kept if they were explicitly allowed
+    if value.as_bytes().starts_with("()".as_bytes()) && keep != Some(true) && check != Some(true) {
         return false;
     }
 
     if key == "TZ" {
-        return in_table(key, cfg.env_keep())
-            || (in_table(key, cfg.env_check()) && is_safe_tz(value.as_bytes()));
+        return keep.is_some() || (check.is_some() && is_safe_tz(value.as_bytes()));
     }
 
-    if in_table(key, cfg.env_check()) {
+    if check.is_some() {
         return !value.as_bytes().iter().any(|c| *c == b'%' || *c == b'/');
     }
 
-    in_table(key, cfg.env_keep())
+    keep.is_some()
+}
+
+/// Determine whether a specific environment variable should be removed when the invoking user's
+/// environment is preserved in its entirety
+fn should_delete(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
+    value.as_bytes().starts_with("()".as_bytes())
+        || in_table(key, value, cfg.env_delete())
+        || (in_table(key, value, cfg.env_check()) && !should_keep(key, value, cfg))
+}
+
+/// Check that the user is allowed to pass the requested environment variables to the command.
+/// Unless the policy permits setting any variable (the `SETENV` tag), `--preserve-env` without
+/// a list of variables is refused, and only variables that would be preserved from the invoking
+/// user's environment anyway can be set; furthermore `PATH` cannot be overridden if a
+/// `secure_path` is configured.
+pub fn validate_user_env(
+    user_override: &[(String, String)],
+    preserve_env: bool,
+    settings: &impl Policy,
+) -> Result<(), Error> {
+    if settings.allows_setenv() {
+        return Ok(());
+    }
+
+    if preserve_env {
+        return Err(Error::PreserveEnvNotAllowed);
+    }
+
+    let forbidden = user_override
+        .iter()
+        .filter(|(key, value)| {
+            (key == "PATH" && settings.secure_path().is_some())
+                || !should_keep(OsStr::new(key), OsStr::new(value), settings)
+        })
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+
+    if forbidden.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::EnvVarNotAllowed(forbidden))
+    }
 }
 
 /// Construct the final environment from the current one and a sudo context
@@ -190,13 +264,22 @@ fn should_keep(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
 /// If the PATH and TERM variables are not preserved from the user's environment, they will be set to default value
 ///
 /// Environment variables with a value beginning with ‘()’ are removed
+///
+/// If `preserve_env` is set, or env_reset is disabled, all variables are preserved except those
+/// matching env_delete (and those matching env_check that have unsafe values); this never applies
+/// to a login shell (`sudo -i`), which always starts from a reset environment. The variables in
+/// `user_override` are set last, and should have been checked using [validate_user_env].
 pub fn get_target_environment(
     current_env: Environment,
     additional_env: Environment,
+    user_override: Vec<(String, String)>,
+    preserve_env: bool,
     context: &Context,
     settings: &impl Policy,
 ) -> Environment {
     let mut environment = Environment::default();
+    let preserve_env =
+        context.launch != LaunchType::Login && (preserve_env || !settings.env_reset());
 
     // retrieve SUDO_PS1 value to set a PS1 value as additional environment
     let sudo_ps1 = current_env.get(OsStr::new("SUDO_PS1")).cloned();
@@ -205,26 +288,48 @@ pub fn get_target_environment(
     // env_keep list take precedence over those in the PAM environment
     environment.extend(additional_env);
 
+    if preserve_env {
+        environment.extend(
+            current_env
+                .into_iter()
+                .filter(|(key, value)| !should_delete(key, value, settings)),
+        );
+
+        // these identify the target user, even if the rest of the environment is preserved
+        for key in ["LOGNAME", "USER", "MAIL"] {
+            environment.remove(OsStr::new(key));
+        }
+    } else {
+        environment.extend(
+            current_env
+                .into_iter()
+                .filter(|(key, value)| should_keep(key, value, settings)),
+        );
+    }
+
+    add_extra_env(context, settings, sudo_ps1, preserve_env, &mut environment);
+
+    // variables explicitly set on the command line take precedence over everything else
     environment.extend(
-        current_env
+        user_override
             .into_iter()
-            .filter(|(key, value)| should_keep(key, value, settings)),
+            .map(|(key, value)| (key.into(), value.into())),
     );
 
-    add_extra_env(context, settings, sudo_ps1, &mut environment);
-
     environment
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{is_safe_tz, should_keep, PATH_ZONEINFO};
+    use super::{is_safe_tz, should_delete, should_keep, validate_user_env, PATH_ZONEINFO};
+    use crate::common::Error;
     use crate::sudoers::Policy;
     use std::{collections::HashSet, ffi::OsStr};
 
     struct TestConfiguration {
         keep: HashSet<String>,
         check: HashSet<String>,
+        delete: HashSet<String>,
     }
 
     impl Policy for TestConfiguration {
@@ -236,6 +341,10 @@ mod tests {
             &self.check
         }
 
+        fn env_delete(&self) -> &HashSet<String> {
+            &self.delete
+        }
+
         fn secure_path(&self) -> Option<String> {
             None
         }
@@ -250,6 +359,7 @@ mod tests {
         let config = TestConfiguration {
             keep: HashSet::from(["AAP".to_string(), "NOOT".to_string()]),
             check: HashSet::from(["MIES".to_string(), "TZ".to_string()]),
+            delete: HashSet::from(["LD_*".to_string()]),
         };
 
         let check_should_keep = |key: &str, value: &str, expected: bool| {
@@ -271,6 +381,90 @@ mod tests {
         check_should_keep("MIES", "FOO%", false);
     }
 
+    #[test]
+    fn test_filtering_with_values() {
+        let config = TestConfiguration {
+            keep: HashSet::from(["PAGER=less".to_string(), "FUNC=()*".to_string()]),
+            check: HashSet::from(["EDITOR=vi*".to_string()]),
+            delete: HashSet::new(),
+        };
+
+        let check_should_keep = |key: &str, value: &str, expected: bool| {
+            assert_eq!(
+                should_keep(OsStr::new(key), OsStr::new(value), &config),
+                expected,
+                "{}={} should {}",
+                key,
+                value,
+                if expected { "be kept" } else { "not be kept" }
+            );
+        };
+
+        check_should_keep("PAGER", "less", true);
+        check_should_keep("PAGER", "less -R", false);
+        check_should_keep("PAGER", "/usr/bin/evil", false);
+        check_should_keep("FUNC", "() { true; }", true);
+        check_should_keep("EDITOR", "vim", true);
+        check_should_keep("EDITOR", "vi/../evil", false);
+        check_should_keep("EDITOR", "emacs", false);
+    }
+
+    #[test]
+    fn test_deletion() {
+        let config = TestConfiguration {
+            keep: HashSet::from(["AAP".to_string()]),
+            check: HashSet::from(["MIES".to_string()]),
+            delete: HashSet::from(["LD_*".to_string()]),
+        };
+
+        let check_should_delete = |key: &str, value: &str, expected: bool| {
+            assert_eq!(
+                should_delete(OsStr::new(key), OsStr::new(value), &config),
+                expected,
+                "{} should {}",
+                key,
+                if expected {
+                    "be deleted"
+                } else {
+                    "not be deleted"
+                }
+            );
+        };
+
+        check_should_delete("AAP", "FOO", false);
+        check_should_delete("NOOT", "FOO", false);
+        check_should_delete("LD_PRELOAD", "/tmp/evil.so", true);
+        check_should_delete("NOOT", "() { evil; }", true);
+        check_should_delete("MIES", "BAR", false);
+        check_should_delete("MIES", "FOO/BAR", true);
+    }
+
+    #[test]
+    fn test_user_env_validation() {
+        let config = TestConfiguration {
+            keep: HashSet::from(["AAP".to_string(), "PATH".to_string()]),
+            check: HashSet::from(["MIES".to_string()]),
+            delete: HashSet::new(),
+        };
+
+        let vars = |list: &[(&str, &str)]| {
+            list.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(validate_user_env(&vars(&[("AAP", "1"), ("MIES", "2")]), false, &config).is_ok());
+        assert!(validate_user_env(&vars(&[("PATH", "/tmp")]), false, &config).is_ok());
+        assert!(matches!(
+            validate_user_env(&vars(&[("AAP", "1"), ("NOOT", "2"), ("MIES", "a/b")]), false, &config),
+            Err(Error::EnvVarNotAllowed(names)) if names == ["NOOT", "MIES"]
+        ));
+        assert!(matches!(
+            validate_user_env(&[], true, &config),
+            Err(Error::PreserveEnvNotAllowed)
+        ));
+    }
+
     #[allow(clippy::useless_format)]
     #[allow(clippy::bool_assert_comparison)]
     #[test]
@@ -4,8 +4,10 @@ use crate::sudo::{
     cli::{SudoAction, SudoRunOptions},
     env::environment::get_target_environment,
 };
+use crate::sudoers::Policy;
 use crate::system::{Group, Hostname, Process, User};
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 
 const TESTS: &str = "
 > env
@@ -131,10 +133,17 @@ fn create_test_context(sudo_options: &SudoRunOptions) -> Context {
         launch: crate::common::context::LaunchType::Direct,
         chdir: sudo_options.chdir.clone(),
         stdin: sudo_options.stdin,
+        askpass: None,
+        prompt: sudo_options.prompt.clone(),
         non_interactive: sudo_options.non_interactive,
+        background: sudo_options.background,
+        close_from: sudo_options.close_from,
         process: Process::new(),
         use_session_records: false,
         use_pty: true,
+        iolog: None,
+        noexec: false,
+        executable: Default::default(),
     }
 }
 
@@ -159,8 +168,14 @@ fn test_environment_variable_filtering() {
             .unwrap();
         let settings = crate::sudoers::Judgement::default();
         let context = create_test_context(&options);
-        let resulting_env =
-            get_target_environment(initial_env.clone(), HashMap::new(), &context, &settings);
+        let resulting_env = get_target_environment(
+            initial_env.clone(),
+            HashMap::new(),
+            Vec::new(),
+            false,
+            &context,
+            &settings,
+        );
 
         let resulting_env = environment_to_set(resulting_env);
         let expected_env = environment_to_set(expected_env);
@@ -176,3 +191,123 @@ fn test_environment_variable_filtering() {
         );
     }
 }
+
+struct EnvSettings {
+    env_reset: bool,
+    always_set_home: bool,
+    defaults: crate::sudoers::Judgement,
+}
+
+impl Policy for EnvSettings {
+    fn env_reset(&self) -> bool {
+        self.env_reset
+    }
+
+    fn env_keep(&self) -> &HashSet<String> {
+        self.defaults.env_keep()
+    }
+
+    fn env_check(&self) -> &HashSet<String> {
+        self.defaults.env_check()
+    }
+
+    fn env_delete(&self) -> &HashSet<String> {
+        self.defaults.env_delete()
+    }
+
+    fn always_set_home(&self) -> bool {
+        self.always_set_home
+    }
+
+    fn secure_path(&self) -> Option<String> {
+        None
+    }
+
+    fn use_pty(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_environment_without_env_reset() {
+    let mut initial_env = parse_env_commands(TESTS).remove(0).1;
+    initial_env.insert("LD_PRELOAD".into(), "/tmp/evil.so".into());
+    initial_env.insert("BASH_FUNC_x%%".into(), "() { evil; }".into());
+
+    let options = SudoAction::try_parse_from(["sudo", "env"])
+        .unwrap()
+        .try_into_run()
+        .ok()
+        .unwrap();
+    let context = create_test_context(&options);
+    let target_env = |settings: &EnvSettings| {
+        get_target_environment(
+            initial_env.clone(),
+            HashMap::new(),
+            Vec::new(),
+            false,
+            &context,
+            settings,
+        )
+    };
+
+    let settings = EnvSettings {
+        env_reset: false,
+        always_set_home: false,
+        defaults: Default::default(),
+    };
+    let env = target_env(&settings);
+    assert_eq!(env[OsStr::new("FOO")], "BAR");
+    assert_eq!(env[OsStr::new("HOME")], "/home/test");
+    assert_eq!(env[OsStr::new("USER")], "root");
+    assert!(!env.contains_key(OsStr::new("LD_PRELOAD")));
+    assert!(!env.contains_key(OsStr::new("BASH_FUNC_x%%")));
+
+    let settings = EnvSettings {
+        always_set_home: true,
+        ..settings
+    };
+    let env = target_env(&settings);
+    assert_eq!(env[OsStr::new("FOO")], "BAR");
+    assert_eq!(env[OsStr::new("HOME")], "/root");
+
+    let settings = EnvSettings {
+        env_reset: true,
+        ..settings
+    };
+    let env = target_env(&settings);
+    assert!(!env.contains_key(OsStr::new("FOO")));
+    assert_eq!(env[OsStr::new("HOME")], "/root");
+}
+
+#[test]
+fn test_login_shell_resets_environment() {
+    let initial_env = parse_env_commands(TESTS).remove(0).1;
+
+    let options = SudoAction::try_parse_from(["sudo", "-i", "env"])
+        .unwrap()
+        .try_into_run()
+        .ok()
+        .unwrap();
+    let mut context = create_test_context(&options);
+    context.launch = crate::common::context::LaunchType::Login;
+
+    let settings = EnvSettings {
+        env_reset: false,
+        always_set_home: false,
+        defaults: Default::default(),
+    };
+    for preserve_env in [false, true] {
+        let env = get_target_environment(
+            initial_env.clone(),
+            HashMap::new(),
+            Vec::new(),
+            preserve_env,
+            &context,
+            &settings,
+        );
+        assert!(!env.contains_key(OsStr::new("FOO")));
+        assert_eq!(env[OsStr::new("HOME")], "/root");
+        assert_eq!(env[OsStr::new("SHELL")], "/bin/bash");
+    }
+}
@@ -3,7 +3,7 @@
 use crate::common::resolve::CurrentUser;
 use crate::common::{Context, Error};
 use crate::log::dev_info;
-use crate::system::timestamp::RecordScope;
+use crate::system::timestamp::{RecordScope, TimestampType};
 use crate::system::User;
 use crate::system::{time::Duration, timestamp::SessionRecordFile, Process};
 use cli::help;
@@ -17,6 +17,7 @@ use std::path::Path;
 
 mod cli;
 pub mod diagnostic;
+mod edit;
 mod env;
 mod pam;
 mod pipeline;
@@ -73,6 +74,7 @@ impl PolicyPlugin for SudoersPolicy {
                 group: &context.target_group,
                 command: &context.command.command,
                 arguments: &context.command.arguments,
+                digest: &|algorithm| context.open_executable()?.digest(algorithm),
             },
         ))
     }
@@ -109,11 +111,19 @@ fn sudo_process() -> Result<(), Error> {
                 Ok(())
             }
             SudoAction::ResetTimestamp(_) => {
-                if let Some(scope) = RecordScope::for_process(&Process::new()) {
+                // the sudoers file is not consulted, so invalidate the records of any timestamp type
+                let process = Process::new();
+                let scopes: Vec<_> = TimestampType::ALL
+                    .into_iter()
+                    .filter_map(|timestamp_type| RecordScope::for_process(&process, timestamp_type))
+                    .collect();
+                if !scopes.is_empty() {
                     let user = CurrentUser::resolve()?;
                     let mut record_file =
                         SessionRecordFile::open_for_user(&user, Duration::seconds(0))?;
-                    record_file.disable(scope, None)?;
+                    for scope in scopes {
+                        record_file.disable(scope, None)?;
+                    }
                 }
                 Ok(())
             }
@@ -128,10 +138,7 @@ fn sudo_process() -> Result<(), Error> {
                 }
             }
             SudoAction::List(options) => pipeline.run_list(options),
-            SudoAction::Edit(_) => {
-                eprintln_ignore_io_error!("error: `--edit` flag has not yet been implemented");
-                std::process::exit(1);
-            }
+            SudoAction::Edit(options) => pipeline.run_edit(options),
         },
         Err(e) => {
             eprintln_ignore_io_error!("{e}\n{}", help::USAGE_MSG);
@@ -4,12 +4,16 @@ use std::ffi::OsString;
 use crate::common::context::LaunchType;
 use crate::common::{error::Error, Context};
 use crate::log::{dev_info, user_warn};
-use crate::pam::{CLIConverser, Converser, PamContext, PamError, PamErrorType, PamResult};
+use crate::pam::{
+    Askpass, CLIConverser, Converser, PamContext, PamError, PamErrorType, PamResult, PromptOptions,
+};
+use crate::sudoers::AuthorizationAllowed;
 use crate::system::term::current_tty_name;
+use crate::system::{Group, User};
 
 use super::pipeline::AuthPlugin;
 
-type PamBuilder<C> = dyn Fn(&Context) -> PamResult<PamContext<C>>;
+type PamBuilder<C> = dyn Fn(&Context, &AuthorizationAllowed) -> PamResult<PamContext<C>>;
 
 pub struct PamAuthenticator<C: Converser> {
     builder: Box<PamBuilder<C>>,
@@ -18,7 +22,7 @@ pub struct PamAuthenticator<C: Converser> {
 
 impl<C: Converser> PamAuthenticator<C> {
     fn new(
-        initializer: impl Fn(&Context) -> PamResult<PamContext<C>> + 'static,
+        initializer: impl Fn(&Context, &AuthorizationAllowed) -> PamResult<PamContext<C>> + 'static,
     ) -> PamAuthenticator<C> {
         PamAuthenticator {
             builder: Box::new(initializer),
@@ -29,12 +33,46 @@ impl<C: Converser> PamAuthenticator<C> {
 
 impl PamAuthenticator<CLIConverser> {
     pub fn new_cli() -> PamAuthenticator<CLIConverser> {
-        PamAuthenticator::new(|context| {
+        PamAuthenticator::new(|context, auth| {
+            let user = &context.current_user;
+            // the askpass program runs with the credentials of the invoking user
+            let askpass = match &context.askpass {
+                Some(program) => {
+                    let group = Group::from_gid(user.gid)?.unwrap_or_else(|| Group {
+                        gid: user.gid,
+                        name: user.gid.to_string(),
+                    });
+                    Some(Askpass::new(program.clone(), User::clone(user), group))
+                }
+                None => None,
+            };
+            let options = PromptOptions {
+                askpass,
+                // a prompt given on the command line takes precedence over the policy
+                prompt: context
+                    .prompt
+                    .as_deref()
+                    .or(auth.prompt.as_deref())
+                    .map(|prompt| {
+                        expand_prompt(
+                            prompt,
+                            &context.hostname,
+                            &user.name,
+                            &context.target_user.name,
+                        )
+                    }),
+                prompt_override: auth.prompt_override,
+                pwfeedback: auth.pwfeedback,
+                visiblepw: auth.visiblepw,
+                timeout: auth.password_timeout,
+            };
+
             init_pam(
                 matches!(context.launch, LaunchType::Login),
                 matches!(context.launch, LaunchType::Shell),
                 context.stdin,
                 context.non_interactive,
+                options,
                 &context.current_user.name,
                 &context.current_user.name,
             )
@@ -43,8 +81,8 @@ impl PamAuthenticator<CLIConverser> {
 }
 
 impl<C: Converser> AuthPlugin for PamAuthenticator<C> {
-    fn init(&mut self, context: &Context) -> Result<(), Error> {
-        self.pam = Some((self.builder)(context)?);
+    fn init(&mut self, context: &Context, auth: &AuthorizationAllowed) -> Result<(), Error> {
+        self.pam = Some((self.builder)(context, auth)?);
         Ok(())
     }
 
@@ -108,11 +146,12 @@ pub fn init_pam(
     is_shell: bool,
     use_stdin: bool,
     non_interactive: bool,
+    prompt_options: PromptOptions,
     auth_user: &str,
     requesting_user: &str,
 ) -> PamResult<PamContext<CLIConverser>> {
     let service_name = if is_login_shell { "sudo-i" } else { "sudo" };
-    let mut pam = PamContext::builder_cli("sudo", use_stdin, non_interactive)
+    let mut pam = PamContext::builder_cli("sudo", use_stdin, non_interactive, prompt_options)
         .service_name(service_name)
         .build()?;
     pam.mark_silent(!is_shell && !is_login_shell);
@@ -128,6 +167,37 @@ pub fn init_pam(
     Ok(pam)
 }
 
+/// Expand the escape sequences in a password prompt: `%H` and `%h` for the (short) host name, `%p`
+/// for the user whose password is asked for, `%U` for the target user, `%u` for the invoking user
+/// and `%%` for a single `%`
+fn expand_prompt(prompt: &str, hostname: &str, user: &str, target_user: &str) -> String {
+    let mut expanded = String::with_capacity(prompt.len());
+    let mut chars = prompt.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('H') => expanded.push_str(hostname),
+            Some('h') => expanded.push_str(hostname.split('.').next().unwrap_or(hostname)),
+            // only the invoking user is asked for their password
+            Some('p' | 'u') => expanded.push_str(user),
+            Some('U') => expanded.push_str(target_user),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    expanded
+}
+
 pub fn attempt_authenticate<C: Converser>(
     pam: &mut PamContext<C>,
     non_interactive: bool,
@@ -157,6 +227,11 @@ pub fn attempt_authenticate<C: Converser>(
                 }
             }
 
+            // the user could not be asked for their password
+            Err(PamError::InteractionRequired) => {
+                return Err(Error::Authentication("interaction required".to_string()));
+            }
+
             // there was another pam error, return the error
             Err(e) => {
                 return Err(e.into());
@@ -166,3 +241,21 @@ pub fn attempt_authenticate<C: Converser>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::expand_prompt;
+
+    #[test]
+    fn prompt_escapes() {
+        let expand = |prompt| expand_prompt(prompt, "vm.example.org", "ferris", "root");
+
+        assert_eq!(
+            expand("[sudo] password for %p: "),
+            "[sudo] password for ferris: "
+        );
+        assert_eq!(expand("%u@%h as %U"), "ferris@vm as root");
+        assert_eq!(expand("%H"), "vm.example.org");
+        assert_eq!(expand("100%% sure, %x%"), "100% sure, %x%");
+    }
+}
@@ -1,11 +1,12 @@
 use std::ffi::OsStr;
 use std::process::exit;
 
-use super::cli::{SudoRunOptions, SudoValidateOptions};
+use super::cli::{PreserveEnv, SudoEditOptions, SudoRunOptions, SudoValidateOptions};
 use crate::common::context::OptionsForContext;
-use crate::common::resolve::CurrentUser;
+use crate::common::resolve::{editor_path_fallback, CurrentUser};
 use crate::common::{Context, Environment, Error};
 use crate::exec::{ExecOutput, ExitReason};
+use crate::log::mail::{send_mail, Incident, MailOptions};
 use crate::log::{auth_info, auth_warn};
 use crate::sudo::env::environment;
 use crate::sudo::Duration;
@@ -13,7 +14,7 @@ use crate::sudoers::{Authorization, AuthorizationAllowed, DirChange, Policy, Pre
 use crate::system::interface::UserId;
 use crate::system::term::current_tty_name;
 use crate::system::timestamp::{RecordScope, SessionRecordFile, TouchResult};
-use crate::system::{escape_os_str_lossy, Process};
+use crate::system::{escape_os_str_lossy, Hostname, Process};
 
 mod list;
 
@@ -30,7 +31,7 @@ pub trait PolicyPlugin {
 }
 
 pub trait AuthPlugin {
-    fn init(&mut self, context: &Context) -> Result<(), Error>;
+    fn init(&mut self, context: &Context, auth: &AuthorizationAllowed) -> Result<(), Error>;
     fn authenticate(&mut self, non_interactive: bool, max_tries: u16) -> Result<(), Error>;
     fn pre_exec(&mut self, target_user: &str) -> Result<Environment, Error>;
     fn cleanup(&mut self);
@@ -42,17 +43,8 @@ pub struct Pipeline<Policy: PolicyPlugin, Auth: AuthPlugin> {
 }
 
 impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
-    pub fn run(mut self, cmd_opts: SudoRunOptions) -> Result<(), Error> {
-        if !cmd_opts.env_var_list.is_empty() {
-            eprintln_ignore_io_error!(
-                "warning: CLI-level env var list has not yet been implemented and will be ignored"
-            )
-        }
-        if !cmd_opts.preserve_env.is_nothing() {
-            eprintln_ignore_io_error!(
-                "warning: `--preserve-env` has not yet been implemented and will be ignored"
-            )
-        }
+    pub fn run(mut self, mut cmd_opts: SudoRunOptions) -> Result<(), Error> {
+        let (user_override, preserve_env) = requested_environment(&mut cmd_opts);
 
         let pre = self.policy.init()?;
         let mut context = build_context(cmd_opts.into(), &pre)?;
@@ -62,6 +54,7 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
 
         match authorization {
             Authorization::Forbidden => {
+                report_denial(&policy, &context);
                 return Err(Error::auth(&format!(
                     "I'm sorry {}. I'm afraid I can't do that",
                     context.current_user.name
@@ -69,18 +62,28 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
             }
             Authorization::Allowed(auth) => {
                 self.apply_policy_to_context(&mut context, &policy)?;
-                self.auth_and_update_record_file(&context, auth)?;
+                self.auth_and_update_record_file(
+                    &context,
+                    auth,
+                    policy.mail(Incident::AuthFailure),
+                )?;
             }
         }
 
+        environment::validate_user_env(&user_override, preserve_env, &policy)?;
+
         let additional_env = self.authenticator.pre_exec(&context.target_user.name)?;
 
         // build environment
         let current_env = std::env::vars_os().collect();
-        let target_env =
-            environment::get_target_environment(current_env, additional_env, &context, &policy);
-
-        let pid = context.process.pid;
+        let target_env = environment::get_target_environment(
+            current_env,
+            additional_env,
+            user_override,
+            preserve_env,
+            &context,
+            &policy,
+        );
 
         // run command and return corresponding exit code
         let exec_result = if context.command.resolved {
@@ -105,42 +108,101 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
         match command_exit_reason {
             ExitReason::Code(code) => exit(code),
             ExitReason::Signal(signal) => {
-                crate::system::kill(pid, signal)?;
+                // in the background, this is no longer the process that sudo was started as
+                crate::system::kill(crate::system::Process::process_id(), signal)?;
             }
         }
 
         Ok(())
     }
 
+    pub fn run_edit(mut self, cmd_opts: SudoEditOptions) -> Result<(), Error> {
+        let pre = self.policy.init()?;
+        let mut context = build_context(cmd_opts.into(), &pre)?;
+        let editor = match pre.solve_editor_path(&context.current_user, &context.hostname) {
+            Some(path) => path,
+            None => editor_path_fallback()?,
+        };
+
+        let policy = self.policy.judge(pre, &context)?;
+        let authorization = policy.authorization();
+
+        match authorization {
+            Authorization::Forbidden => {
+                report_denial(&policy, &context);
+                return Err(Error::auth(&format!(
+                    "I'm sorry {}. I'm afraid I can't do that",
+                    context.current_user.name
+                )));
+            }
+            Authorization::Allowed(auth) => {
+                self.apply_policy_to_context(&mut context, &policy)?;
+                self.auth_and_update_record_file(
+                    &context,
+                    auth,
+                    policy.mail(Incident::AuthFailure),
+                )?;
+            }
+        }
+
+        // this performs account validation and opens the session for the target user
+        self.authenticator.pre_exec(&context.target_user.name)?;
+
+        log_command_execution(&context);
+
+        let result = super::edit::edit_files(&context, &editor);
+
+        self.authenticator.cleanup();
+
+        result
+    }
+
     pub fn run_validate(mut self, cmd_opts: SudoValidateOptions) -> Result<(), Error> {
         let pre = self.policy.init()?;
         let context = build_context(cmd_opts.into(), &pre)?;
 
-        match pre.validate_authorization() {
+        match pre.validate_authorization(&context.current_user, &context.hostname) {
             Authorization::Forbidden => {
+                if let Some(mail) = pre.mail(
+                    &context.current_user,
+                    &context.hostname,
+                    Incident::NotInSudoers,
+                ) {
+                    send_incident_mail(&mail, &context, Incident::NotInSudoers);
+                }
                 return Err(Error::auth(&format!(
                     "I'm sorry {}. I'm afraid I can't do that",
                     context.current_user.name
                 )));
             }
             Authorization::Allowed(auth) => {
-                self.auth_and_update_record_file(&context, auth)?;
+                let mail = pre.mail(
+                    &context.current_user,
+                    &context.hostname,
+                    Incident::AuthFailure,
+                );
+                self.auth_and_update_record_file(&context, auth, mail)?;
             }
         }
 
         Ok(())
     }
 
+    /// Authenticate the user if that is required; failures are reported to `mail`, if given
     fn auth_and_update_record_file(
         &mut self,
         context: &Context,
-        AuthorizationAllowed {
+        auth: AuthorizationAllowed,
+        mail: Option<MailOptions>,
+    ) -> Result<(), Error> {
+        let AuthorizationAllowed {
             must_authenticate,
             prior_validity,
             allowed_attempts,
-        }: AuthorizationAllowed,
-    ) -> Result<(), Error> {
-        let scope = RecordScope::for_process(&Process::new());
+            timestamp_type,
+            ..
+        } = auth;
+        let scope = RecordScope::for_process(&Process::new(), timestamp_type);
         let mut auth_status = determine_auth_status(
             must_authenticate,
             context.use_session_records,
@@ -149,10 +211,15 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
             &context.current_user,
             prior_validity,
         );
-        self.authenticator.init(context)?;
+        self.authenticator.init(context, &auth)?;
         if auth_status.must_authenticate {
-            self.authenticator
-                .authenticate(context.non_interactive, allowed_attempts)?;
+            let result = self
+                .authenticator
+                .authenticate(context.non_interactive, allowed_attempts);
+            if let (Err(Error::MaxAuthAttempts(_)), Some(mail)) = (&result, mail) {
+                send_incident_mail(&mail, context, Incident::AuthFailure);
+            }
+            result?;
             if let (Some(record_file), Some(scope)) = (&mut auth_status.record_file, scope) {
                 match record_file.create(scope, context.current_user.uid) {
                     Ok(_) => (),
@@ -186,6 +253,10 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
             }
         }
 
+        if context.close_from.is_some() && !policy.closefrom_override() {
+            return Err(Error::CloseFromNotAllowed);
+        }
+
         // expand tildes in the path with the users home directory
         if let Some(dir) = context.chdir.take() {
             context.chdir = Some(dir.expand_tilde_in_path(&context.target_user.name)?)
@@ -196,18 +267,46 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
             context.use_pty = false
         }
 
+        context.iolog = policy.iolog();
+        context.noexec = policy.noexec();
+
         Ok(())
     }
 }
 
+/// Collect the environment variables that the user asked to pass on to the command: those given
+/// as `VAR=value` arguments, and those listed with `--preserve-env=VAR,...`; also returns whether
+/// the entire environment should be preserved (`--preserve-env` without a list)
+fn requested_environment(cmd_opts: &mut SudoRunOptions) -> (Vec<(String, String)>, bool) {
+    let mut user_override = std::mem::take(&mut cmd_opts.env_var_list);
+
+    let preserve_env = match std::mem::take(&mut cmd_opts.preserve_env) {
+        PreserveEnv::Nothing => false,
+        PreserveEnv::Everything => true,
+        PreserveEnv::Only(names) => {
+            for name in names {
+                // variables that are not set (or are not valid UTF-8) are silently skipped
+                if let Ok(value) = std::env::var(&name) {
+                    user_override.push((name, value));
+                }
+            }
+            false
+        }
+    };
+
+    (user_override, preserve_env)
+}
+
 fn build_context(
     cmd_opts: OptionsForContext,
     pre: &dyn PreJudgementPolicy,
 ) -> Result<Context, Error> {
+    let hostname = Hostname::resolve();
+    let current_user = CurrentUser::resolve()?;
     let secure_path: String = pre
-        .secure_path()
+        .secure_path(&current_user, &hostname)
         .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default());
-    Context::build_from_options(cmd_opts, secure_path)
+    Context::build_from_options(cmd_opts, current_user, hostname, secure_path)
 }
 
 /// This should determine what the authentication status for the given record
@@ -262,7 +361,8 @@ impl AuthStatus {
     }
 }
 
-fn log_command_execution(context: &Context) {
+/// Describe the command being run, in the format used for the log and for mail
+fn command_details(context: &Context) -> String {
     let tty_info = if let Ok(tty_name) = current_tty_name() {
         format!("TTY={} ;", escape_os_str_lossy(&tty_name))
     } else {
@@ -275,12 +375,39 @@ fn log_command_execution(context: &Context) {
             .unwrap_or_else(|_| OsStr::new("unknown")),
     );
     let user = context.target_user.name.escape_debug().collect::<String>();
+    format!(
+        "{} PWD={} ; USER={} ; COMMAND={}",
+        tty_info, pwd, user, &context.command
+    )
+}
+
+fn log_command_execution(context: &Context) {
     auth_info!(
-        "{} : {} PWD={} ; USER={} ; COMMAND={}",
+        "{} : {}",
+        &context.current_user.name,
+        command_details(context)
+    );
+}
+
+/// Notify the administrator that the policy denied the request, if the policy asks for that
+fn report_denial(policy: &impl Policy, context: &Context) {
+    let incident = if policy.user_listed() {
+        Incident::CommandDenied
+    } else {
+        Incident::NotInSudoers
+    };
+
+    if let Some(mail) = policy.mail(incident) {
+        send_incident_mail(&mail, context, incident);
+    }
+}
+
+fn send_incident_mail(mail: &MailOptions, context: &Context, incident: Incident) {
+    send_mail(
+        mail,
+        &context.hostname,
         &context.current_user.name,
-        tty_info,
-        pwd,
-        user,
-        &context.command
+        incident,
+        command_details(context).trim_start(),
     );
 }
@@ -2,6 +2,7 @@ use std::{borrow::Cow, ops::ControlFlow, path::Path};
 
 use crate::{
     common::{Context, Error},
+    log::mail::Incident,
     pam::CLIConverser,
     sudo::{cli::SudoListOptions, pam::PamAuthenticator, SudoersPolicy},
     sudoers::{Authorization, ListRequest, Policy, Request, Sudoers},
@@ -38,22 +39,28 @@ impl Pipeline<SudoersPolicy, PamAuthenticator<CLIConverser>> {
             return Ok(());
         }
 
-        if let Some(other_user) = &other_user {
-            check_other_users_list_perms(other_user, &context, &sudoers, &original_command)?;
-        }
-
         if let Some(original_command) = original_command {
             check_sudo_command_perms(&original_command, &context, &other_user, &sudoers)?;
         } else {
             let invoking_user = other_user.as_ref().unwrap_or(&context.current_user);
+            let matching_entries = sudoers.matching_entries(invoking_user, &context.hostname);
+
+            if matching_entries.is_empty() {
+                println_ignore_io_error!(
+                    "User {} is not allowed to run sudo on {}.",
+                    invoking_user.name,
+                    context.hostname
+                );
+
+                return Ok(());
+            }
+
             println_ignore_io_error!(
                 "User {} may run the following commands on {}:",
                 invoking_user.name,
                 context.hostname
             );
 
-            let matching_entries = sudoers.matching_entries(invoking_user, &context.hostname);
-
             for entry in matching_entries {
                 if verbose_list_mode {
                     let entry = entry.verbose();
@@ -74,27 +81,47 @@ impl Pipeline<SudoersPolicy, PamAuthenticator<CLIConverser>> {
         original_command: &Option<String>,
         other_user: &Option<User>,
     ) -> Result<ControlFlow<(), ()>, Error> {
-        let list_request = ListRequest {
-            target_user: &context.target_user,
-            target_group: &context.target_group,
+        let judgement = if other_user.is_some() {
+            // listing the privileges of another user requires the `list` pseudo-command
+            let request = Request {
+                user: &context.target_user,
+                group: &context.target_group,
+                command: Path::new("list"),
+                arguments: &[],
+                digest: &|_| None,
+            };
+            sudoers.check(&*context.current_user, &context.hostname, request)
+        } else {
+            let list_request = ListRequest {
+                target_user: &context.target_user,
+                target_group: &context.target_group,
+            };
+            sudoers.check_list_permission(&*context.current_user, &context.hostname, list_request)
         };
-        let judgement =
-            sudoers.check_list_permission(&*context.current_user, &context.hostname, list_request);
         match judgement.authorization() {
             Authorization::Allowed(auth) => {
-                self.auth_and_update_record_file(context, auth)?;
+                self.auth_and_update_record_file(
+                    context,
+                    auth,
+                    judgement.mail(Incident::AuthFailure),
+                )?;
                 Ok(ControlFlow::Continue(()))
             }
 
             Authorization::Forbidden => {
                 if context.current_user.uid == 0 {
+                    // root may always list the privileges of other users
+                    if other_user.is_some() {
+                        return Ok(ControlFlow::Continue(()));
+                    }
+
                     if original_command.is_some() {
                         return Err(Error::Silent);
                     }
 
                     println_ignore_io_error!(
                         "User {} is not allowed to run sudo on {}.",
-                        other_user.as_ref().unwrap_or(&context.current_user).name,
+                        context.current_user.name,
                         context.hostname
                     );
 
@@ -120,30 +147,6 @@ impl Pipeline<SudoersPolicy, PamAuthenticator<CLIConverser>> {
     }
 }
 
-fn check_other_users_list_perms(
-    other_user: &User,
-    context: &Context,
-    sudoers: &Sudoers,
-    original_command: &Option<String>,
-) -> Result<(), Error> {
-    let list_request = ListRequest {
-        target_user: &context.target_user,
-        target_group: &context.target_group,
-    };
-    let judgement = sudoers.check_list_permission(other_user, &context.hostname, list_request);
-
-    if let Authorization::Forbidden = judgement.authorization() {
-        return Err(Error::NotAllowed {
-            username: context.current_user.name.clone(),
-            command: format_list_command(original_command),
-            hostname: context.hostname.clone(),
-            other_user: Some(other_user.name.clone()),
-        });
-    }
-
-    Ok(())
-}
-
 fn check_sudo_command_perms(
     original_command: &str,
     context: &Context,
@@ -157,6 +160,7 @@ fn check_sudo_command_perms(
         group: &context.target_group,
         command: &context.command.command,
         arguments: &context.command.arguments,
+        digest: &|algorithm| context.open_executable()?.digest(algorithm),
     };
 
     let judgement = sudoers.check(user, &context.hostname, request);
@@ -1,6 +1,7 @@
 use super::ast_names::UserFriendly;
 use super::basic_parser::*;
 use super::tokens::*;
+use crate::common::digest::DigestAlgorithm;
 use crate::common::SudoString;
 use crate::common::{
     HARDENED_ENUM_VALUE_0, HARDENED_ENUM_VALUE_1, HARDENED_ENUM_VALUE_2, HARDENED_ENUM_VALUE_3,
@@ -8,7 +9,8 @@ use crate::common::{
 };
 
 /// The Sudoers file allows negating items with the exclamation mark.
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug, Eq))]
 #[repr(u32)]
 pub enum Qualified<T> {
     Allow(T) = HARDENED_ENUM_VALUE_0,
@@ -45,18 +47,21 @@ pub type Spec<T> = Qualified<Meta<T>>;
 pub type SpecList<T> = Vec<Spec<T>>;
 
 /// An identifier is a name or a #number
-#[cfg_attr(test, derive(Clone, Debug, PartialEq, Eq))]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug, Eq))]
 pub enum Identifier {
     Name(SudoString),
     ID(u32),
 }
 
 /// A userspecifier is either a username, or a (non-unix) group name, or netgroup
-#[cfg_attr(test, derive(Clone, Debug, PartialEq, Eq))]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug, Eq))]
 pub enum UserSpecifier {
     User(Identifier),
     Group(Identifier),
     NonunixGroup(Identifier),
+    Netgroup(SudoString),
 }
 
 /// The RunAs specification consists of a (possibly empty) list of userspecifiers, followed by a (possibly empty) list of groups.
@@ -78,12 +83,32 @@ pub enum Authenticate {
     Nopasswd = HARDENED_ENUM_VALUE_2,
 }
 
+// `sudo -l l` calls this the `setenv` option
+#[derive(Copy, Clone, Default, PartialEq)]
+#[cfg_attr(test, derive(Debug, Eq))]
+#[repr(u32)]
+pub enum EnvironmentControl {
+    #[default]
+    Implicit = HARDENED_ENUM_VALUE_0,
+    // SETENV:
+    Setenv = HARDENED_ENUM_VALUE_1,
+    // NOSETENV:
+    Nosetenv = HARDENED_ENUM_VALUE_2,
+}
+
 /// Commands in /etc/sudoers can have attributes attached to them, such as NOPASSWD, NOEXEC, ...
 #[derive(Default, Clone, PartialEq)]
 #[cfg_attr(test, derive(Debug, Eq))]
 pub struct Tag {
     pub authenticate: Authenticate,
     pub cwd: Option<ChDir>,
+    pub env: EnvironmentControl,
+    // LOG_INPUT: / NOLOG_INPUT:, if not present the `log_input` setting applies
+    pub log_input: Option<bool>,
+    // LOG_OUTPUT: / NOLOG_OUTPUT:, if not present the `log_output` setting applies
+    pub log_output: Option<bool>,
+    // NOEXEC: / EXEC:, if not present the `noexec` setting applies
+    pub noexec: Option<bool>,
 }
 
 impl Tag {
@@ -113,11 +138,23 @@ pub enum Directive {
     HostAlias(Defs<Hostname>) = HARDENED_ENUM_VALUE_1,
     CmndAlias(Defs<Command>) = HARDENED_ENUM_VALUE_2,
     RunasAlias(Defs<UserSpecifier>) = HARDENED_ENUM_VALUE_3,
-    Defaults(Vec<(String, ConfigValue)>) = HARDENED_ENUM_VALUE_4,
+    Defaults(Vec<(String, ConfigValue)>, ConfigScope) = HARDENED_ENUM_VALUE_4,
+}
+
+/// The scope of a `Defaults` directive; i.e. `Defaults@host`, `Defaults:user`, `Defaults>runas`
+/// or `Defaults!command` only apply if the host, user, runas user or command matches.
+#[repr(u32)]
+pub enum ConfigScope {
+    Generic = HARDENED_ENUM_VALUE_0,
+    Host(SpecList<Hostname>) = HARDENED_ENUM_VALUE_1,
+    User(SpecList<UserSpecifier>) = HARDENED_ENUM_VALUE_2,
+    RunAs(SpecList<UserSpecifier>) = HARDENED_ENUM_VALUE_3,
+    Command(SpecList<Command>) = HARDENED_ENUM_VALUE_4,
 }
 
 pub type TextEnum = crate::defaults::StrEnum<'static>;
 
+#[derive(Clone)]
 pub enum ConfigValue {
     Flag(bool),
     Text(Option<Box<str>>),
@@ -126,6 +163,7 @@ pub enum ConfigValue {
     Enum(TextEnum),
 }
 
+#[derive(Clone, Copy)]
 #[repr(u32)]
 pub enum Mode {
     Add = HARDENED_ENUM_VALUE_0,
@@ -284,8 +322,8 @@ impl Parse for UserSpecifier {
             // in this case we must fail 'hard', since input has been consumed
             ctor(expect_nonterminal(stream)?)
         } else if accept_if(|c| c == '+', stream).is_some() {
-            // TODO Netgroups
-            unrecoverable!(stream, "netgroups are not supported yet");
+            let Username(name) = expect_nonterminal(stream)?;
+            UserSpecifier::Netgroup(name)
         } else {
             // in this case we must fail 'softly', since no input has been consumed yet
             UserSpecifier::User(try_nonterminal(stream)?)
@@ -348,6 +386,14 @@ impl Parse for MetaOrTag {
         let result: Modifier = match keyword.as_str() {
             "PASSWD" => switch(|tag| tag.authenticate = Authenticate::Passwd)?,
             "NOPASSWD" => switch(|tag| tag.authenticate = Authenticate::Nopasswd)?,
+            "SETENV" => switch(|tag| tag.env = EnvironmentControl::Setenv)?,
+            "NOSETENV" => switch(|tag| tag.env = EnvironmentControl::Nosetenv)?,
+            "LOG_INPUT" => switch(|tag| tag.log_input = Some(true))?,
+            "NOLOG_INPUT" => switch(|tag| tag.log_input = Some(false))?,
+            "LOG_OUTPUT" => switch(|tag| tag.log_output = Some(true))?,
+            "NOLOG_OUTPUT" => switch(|tag| tag.log_output = Some(false))?,
+            "NOEXEC" => switch(|tag| tag.noexec = Some(true))?,
+            "EXEC" => switch(|tag| tag.noexec = Some(false))?,
             "CWD" => {
                 expect_syntax('=', stream)?;
                 let path: ChDir = expect_nonterminal(stream)?;
@@ -381,31 +427,78 @@ impl Parse for CommandSpec {
             }
         }
 
+        let cmd: Spec<Command> = expect_nonterminal(stream)?;
+
+        make(CommandSpec(tags, cmd))
+    }
+}
+
+/// grammar:
+/// ```text
+/// command = [digest ","]* path [args]* | "sudoedit" [path]* | "list" | ALL | alias
+/// digest = ("sha224" | "sha256" | "sha384" | "sha512") ":" (hex | base64)
+/// ```
+impl Parse for Meta<Command> {
+    fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
+        use Meta::*;
+        // commands are paths or aliases; only keywords start with a lower case letter
+        if !stream.peek().is_some_and(|c| c.is_ascii_lowercase()) {
+            let cmd: Meta<SimpleCommand> = try_nonterminal(stream)?;
+            return make(match cmd {
+                All => All,
+                Only(cmd) => Only((cmd, Box::default())),
+                Alias(name) => Alias(name),
+            });
+        }
+
         let start_pos = stream.get_pos();
-        if let Some(Username(keyword)) = try_nonterminal(stream)? {
-            if keyword == "sudoedit" {
-                // note: special behaviour of forward slashes in wildcards, tread carefully
-                unrecoverable!(pos = start_pos, stream, "sudoedit is not yet supported");
-            } else if keyword == "list" {
-                unrecoverable!(pos = start_pos, stream, "list is not yet supported");
-            } else if keyword.starts_with("sha") {
-                unrecoverable!(
-                    pos = start_pos,
-                    stream,
-                    "digest specifications are not supported"
-                )
-            } else {
+        let Username(mut keyword) = try_nonterminal(stream)?;
+        if keyword == "sudoedit" {
+            // note: special behaviour of forward slashes in wildcards, tread carefully
+            let paths = match try_nonterminal(stream)? {
+                Some(EditPaths(paths)) => paths,
+                None => Vec::new(),
+            };
+            let cmd = match sudoedit(paths) {
+                Ok(cmd) => cmd,
+                Err(msg) => unrecoverable!(pos = start_pos, stream, "{msg}"),
+            };
+
+            return make(Only((cmd, Box::default())));
+        } else if keyword == "list" {
+            return make(Only((list(), Box::default())));
+        }
+
+        let mut digests = Vec::new();
+        loop {
+            let Some(algorithm) = DigestAlgorithm::from_name(&keyword) else {
                 unrecoverable!(
                     pos = start_pos,
                     stream,
                     "expected command but found {keyword}"
                 )
             };
+            expect_syntax(':', stream)?;
+            let digest_pos = stream.get_pos();
+            let DigestText(text) = expect_nonterminal(stream)?;
+            match Digest::decode(algorithm, &text) {
+                Ok(digest) => digests.push(digest),
+                Err(msg) => unrecoverable!(pos = digest_pos, stream, "{msg}"),
+            }
+            if digests.len() > Command::LIMIT {
+                unrecoverable!(stream, "too many digests for command")
+            }
+
+            if !is_syntax(',', stream)? {
+                break;
+            }
+            // after a comma, only another digest can follow
+            Username(keyword) = expect_nonterminal(stream)?;
         }
 
-        let cmd: Spec<Command> = expect_nonterminal(stream)?;
+        let cmd: SimpleCommand = expect_nonterminal(stream)?;
 
-        make(CommandSpec(tags, cmd))
+        make(Only((cmd, digests.into_boxed_slice())))
     }
 }
 
@@ -459,7 +552,7 @@ impl Many for (Option<RunAs>, CommandSpec) {}
 /// ```text
 /// sudo = permissionspec
 ///      | Keyword_Alias identifier = identifier_list
-///      | Defaults (name [+-]?= ...)+
+///      | Defaults[scope] (name [+-]?= ...)+
 /// ```
 /// There is a syntactical ambiguity in the sudoer Directive and Permission specifications, so we
 /// have to parse them 'together' and do a delayed decision on which category we are in.
@@ -503,7 +596,7 @@ impl Parse for Sudo {
         if let Some(users) = maybe(try_nonterminal::<SpecList<_>>(stream))? {
             // element 1 always exists (parse_list fails on an empty list)
             let key = &users[0];
-            if let Some(directive) = maybe(get_directive(key, stream))? {
+            if let Some(directive) = maybe(get_directive(key, start_pos, stream))? {
                 if users.len() != 1 {
                     unrecoverable!(pos = start_pos, stream, "invalid user name list");
                 }
@@ -561,8 +654,7 @@ use crate::defaults::SudoDefault as Setting;
 ///
 impl<T> Parse for Def<T>
 where
-    T: UserFriendly,
-    Meta<T>: Parse + Many,
+    Meta<T>: Parse + Many + UserFriendly,
 {
     fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
         let begin_pos = stream.get_pos();
@@ -586,6 +678,7 @@ impl<T> Many for Def<T> {
 
 fn get_directive(
     perhaps_keyword: &Spec<UserSpecifier>,
+    keyword_pos: (usize, usize),
     stream: &mut impl CharStream,
 ) -> Parsed<Directive> {
     use super::ast::Directive::*;
@@ -601,11 +694,56 @@ fn get_directive(
         "Host_Alias" => make(HostAlias(expect_nonterminal(stream)?)),
         "Cmnd_Alias" | "Cmd_Alias" => make(CmndAlias(expect_nonterminal(stream)?)),
         "Runas_Alias" => make(RunasAlias(expect_nonterminal(stream)?)),
-        "Defaults" => make(Defaults(expect_nonterminal(stream)?)),
+        "Defaults" => {
+            // a scope has to follow the keyword directly, "Defaults !flag" is a negated setting
+            let scope = if stream.get_pos() == (keyword_pos.0, keyword_pos.1 + keyword.len()) {
+                get_scope(stream)?
+            } else {
+                ConfigScope::Generic
+            };
+            make(Defaults(expect_nonterminal(stream)?, scope))
+        }
         _ => reject(),
     }
 }
 
+/// grammar:
+/// ```text
+/// scope = "@" host_list | ":" user_list | ">" runas_list | "!" command_list
+/// ```
+/// Commands in a scope cannot have arguments.
+fn get_scope(stream: &mut impl CharStream) -> Parsed<ConfigScope> {
+    use Meta::*;
+    use Qualified::*;
+    let scope = if is_syntax('@', stream)? {
+        ConfigScope::Host(expect_nonterminal(stream)?)
+    } else if is_syntax(':', stream)? {
+        ConfigScope::User(expect_nonterminal(stream)?)
+    } else if is_syntax('>', stream)? {
+        ConfigScope::RunAs(expect_nonterminal(stream)?)
+    } else if is_syntax('!', stream)? {
+        let commands: SpecList<CommandPath> = expect_nonterminal(stream)?;
+        let embed = |meta| match meta {
+            All => All,
+            Alias(name) => Alias(name),
+            Only(CommandPath(cmd)) => Only((cmd, Box::default())),
+        };
+        ConfigScope::Command(
+            commands
+                .into_iter()
+                .map(|spec| match spec {
+                    Allow(meta) => Allow(embed(meta)),
+                    Forbid(meta) => Forbid(embed(meta)),
+                })
+                .collect(),
+        )
+    } else {
+        ConfigScope::Generic
+    };
+
+    make(scope)
+}
+
 /// grammar:
 /// ```text
 /// parameter = name [+-]?= ...
@@ -619,10 +757,12 @@ impl Parse for (String, ConfigValue) {
             if accept_if(|c| c == '"', stream).is_some() {
                 let mut result = Vec::new();
                 while let Some(EnvVar(name)) = try_nonterminal(stream)? {
-                    result.push(name);
+                    // an entry of the form "NAME=value" also has to match the value
                     if is_syntax('=', stream)? {
-                        let EnvVar(_) = expect_nonterminal(stream)?;
-                        unrecoverable!(stream, "values in environment variables not yet supported")
+                        let EnvVar(value) = expect_nonterminal(stream)?;
+                        result.push(format!("{name}={value}"));
+                    } else {
+                        result.push(name);
                     }
                     if result.len() > Identifier::LIMIT {
                         unrecoverable!(stream, "environment variable list too long")
@@ -636,6 +776,9 @@ impl Parse for (String, ConfigValue) {
                 make(result)
             } else {
                 let EnvVar(name) = expect_nonterminal(stream)?;
+                if accept_if(|c| c == '=', stream).is_some() {
+                    unrecoverable!(stream, "values in environment variables must be quoted")
+                }
 
                 make(vec![name])
             }
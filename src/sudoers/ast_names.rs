@@ -39,6 +39,18 @@ mod names {
         const DESCRIPTION: &'static str = "path to binary (or sudoedit)";
     }
 
+    impl UserFriendly for tokens::SimpleCommand {
+        const DESCRIPTION: &'static str = "path to binary";
+    }
+
+    impl UserFriendly for tokens::CommandPath {
+        const DESCRIPTION: &'static str = "path to binary (or sudoedit)";
+    }
+
+    impl UserFriendly for tokens::DigestText {
+        const DESCRIPTION: &'static str = "digest";
+    }
+
     impl UserFriendly
         for (
             SpecList<tokens::Hostname>,
@@ -61,6 +73,10 @@ mod names {
         const DESCRIPTION: &'static str = "user";
     }
 
+    impl UserFriendly for tokens::Username {
+        const DESCRIPTION: &'static str = "name";
+    }
+
     impl UserFriendly for tokens::Hostname {
         const DESCRIPTION: &'static str = "host name";
     }
@@ -85,6 +101,10 @@ mod names {
         const DESCRIPTION: &'static str = "alias name";
     }
 
+    impl UserFriendly for tokens::EditPaths {
+        const DESCRIPTION: &'static str = "path to file";
+    }
+
     impl UserFriendly for tokens::DefaultName {
         const DESCRIPTION: &'static str = "configuration option";
     }
@@ -310,7 +310,21 @@ impl<T: Parse + Many + UserFriendly> Parse for Vec<T> {
 }
 
 /// Entry point utility function; parse a `Vec<T>` but with fatal error recovery per line
+#[cfg(test)]
 pub fn parse_lines<T, Stream: CharStream>(stream: &mut Stream) -> Vec<Parsed<T>>
+where
+    T: Parse + UserFriendly,
+{
+    parse_lines_with_pos(stream)
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Like `parse_lines`, but every item is accompanied by the position it was parsed from
+pub fn parse_lines_with_pos<T, Stream: CharStream>(
+    stream: &mut Stream,
+) -> Vec<(Position, Parsed<T>)>
 where
     T: Parse + UserFriendly,
 {
@@ -321,9 +335,10 @@ where
     // (which will cause the next iteration to fall through)
 
     while LeadingWhitespace::parse(stream).is_ok() {
+        let start = stream.get_pos();
         let item = expect_nonterminal(stream);
         let parsed_item_ok = item.is_ok();
-        result.push(item);
+        result.push((start..stream.get_pos(), item));
 
         let _ = maybe(Comment::parse(stream));
         if accept_if(|c| c == '\n', stream).is_none() {
@@ -334,7 +349,8 @@ where
                     "garbage at end of line"
                 };
                 let error = |stream: &mut Stream| unrecoverable!(stream, "{msg}");
-                result.push(error(stream));
+                let pos = stream.get_pos();
+                result.push((pos..pos, error(stream)));
             }
             while accept_if(|c| c != '\n', stream).is_some() {}
         }
@@ -356,7 +372,7 @@ fn expect_complete<T: Parse>(stream: &mut impl CharStream) -> Parsed<T> {
 /// AST constructors by hand.
 #[cfg(test)]
 pub fn parse_string<T: Parse>(text: &str) -> Parsed<T> {
-    expect_complete(&mut text.chars().peekable())
+    expect_complete(&mut super::char_stream::PeekableWithPos::new(text.chars()))
 }
 
 #[cfg(test)]
@@ -2,13 +2,13 @@ use core::fmt;
 
 use crate::sudoers::{
     ast::{Identifier, Qualified, UserSpecifier},
-    tokens::{ChDir, Meta},
+    tokens::{ChDir, Hostname, Meta},
 };
 
 use self::verbose::Verbose;
 
 use super::{
-    ast::{Authenticate, RunAs, Tag},
+    ast::{Authenticate, EnvironmentControl, RunAs, Tag},
     tokens::Command,
 };
 
@@ -76,36 +76,49 @@ fn write_users(run_as: &RunAs, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Er
         }
         is_first_user = false;
 
-        let meta = match user {
-            Qualified::Allow(meta) => meta,
-            Qualified::Forbid(meta) => {
-                f.write_str("!")?;
-                meta
-            }
-        };
+        write_user(f, &user.as_ref())?;
+    }
+
+    Ok(())
+}
 
-        match meta {
-            Meta::All => f.write_str("ALL")?,
-            Meta::Only(user) => {
-                let ident = match user {
-                    UserSpecifier::User(ident) => ident,
-                    UserSpecifier::Group(ident) => {
-                        f.write_str("%")?;
-                        ident
-                    }
-                    UserSpecifier::NonunixGroup(ident) => {
-                        f.write_str("%:")?;
-                        ident
-                    }
-                };
-
-                match ident {
-                    Identifier::Name(name) => f.write_str(name)?,
-                    Identifier::ID(id) => write!(f, "#{id}")?,
+pub(super) fn write_user(
+    f: &mut fmt::Formatter,
+    user: &Qualified<&Meta<UserSpecifier>>,
+) -> fmt::Result {
+    let meta = match user {
+        Qualified::Allow(meta) => meta,
+        Qualified::Forbid(meta) => {
+            f.write_str("!")?;
+            meta
+        }
+    };
+
+    match meta {
+        Meta::All => f.write_str("ALL")?,
+        Meta::Only(user) => {
+            let ident = match user {
+                UserSpecifier::User(ident) => ident,
+                UserSpecifier::Group(ident) => {
+                    f.write_str("%")?;
+                    ident
+                }
+                UserSpecifier::NonunixGroup(ident) => {
+                    f.write_str("%:")?;
+                    ident
                 }
+                UserSpecifier::Netgroup(name) => {
+                    f.write_str("+")?;
+                    return f.write_str(name);
+                }
+            };
+
+            match ident {
+                Identifier::Name(name) => f.write_str(name)?,
+                Identifier::ID(id) => write!(f, "#{id}")?,
             }
-            Meta::Alias(alias) => f.write_str(alias)?,
         }
+        Meta::Alias(alias) => f.write_str(alias)?,
     }
 
     Ok(())
@@ -119,29 +132,52 @@ fn write_groups(run_as: &RunAs, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::E
         }
         is_first_group = false;
 
-        let meta = match group {
-            Qualified::Allow(meta) => meta,
-            Qualified::Forbid(meta) => {
-                f.write_str("!")?;
-                meta
-            }
-        };
+        write_group(f, &group.as_ref())?;
+    }
 
-        match meta {
-            Meta::All => f.write_str("ALL")?,
-            Meta::Only(ident) => match ident {
-                Identifier::Name(name) => f.write_str(name)?,
-                Identifier::ID(id) => write!(f, "#{id}")?,
-            },
-            Meta::Alias(alias) => f.write_str(alias)?,
+    Ok(())
+}
+
+pub(super) fn write_group(
+    f: &mut fmt::Formatter,
+    group: &Qualified<&Meta<Identifier>>,
+) -> fmt::Result {
+    let meta = match group {
+        Qualified::Allow(meta) => meta,
+        Qualified::Forbid(meta) => {
+            f.write_str("!")?;
+            meta
         }
+    };
+
+    match meta {
+        Meta::All => f.write_str("ALL"),
+        Meta::Only(ident) => match ident {
+            Identifier::Name(name) => f.write_str(name),
+            Identifier::ID(id) => write!(f, "#{id}"),
+        },
+        Meta::Alias(alias) => f.write_str(alias),
     }
+}
 
-    Ok(())
+pub(super) fn write_host(f: &mut fmt::Formatter, host: &Qualified<&Meta<Hostname>>) -> fmt::Result {
+    let meta = match host {
+        Qualified::Allow(meta) => meta,
+        Qualified::Forbid(meta) => {
+            f.write_str("!")?;
+            meta
+        }
+    };
+
+    match meta {
+        Meta::All => f.write_str("ALL"),
+        Meta::Only(host) => f.write_str(host),
+        Meta::Alias(alias) => f.write_str(alias),
+    }
 }
 
-fn write_tag(f: &mut fmt::Formatter, tag: &Tag, last_tag: Option<&Tag>) -> fmt::Result {
-    let (cwd, auth) = if let Some(last_tag) = last_tag {
+pub(super) fn write_tag(f: &mut fmt::Formatter, tag: &Tag, last_tag: Option<&Tag>) -> fmt::Result {
+    let (cwd, auth, env, log_input, log_output, noexec) = if let Some(last_tag) = last_tag {
         let cwd = if last_tag.cwd == tag.cwd {
             None
         } else {
@@ -154,9 +190,40 @@ fn write_tag(f: &mut fmt::Formatter, tag: &Tag, last_tag: Option<&Tag>) -> fmt::
             Some(tag.authenticate)
         };
 
-        (cwd, auth)
+        let env = if last_tag.env == tag.env {
+            None
+        } else {
+            Some(tag.env)
+        };
+
+        let log_input = if last_tag.log_input == tag.log_input {
+            None
+        } else {
+            tag.log_input
+        };
+
+        let log_output = if last_tag.log_output == tag.log_output {
+            None
+        } else {
+            tag.log_output
+        };
+
+        let noexec = if last_tag.noexec == tag.noexec {
+            None
+        } else {
+            tag.noexec
+        };
+
+        (cwd, auth, env, log_input, log_output, noexec)
     } else {
-        (tag.cwd.as_ref(), Some(tag.authenticate))
+        (
+            tag.cwd.as_ref(),
+            Some(tag.authenticate),
+            Some(tag.env),
+            tag.log_input,
+            tag.log_output,
+            tag.noexec,
+        )
     };
 
     if let Some(cwd) = cwd {
@@ -180,10 +247,42 @@ fn write_tag(f: &mut fmt::Formatter, tag: &Tag, last_tag: Option<&Tag>) -> fmt::
         }
     }
 
+    if let Some(env) = env {
+        if env != EnvironmentControl::Implicit {
+            let tag = if env == EnvironmentControl::Setenv {
+                "SETENV"
+            } else {
+                "NOSETENV"
+            };
+            f.write_str(tag)?;
+            f.write_str(": ")?;
+        }
+    }
+
+    if let Some(log_input) = log_input {
+        f.write_str(if log_input {
+            "LOG_INPUT: "
+        } else {
+            "NOLOG_INPUT: "
+        })?;
+    }
+
+    if let Some(log_output) = log_output {
+        f.write_str(if log_output {
+            "LOG_OUTPUT: "
+        } else {
+            "NOLOG_OUTPUT: "
+        })?;
+    }
+
+    if let Some(noexec) = noexec {
+        f.write_str(if noexec { "NOEXEC: " } else { "EXEC: " })?;
+    }
+
     Ok(())
 }
 
-fn write_spec(f: &mut fmt::Formatter, spec: &Qualified<&Meta<Command>>) -> fmt::Result {
+pub(super) fn write_spec(f: &mut fmt::Formatter, spec: &Qualified<&Meta<Command>>) -> fmt::Result {
     let meta = match spec {
         Qualified::Allow(meta) => meta,
         Qualified::Forbid(meta) => {
@@ -194,9 +293,16 @@ fn write_spec(f: &mut fmt::Formatter, spec: &Qualified<&Meta<Command>>) -> fmt::
 
     match meta {
         Meta::All => f.write_str("ALL")?,
-        Meta::Only((cmd, args)) => {
+        Meta::Only(((cmd, args), digests)) => {
+            for digest in digests.iter() {
+                write!(f, "{digest} ")?;
+            }
             write!(f, "{cmd}")?;
             if let Some(args) = args {
+                if args.is_empty() {
+                    // the command may only be run without arguments
+                    f.write_str(" \"\"")?;
+                }
                 for arg in args.iter() {
                     write!(f, " {arg}")?;
                 }
@@ -1,7 +1,7 @@
 use core::fmt;
 
 use crate::sudoers::{
-    ast::{Authenticate, RunAs, Tag},
+    ast::{Authenticate, EnvironmentControl, RunAs, Tag},
     tokens::ChDir,
 };
 
@@ -57,12 +57,42 @@ fn write_groups(run_as: &RunAs, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 }
 
 fn write_tag(f: &mut fmt::Formatter, tag: &Tag) -> fmt::Result {
+    let mut options = vec![];
     if tag.authenticate != Authenticate::None {
+        options.push(if tag.authenticate == Authenticate::Passwd {
+            "authenticate"
+        } else {
+            "!authenticate"
+        });
+    }
+
+    if tag.env != EnvironmentControl::Implicit {
+        options.push(if tag.env == EnvironmentControl::Setenv {
+            "setenv"
+        } else {
+            "!setenv"
+        });
+    }
+
+    if let Some(log_input) = tag.log_input {
+        options.push(if log_input { "log_input" } else { "!log_input" });
+    }
+
+    if let Some(log_output) = tag.log_output {
+        options.push(if log_output {
+            "log_output"
+        } else {
+            "!log_output"
+        });
+    }
+
+    if let Some(noexec) = tag.noexec {
+        options.push(if noexec { "noexec" } else { "!noexec" });
+    }
+
+    if !options.is_empty() {
         f.write_str("\n    Options: ")?;
-        if tag.authenticate != Authenticate::Passwd {
-            f.write_str("!")?;
-        }
-        f.write_str("authenticate")?;
+        f.write_str(&options.join(", "))?;
     }
 
     if let Some(cwd) = &tag.cwd {
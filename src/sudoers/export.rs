@@ -0,0 +1,770 @@
+//! Exporting the analyzed sudoers policy: as JSON for `visudo -x`, and as JSON or in canonical
+//! sudoers syntax for `cvtsudoers`
+
+use std::fmt;
+
+use super::ast::*;
+use super::basic_parser::Token;
+use super::entry::{write_group, write_host, write_spec, write_tag, write_user};
+use super::tokens::*;
+use super::{distribute_tags, Origin, Scoped, Settings, Sudoers};
+use crate::common::json::Json;
+
+/// Increased whenever the structure of the exported JSON changes
+const SCHEMA_VERSION: i64 = 1;
+
+/// How to write a list item of a certain kind
+type ToJson<T> = fn(&Qualified<&Meta<T>>) -> Json;
+
+/// Adapts one of the printing functions of `Entry` to `Display`
+struct Fmt<F: Fn(&mut fmt::Formatter) -> fmt::Result>(F);
+
+impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> fmt::Display for Fmt<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.0)(f)
+    }
+}
+
+impl Sudoers {
+    /// The policy as a JSON object: the files it was read from, the `Defaults` (for those that
+    /// apply everywhere, only the resulting settings that differ from the built-in defaults),
+    /// the aliases (together with what they expand to) and the rules. Every item that comes
+    /// from a line in a sudoers file records the file and line it was read from.
+    pub fn to_json(&self) -> Json {
+        let Sudoers {
+            rules,
+            aliases,
+            settings,
+            scoped_settings: scoped,
+            origins,
+            ..
+        } = self;
+
+        let mut scoped_defaults = Vec::new();
+        scoped_defaults.extend(scoped_to_json(
+            "host",
+            &scoped.host,
+            &origins.host_defaults,
+            host,
+        ));
+        scoped_defaults.extend(scoped_to_json(
+            "user",
+            &scoped.user,
+            &origins.user_defaults,
+            user,
+        ));
+        scoped_defaults.extend(scoped_to_json(
+            "runas",
+            &scoped.runas,
+            &origins.runas_defaults,
+            user,
+        ));
+        scoped_defaults.extend(scoped_to_json(
+            "command",
+            &scoped.cmnd,
+            &origins.cmnd_defaults,
+            command,
+        ));
+
+        let aliases = Json::object([
+            (
+                "user",
+                aliases_to_json(&aliases.user.1, &origins.user_alias, user),
+            ),
+            (
+                "host",
+                aliases_to_json(&aliases.host.1, &origins.host_alias, host),
+            ),
+            (
+                "command",
+                aliases_to_json(&aliases.cmnd.1, &origins.cmnd_alias, command),
+            ),
+            (
+                "runas",
+                aliases_to_json(&aliases.runas.1, &origins.runas_alias, user),
+            ),
+        ]);
+
+        let rules = rules
+            .iter()
+            .zip(&origins.rules)
+            .map(|(rule, origin)| rule_to_json(rule, origin))
+            .collect();
+
+        Json::object([
+            ("version", SCHEMA_VERSION.into()),
+            (
+                "files",
+                origins
+                    .files
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ("defaults", settings_to_json(settings)),
+            ("scoped_defaults", Json::Array(scoped_defaults)),
+            ("aliases", aliases),
+            ("rules", Json::Array(rules)),
+        ])
+    }
+}
+
+impl Sudoers {
+    /// The policy in sudoers syntax: first the `Defaults` (for those that apply everywhere, only
+    /// the resulting settings that differ from the built-in defaults), then the aliases and then
+    /// the rules, each on a line of its own.
+    pub fn to_sudoers(&self) -> String {
+        Fmt(|f| self.write_sudoers(f)).to_string()
+    }
+
+    fn write_sudoers(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Sudoers {
+            rules,
+            aliases,
+            settings,
+            scoped_settings: scoped,
+            ..
+        } = self;
+
+        for (name, value) in changed_settings(settings) {
+            f.write_str("Defaults ")?;
+            write_setting(f, &name, &value)?;
+            writeln!(f)?;
+        }
+        write_scoped(f, '@', &scoped.host, write_host)?;
+        write_scoped(f, ':', &scoped.user, write_user)?;
+        write_scoped(f, '>', &scoped.runas, write_user)?;
+        write_scoped(f, '!', &scoped.cmnd, write_spec)?;
+
+        write_aliases(f, "User_Alias", &aliases.user.1, write_user)?;
+        write_aliases(f, "Runas_Alias", &aliases.runas.1, write_user)?;
+        write_aliases(f, "Host_Alias", &aliases.host.1, write_host)?;
+        write_aliases(f, "Cmnd_Alias", &aliases.cmnd.1, write_spec)?;
+
+        for rule in rules {
+            write_rule(f, rule)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace every reference to an alias by the members of that alias, and drop the alias
+    /// definitions; the policy still means the same. The exception are the command aliases in
+    /// the scope of a `Defaults!` entry, since that can only list commands without arguments or
+    /// digests: these aliases are kept, with their own members expanded.
+    pub fn expand_aliases(&mut self) {
+        let Sudoers {
+            rules,
+            aliases,
+            scoped_settings: scoped,
+            origins,
+            ..
+        } = self;
+
+        for rule in rules {
+            rule.users = expand_list(&aliases.user.1, &rule.users);
+            for (hosts, runas_cmds) in &mut rule.permissions {
+                *hosts = expand_list(&aliases.host.1, hosts);
+                for RunAs { users, groups } in runas_cmds
+                    .iter_mut()
+                    .filter_map(|(runas, _)| runas.as_mut())
+                {
+                    *users = expand_list(&aliases.runas.1, users);
+                    *groups = expand_groups(&aliases.runas.1, groups);
+                }
+                *runas_cmds = expand_commands(&aliases.cmnd.1, std::mem::take(runas_cmds));
+            }
+        }
+
+        for (specs, _) in &mut scoped.host {
+            *specs = expand_list(&aliases.host.1, specs);
+        }
+        for (specs, _) in &mut scoped.user {
+            *specs = expand_list(&aliases.user.1, specs);
+        }
+        for (specs, _) in &mut scoped.runas {
+            *specs = expand_list(&aliases.runas.1, specs);
+        }
+
+        let in_scope = |name: &String| {
+            let alias = Meta::Alias(name.clone());
+            (scoped.cmnd.iter().flat_map(|(specs, _)| specs))
+                .any(|(Qualified::Allow(meta) | Qualified::Forbid(meta))| *meta == alias)
+        };
+        let (cmnd_aliases, cmnd_origins) = (aliases.cmnd.1.iter().zip(&origins.cmnd_alias))
+            .filter(|(Def(name, _), _)| in_scope(name))
+            .map(|(Def(name, members), origin)| {
+                let members = expand_list(&aliases.cmnd.1, members);
+                (Def(name.clone(), members), origin.clone())
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        *aliases = Default::default();
+        aliases.cmnd = ((0..cmnd_aliases.len()).collect(), cmnd_aliases);
+        origins.user_alias.clear();
+        origins.host_alias.clear();
+        origins.cmnd_alias = cmnd_origins;
+        origins.runas_alias.clear();
+    }
+}
+
+/// How to write a list item of a certain kind in sudoers syntax
+type Write<T> = fn(&mut fmt::Formatter, &Qualified<&Meta<T>>) -> fmt::Result;
+
+fn write_list<T>(f: &mut fmt::Formatter, specs: &[Spec<T>], write: Write<T>) -> fmt::Result {
+    for (i, spec) in specs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write(f, &spec.as_ref())?;
+    }
+
+    Ok(())
+}
+
+/// Write `text` so that the characters for which `escaped` holds are preceded by a backslash
+fn write_escaped(f: &mut fmt::Formatter, text: &str, escaped: fn(char) -> bool) -> fmt::Result {
+    for c in text.chars() {
+        if escaped(c) {
+            f.write_str("\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+
+    Ok(())
+}
+
+fn write_setting(f: &mut fmt::Formatter, name: &str, value: &ConfigValue) -> fmt::Result {
+    match value {
+        ConfigValue::Flag(true) => f.write_str(name),
+        ConfigValue::Flag(false) | ConfigValue::Text(None) => write!(f, "!{name}"),
+        ConfigValue::List(Mode::Set, values) if values.is_empty() => write!(f, "!{name}"),
+        ConfigValue::Text(Some(text)) => {
+            write!(f, "{name}=\"")?;
+            write_escaped(f, text, QuotedText::escaped)?;
+            f.write_str("\"")
+        }
+        ConfigValue::Num(value) => write!(f, "{name}={value}"),
+        ConfigValue::Enum(value) => write!(f, "{name}={}", value.get()),
+        ConfigValue::List(mode, values) => {
+            let operator = match mode {
+                Mode::Set => "=",
+                Mode::Add => "+=",
+                Mode::Del => "-=",
+            };
+            write!(f, "{name}{operator}\"")?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(" ")?;
+                }
+                // an entry can have the form "NAME=value"
+                let (name, value) = match value.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (value.as_str(), None),
+                };
+                write_escaped(f, name, EnvVar::escaped)?;
+                if let Some(value) = value {
+                    f.write_str("=")?;
+                    write_escaped(f, value, EnvVar::escaped)?;
+                }
+            }
+            f.write_str("\"")
+        }
+    }
+}
+
+fn write_scoped<T>(
+    f: &mut fmt::Formatter,
+    scope: char,
+    scoped: &Scoped<T>,
+    write: Write<T>,
+) -> fmt::Result {
+    for (specs, params) in scoped {
+        write!(f, "Defaults{scope}")?;
+        write_list(f, specs, write)?;
+        for (i, (name, value)) in params.iter().enumerate() {
+            f.write_str(if i > 0 { ", " } else { " " })?;
+            write_setting(f, name, value)?;
+        }
+        writeln!(f)?;
+    }
+
+    Ok(())
+}
+
+fn write_aliases<T>(
+    f: &mut fmt::Formatter,
+    keyword: &str,
+    defs: &[Def<T>],
+    write: Write<T>,
+) -> fmt::Result {
+    for Def(name, members) in defs {
+        write!(f, "{keyword} {name} = ")?;
+        write_list(f, members, write)?;
+        writeln!(f)?;
+    }
+
+    Ok(())
+}
+
+fn write_rule(f: &mut fmt::Formatter, rule: &PermissionSpec) -> fmt::Result {
+    write_list(f, &rule.users, write_user)?;
+
+    for (i, (hosts, runas_cmds)) in rule.permissions.iter().enumerate() {
+        f.write_str(if i > 0 { " : " } else { " " })?;
+        write_list(f, hosts, write_host)?;
+        f.write_str(" = ")?;
+
+        let mut last_runas: Option<&RunAs> = None;
+        let mut last_tag = None;
+        for (j, (runas, (tag, spec))) in distribute_tags(runas_cmds).enumerate() {
+            if j > 0 {
+                f.write_str(", ")?;
+            }
+            if let Some(runas @ RunAs { users, groups }) = runas {
+                if !last_runas.is_some_and(|last| std::ptr::eq(last, runas)) {
+                    f.write_str("(")?;
+                    write_list(f, users, write_user)?;
+                    if !groups.is_empty() {
+                        f.write_str(if users.is_empty() { ": " } else { " : " })?;
+                        write_list(f, groups, write_group)?;
+                    }
+                    f.write_str(") ")?;
+                }
+            }
+            last_runas = runas;
+            write_tag(f, &tag, last_tag.as_ref())?;
+            last_tag = Some(tag);
+            write_spec(f, &spec.as_ref())?;
+        }
+    }
+
+    writeln!(f)
+}
+
+/// A copy of the members of the aliases that are referred to in `specs`, in their place
+fn expand_list<T: Clone>(defs: &[Def<T>], specs: &[Spec<T>]) -> Vec<Spec<T>> {
+    let mut expanded = Vec::new();
+    for spec in specs {
+        expand_alias(defs, spec.as_ref(), &mut Vec::new(), &mut expanded);
+    }
+
+    expanded.into_iter().map(to_owned).collect()
+}
+
+fn to_owned<T: Clone>(spec: Qualified<&Meta<T>>) -> Spec<T> {
+    match spec {
+        Qualified::Allow(meta) => Qualified::Allow(meta.clone()),
+        Qualified::Forbid(meta) => Qualified::Forbid(meta.clone()),
+    }
+}
+
+/// Runas groups can refer to a `Runas_Alias`, of which only the plain names and ids can match
+/// a group
+fn expand_groups(
+    defs: &[Def<UserSpecifier>],
+    groups: &[Spec<Identifier>],
+) -> Vec<Spec<Identifier>> {
+    let mut expanded = Vec::new();
+    for group in groups {
+        let (Qualified::Allow(Meta::Alias(name)) | Qualified::Forbid(Meta::Alias(name))) = group
+        else {
+            expanded.push(group.clone());
+            continue;
+        };
+
+        let alias = Meta::Alias(name.clone());
+        let alias = match group {
+            Qualified::Allow(_) => Qualified::Allow(&alias),
+            Qualified::Forbid(_) => Qualified::Forbid(&alias),
+        };
+        let mut members = Vec::new();
+        expand_alias(defs, alias, &mut Vec::new(), &mut members);
+
+        expanded.extend(members.into_iter().filter_map(|member| {
+            let (Qualified::Allow(meta) | Qualified::Forbid(meta)) = member;
+            let meta = match meta {
+                Meta::All => Meta::All,
+                Meta::Only(UserSpecifier::User(ident)) => Meta::Only(ident.clone()),
+                Meta::Only(_) => return None,
+                Meta::Alias(name) => Meta::Alias(name.clone()),
+            };
+            Some(match member {
+                Qualified::Allow(_) => Qualified::Allow(meta),
+                Qualified::Forbid(_) => Qualified::Forbid(meta),
+            })
+        }));
+    }
+
+    expanded
+}
+
+/// A command alias is replaced by its commands; the runas specifier and tags that preceded the
+/// alias are carried over to the commands that follow it, so they are kept with the first one.
+fn expand_commands(
+    defs: &[Def<Command>],
+    runas_cmds: Vec<(Option<RunAs>, CommandSpec)>,
+) -> Vec<(Option<RunAs>, CommandSpec)> {
+    let mut expanded = Vec::new();
+    for (runas, CommandSpec(modifiers, spec)) in runas_cmds {
+        let mut commands = Vec::new();
+        expand_alias(defs, spec.as_ref(), &mut Vec::new(), &mut commands);
+
+        let mut first = Some((runas, modifiers));
+        for command in commands {
+            let (runas, modifiers) = first.take().unwrap_or_default();
+            expanded.push((runas, CommandSpec(modifiers, to_owned(command))));
+        }
+    }
+
+    expanded
+}
+
+fn user(spec: &Qualified<&Meta<UserSpecifier>>) -> Json {
+    Fmt(|f| write_user(f, spec)).to_string().into()
+}
+
+fn group(spec: &Qualified<&Meta<Identifier>>) -> Json {
+    Fmt(|f| write_group(f, spec)).to_string().into()
+}
+
+fn host(spec: &Qualified<&Meta<Hostname>>) -> Json {
+    Fmt(|f| write_host(f, spec)).to_string().into()
+}
+
+fn command(spec: &Qualified<&Meta<Command>>) -> Json {
+    Fmt(|f| write_spec(f, spec)).to_string().into()
+}
+
+fn list<T>(specs: &[Spec<T>], to_json: ToJson<T>) -> Json {
+    Json::Array(specs.iter().map(|spec| to_json(&spec.as_ref())).collect())
+}
+
+fn source(origin: &Origin) -> Json {
+    Json::object([
+        ("file", origin.path.to_string_lossy().into_owned().into()),
+        ("line", origin.position.start.0.into()),
+    ])
+}
+
+fn value_to_json(value: &ConfigValue) -> (&'static str, Json) {
+    match value {
+        ConfigValue::Flag(value) => ("=", (*value).into()),
+        ConfigValue::Text(value) => ("=", value.as_deref().into()),
+        ConfigValue::Num(value) => ("=", (*value).into()),
+        ConfigValue::Enum(value) => ("=", value.get().into()),
+        ConfigValue::List(mode, values) => {
+            let operator = match mode {
+                Mode::Set => "=",
+                Mode::Add => "+=",
+                Mode::Del => "-=",
+            };
+            (
+                operator,
+                values.iter().map(String::as_str).collect::<Vec<_>>().into(),
+            )
+        }
+    }
+}
+
+/// The settings that differ from the built-in defaults, ordered by name; lists are sorted
+fn changed_settings(settings: &Settings) -> Vec<(String, ConfigValue)> {
+    let defaults = Settings::default();
+    let mut changed = Vec::new();
+
+    for name in settings.flags.symmetric_difference(&defaults.flags) {
+        changed.push((
+            name.clone(),
+            ConfigValue::Flag(settings.flags.contains(name)),
+        ));
+    }
+    for (name, value) in &settings.str_value {
+        if defaults.str_value.get(name) != Some(value) {
+            changed.push((name.clone(), ConfigValue::Text(value.clone())));
+        }
+    }
+    for (name, value) in &settings.enum_value {
+        if defaults.enum_value.get(name).map(|default| default.get()) != Some(value.get()) {
+            changed.push((name.clone(), ConfigValue::Enum(value.clone())));
+        }
+    }
+    for (name, value) in &settings.int_value {
+        if defaults.int_value.get(name) != Some(value) {
+            changed.push((name.clone(), ConfigValue::Num(*value)));
+        }
+    }
+    for (name, values) in &settings.list {
+        if defaults.list.get(name) != Some(values) {
+            let mut values = values.iter().cloned().collect::<Vec<_>>();
+            values.sort();
+            changed.push((name.clone(), ConfigValue::List(Mode::Set, values)));
+        }
+    }
+
+    changed.sort_by(|(name, _), (other, _)| name.cmp(other));
+
+    changed
+}
+
+fn settings_to_json(settings: &Settings) -> Json {
+    Json::Object(
+        changed_settings(settings)
+            .into_iter()
+            .map(|(name, value)| (name, value_to_json(&value).1))
+            .collect(),
+    )
+}
+
+fn scoped_to_json<'a, T>(
+    scope: &'a str,
+    scoped: &'a Scoped<T>,
+    origins: &'a [Origin],
+    to_json: ToJson<T>,
+) -> impl Iterator<Item = Json> + 'a {
+    scoped
+        .iter()
+        .zip(origins)
+        .map(move |((specs, params), origin)| {
+            let settings = params
+                .iter()
+                .map(|(name, value)| {
+                    let (operator, value) = value_to_json(value);
+                    Json::object([
+                        ("name", name.as_str().into()),
+                        ("operator", operator.into()),
+                        ("value", value),
+                    ])
+                })
+                .collect();
+
+            Json::object([
+                ("scope", scope.into()),
+                ("match", list(specs, to_json)),
+                ("settings", Json::Array(settings)),
+                ("source", source(origin)),
+            ])
+        })
+}
+
+fn aliases_to_json<T>(defs: &[Def<T>], origins: &[Origin], to_json: ToJson<T>) -> Json {
+    let aliases = defs
+        .iter()
+        .zip(origins)
+        .map(|(Def(name, members), origin)| {
+            let mut expanded = Vec::new();
+            for member in members {
+                expand_alias(defs, member.as_ref(), &mut vec![name], &mut expanded);
+            }
+
+            Json::object([
+                ("name", name.as_str().into()),
+                ("members", list(members, to_json)),
+                (
+                    "expanded",
+                    Json::Array(expanded.iter().map(to_json).collect()),
+                ),
+                ("source", source(origin)),
+            ])
+        })
+        .collect();
+
+    Json::Array(aliases)
+}
+
+/// Replace `spec` by the members of the alias it refers to (if it does), recursively; when an
+/// alias is negated, so are its members. Aliases that are not defined, or that refer to
+/// themselves, are left as they are.
+pub(super) fn expand_alias<'a, T>(
+    defs: &'a [Def<T>],
+    spec: Qualified<&'a Meta<T>>,
+    expanding: &mut Vec<&'a String>,
+    result: &mut Vec<Qualified<&'a Meta<T>>>,
+) {
+    if let Qualified::Allow(Meta::Alias(name)) | Qualified::Forbid(Meta::Alias(name)) = spec {
+        let definition = defs.iter().find(|Def(id, _)| id == name);
+        if let Some(Def(_, members)) = definition.filter(|_| !expanding.contains(&name)) {
+            expanding.push(name);
+            for member in members {
+                let member = match spec {
+                    Qualified::Allow(_) => member.as_ref(),
+                    Qualified::Forbid(_) => member.negate(),
+                };
+                expand_alias(defs, member, expanding, result);
+            }
+            expanding.pop();
+            return;
+        }
+    }
+
+    result.push(spec)
+}
+
+fn rule_to_json(rule: &PermissionSpec, origin: &Origin) -> Json {
+    let permissions = rule
+        .permissions
+        .iter()
+        .map(|(hosts, runas_cmds)| {
+            let commands = distribute_tags(runas_cmds)
+                .map(|(runas, (tag, spec))| {
+                    let runas = runas.map(|RunAs { users, groups }| {
+                        Json::object([
+                            ("users", list(users, user)),
+                            ("groups", list(groups, group)),
+                        ])
+                    });
+                    let tags = Fmt(|f| write_tag(f, &tag, None)).to_string();
+                    let tags = tags.trim_end();
+
+                    Json::object([
+                        ("runas", runas.into()),
+                        ("tags", (!tags.is_empty()).then_some(tags).into()),
+                        ("command", command(&spec.as_ref())),
+                    ])
+                })
+                .collect();
+
+            Json::object([
+                ("hosts", list(hosts, host)),
+                ("commands", Json::Array(commands)),
+            ])
+        })
+        .collect();
+
+    Json::object([
+        ("users", list(&rule.users, user)),
+        ("permissions", Json::Array(permissions)),
+        ("source", source(origin)),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(lines: &[&str]) -> Sudoers {
+        let text = lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>();
+        let (sudoers, errors) = Sudoers::read(text.as_bytes(), "/etc/sudoers").unwrap();
+        assert!(errors.is_empty());
+
+        sudoers
+    }
+
+    fn export(lines: &[&str]) -> String {
+        read(lines).to_json().to_string()
+    }
+
+    /// The output in sudoers syntax has to mean the same as the input
+    fn assert_reparses(sudoers: &str) {
+        let lines = sudoers.lines().collect::<Vec<_>>();
+        assert_eq!(read(&lines).to_sudoers(), sudoers);
+    }
+
+    const POLICY: &[&str] = &[
+        "Defaults !use_pty, passwd_tries=5, secure_path=\"/usr/bin:/opt/my bin\"",
+        "Defaults@WEB env_keep -= \"BAR X=1\\=2\", !env_reset",
+        "Defaults:!ADMINS timestamp_timeout=0",
+        "Defaults!SHELLS noexec",
+        "User_Alias ADMINS = alice, %wheel, !bob",
+        "Runas_Alias OPS = www, %web",
+        "Host_Alias WEB = web1, +webhosts",
+        "Cmnd_Alias SHELLS = /usr/bin/sh, /usr/bin/bash -l",
+        "ADMINS WEB = (OPS : OPS, adm) NOPASSWD: /usr/bin/ls, /usr/bin/vi \"\", PASSWD: SHELLS, (root) !/usr/bin/su : db1 = ALL",
+        "carol ALL = (:wheel) CWD=* /usr/bin/id",
+    ];
+
+    #[test]
+    fn sudoers_syntax() {
+        let sudoers = read(POLICY).to_sudoers();
+
+        assert_eq!(
+            sudoers,
+            r#"Defaults passwd_tries=5
+Defaults secure_path="/usr/bin:/opt/my bin"
+Defaults !use_pty
+Defaults@WEB env_keep-="BAR X=1\=2", !env_reset
+Defaults:!ADMINS timestamp_timeout=0
+Defaults!SHELLS noexec
+User_Alias ADMINS = alice, %wheel, !bob
+Runas_Alias OPS = www, %web
+Host_Alias WEB = web1, +webhosts
+Cmnd_Alias SHELLS = /usr/bin/sh, /usr/bin/bash -l
+ADMINS WEB = (OPS : OPS, adm) NOPASSWD: /usr/bin/ls, /usr/bin/vi "", PASSWD: SHELLS, (root) !/usr/bin/su : db1 = ALL
+carol ALL = (: wheel) CWD=* /usr/bin/id
+"#
+        );
+        assert_reparses(&sudoers);
+    }
+
+    #[test]
+    fn expand_aliases() {
+        let mut sudoers = read(POLICY);
+        sudoers.expand_aliases();
+        let sudoers = sudoers.to_sudoers();
+
+        assert_eq!(
+            sudoers,
+            r#"Defaults passwd_tries=5
+Defaults secure_path="/usr/bin:/opt/my bin"
+Defaults !use_pty
+Defaults@web1, +webhosts env_keep-="BAR X=1\=2", !env_reset
+Defaults:!alice, !%wheel, bob timestamp_timeout=0
+Defaults!SHELLS noexec
+Cmnd_Alias SHELLS = /usr/bin/sh, /usr/bin/bash -l
+alice, %wheel, !bob web1, +webhosts = (www, %web : www, adm) NOPASSWD: /usr/bin/ls, /usr/bin/vi "", PASSWD: /usr/bin/sh, /usr/bin/bash -l, (root) !/usr/bin/su : db1 = ALL
+carol ALL = (: wheel) CWD=* /usr/bin/id
+"#
+        );
+        assert_reparses(&sudoers);
+    }
+
+    #[test]
+    fn defaults() {
+        let json = export(&[
+            "Defaults !use_pty, passwd_tries=5",
+            "Defaults env_keep += FOO",
+            "Defaults:%admin env_keep -= \"BAR BAZ\"",
+        ]);
+
+        assert!(json.contains(r#""defaults":{"env_keep":["#));
+        assert!(json.contains(r#""passwd_tries":5,"use_pty":false}"#));
+        assert!(json.contains(
+            r#""scoped_defaults":[{"scope":"user","match":["%admin"],"settings":[{"name":"env_keep","operator":"-=","value":["BAR","BAZ"]}],"source":{"file":"/etc/sudoers","line":3}}]"#
+        ));
+    }
+
+    #[test]
+    fn expanded_aliases() {
+        let json = export(&[
+            "User_Alias ADMINS = alice, !OPS",
+            "User_Alias OPS = bob, %ops, NESTED",
+            "User_Alias NESTED = carol",
+        ]);
+
+        assert!(json.contains(
+            r#"{"name":"ADMINS","members":["alice","!OPS"],"expanded":["alice","!bob","!%ops","!carol"],"source":{"file":"/etc/sudoers","line":1}}"#
+        ));
+        assert!(json.contains(
+            r#"{"name":"OPS","members":["bob","%ops","NESTED"],"expanded":["bob","%ops","carol"],"#
+        ));
+    }
+
+    #[test]
+    fn rules() {
+        let json = export(&[
+            "Host_Alias WEB = web1",
+            "alice, %wheel WEB = (www : adm) NOPASSWD: /usr/bin/ls, /usr/bin/vi \"\", (root) !/usr/bin/su",
+            "bob ALL = ALL",
+        ]);
+
+        assert!(json.contains(
+            r#""rules":[{"users":["alice","%wheel"],"permissions":[{"hosts":["WEB"],"commands":[{"runas":{"users":["www"],"groups":["adm"]},"tags":"NOPASSWD:","command":"/usr/bin/ls"},{"runas":{"users":["www"],"groups":["adm"]},"tags":"NOPASSWD:","command":"/usr/bin/vi \"\""},{"runas":{"users":["root"],"groups":[]},"tags":"NOPASSWD:","command":"!/usr/bin/su"}]}],"source":{"file":"/etc/sudoers","line":2}}"#
+        ));
+        assert!(json.contains(
+            r#"{"users":["bob"],"permissions":[{"hosts":["ALL"],"commands":[{"runas":null,"tags":null,"command":"ALL"}]}],"source":{"file":"/etc/sudoers","line":3}}]"#
+        ));
+    }
+}
@@ -0,0 +1,108 @@
+//! Group memberships that are not part of the system group database; these are used to resolve
+//! `%:group` in the sudoers file, like the `group_plugin` of the original sudo.
+
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::ast::Identifier;
+use crate::system::interface::UnixUser;
+
+pub trait GroupProvider {
+    /// Whether `user` is a member of the (non-Unix) group `group`
+    fn is_member(&self, user: &dyn UnixUser, group: &Identifier) -> bool;
+}
+
+/// Groups that are defined in a file in the same format as `/etc/group`
+pub struct GroupFile {
+    groups: Vec<GroupEntry>,
+}
+
+struct GroupEntry {
+    name: String,
+    gid: Option<u32>,
+    members: Vec<String>,
+}
+
+impl GroupFile {
+    pub fn open(path: &Path) -> io::Result<GroupFile> {
+        let mut text = String::new();
+        crate::system::secure_open(path, false)?.read_to_string(&mut text)?;
+
+        Ok(GroupFile::parse(&text))
+    }
+
+    fn parse(text: &str) -> GroupFile {
+        let groups = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?;
+                let _password = fields.next()?;
+                let gid = fields.next()?;
+                let members = fields.next()?;
+
+                Some(GroupEntry {
+                    name: name.to_string(),
+                    gid: gid.parse().ok(),
+                    members: members
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|member| !member.is_empty())
+                        .map(String::from)
+                        .collect(),
+                })
+            })
+            .collect();
+
+        GroupFile { groups }
+    }
+}
+
+impl GroupProvider for GroupFile {
+    fn is_member(&self, user: &dyn UnixUser, group: &Identifier) -> bool {
+        self.groups
+            .iter()
+            .filter(|entry| match group {
+                Identifier::Name(name) => entry.name == name.as_str(),
+                Identifier::ID(gid) => entry.gid == Some(*gid),
+            })
+            .any(|entry| entry.members.iter().any(|member| user.has_name(member)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Named(&'static str);
+
+    impl UnixUser for Named {
+        fn has_name(&self, name: &str) -> bool {
+            self.0 == name
+        }
+    }
+
+    #[test]
+    fn group_file_membership() {
+        let groups = GroupFile::parse(
+            "# comment\n\
+             admins:x:5000:alice, bob\n\
+             \n\
+             empty:x:5001:\n\
+             broken:x\n\
+             nogid:x::carol\n",
+        );
+        let name = |name: &str| Identifier::Name(name.into());
+
+        assert!(groups.is_member(&Named("alice"), &name("admins")));
+        assert!(groups.is_member(&Named("bob"), &name("admins")));
+        assert!(groups.is_member(&Named("bob"), &Identifier::ID(5000)));
+        assert!(groups.is_member(&Named("carol"), &name("nogid")));
+        assert!(!groups.is_member(&Named("carol"), &name("admins")));
+        assert!(!groups.is_member(&Named("alice"), &name("empty")));
+        assert!(!groups.is_member(&Named("alice"), &name("broken")));
+        assert!(!groups.is_member(&Named("alice"), &Identifier::ID(5001)));
+    }
+}
@@ -0,0 +1,535 @@
+//! Checks of a sudoers file that go beyond its syntax: mistakes that leave a sudoers file valid,
+//! but that make (parts of) it not do what was intended. These are reported by `visudo`.
+
+use std::ptr;
+
+use super::ast::*;
+use super::tokens::*;
+use super::{distribute_tags, Error, Origin, Scoped, Sudoers};
+use crate::system::{Group, User};
+
+/// The findings of [`Sudoers::lint`]
+#[derive(Default)]
+pub struct Lint {
+    /// mistakes that `visudo --strict` treats as errors
+    pub problems: Vec<Error>,
+    /// things that may very well be intended, such as users that do not exist (yet)
+    pub warnings: Vec<Error>,
+}
+
+/// A reference to an alias, and the line it occurs on
+type Use<'a> = (&'a str, &'a Origin);
+
+impl Sudoers {
+    /// Look for aliases that are not defined or not used, `Defaults` that cannot apply, users and
+    /// groups that are unknown and rules whose commands are all negated by later rules
+    pub fn lint(&self) -> Lint {
+        let mut lint = Lint::default();
+        self.lint_aliases(&mut lint);
+        self.lint_scoped_defaults(&mut lint);
+        self.lint_names(&mut lint);
+        self.lint_negated_rules(&mut lint);
+
+        lint
+    }
+
+    fn lint_aliases(&self, lint: &mut Lint) {
+        let Sudoers {
+            rules,
+            aliases,
+            scoped_settings: scoped,
+            origins,
+            ..
+        } = self;
+
+        let (mut user, mut host, mut cmnd, mut runas) = (vec![], vec![], vec![], vec![]);
+        for (rule, origin) in rules.iter().zip(&origins.rules) {
+            add_uses(&mut user, &rule.users, origin);
+            for (hosts, runas_cmds) in &rule.permissions {
+                add_uses(&mut host, hosts, origin);
+                for (runas_spec, CommandSpec(_, cmnd_spec)) in runas_cmds {
+                    if let Some(RunAs { users, groups }) = runas_spec {
+                        add_uses(&mut runas, users, origin);
+                        add_uses(&mut runas, groups, origin);
+                    }
+                    add_uses(&mut cmnd, std::slice::from_ref(cmnd_spec), origin);
+                }
+            }
+        }
+
+        add_scope_uses(&mut host, &scoped.host, &origins.host_defaults);
+        add_scope_uses(&mut user, &scoped.user, &origins.user_defaults);
+        add_scope_uses(&mut runas, &scoped.runas, &origins.runas_defaults);
+        add_scope_uses(&mut cmnd, &scoped.cmnd, &origins.cmnd_defaults);
+
+        lint_alias_uses(
+            "User_Alias",
+            &aliases.user.1,
+            &origins.user_alias,
+            &user,
+            lint,
+        );
+        lint_alias_uses(
+            "Host_Alias",
+            &aliases.host.1,
+            &origins.host_alias,
+            &host,
+            lint,
+        );
+        lint_alias_uses(
+            "Cmnd_Alias",
+            &aliases.cmnd.1,
+            &origins.cmnd_alias,
+            &cmnd,
+            lint,
+        );
+        lint_alias_uses(
+            "Runas_Alias",
+            &aliases.runas.1,
+            &origins.runas_alias,
+            &runas,
+            lint,
+        );
+    }
+
+    fn lint_scoped_defaults(&self, lint: &mut Lint) {
+        let Sudoers {
+            aliases,
+            scoped_settings: scoped,
+            origins,
+            ..
+        } = self;
+
+        let host = scoped.host.iter().zip(&origins.host_defaults);
+        let host = host.filter(|((scope, _), _)| never_matches(scope, &aliases.host.1));
+        let user = scoped.user.iter().zip(&origins.user_defaults);
+        let user = user.filter(|((scope, _), _)| never_matches(scope, &aliases.user.1));
+        let runas = scoped.runas.iter().zip(&origins.runas_defaults);
+        let runas = runas.filter(|((scope, _), _)| never_matches(scope, &aliases.runas.1));
+        let cmnd = scoped.cmnd.iter().zip(&origins.cmnd_defaults);
+        let cmnd = cmnd.filter(|((scope, _), _)| never_matches(scope, &aliases.cmnd.1));
+
+        let never_applies = host
+            .map(|(_, origin)| (origin, "host"))
+            .chain(user.map(|(_, origin)| (origin, "user")))
+            .chain(runas.map(|(_, origin)| (origin, "runas user")))
+            .chain(cmnd.map(|(_, origin)| (origin, "command")));
+        for (origin, what) in never_applies {
+            let message = format!("Defaults entry cannot apply to any {what}");
+            lint.problems.push(origin.error(message));
+        }
+
+        // these are needed before the runas user and the command are known
+        let runas = scoped.runas.iter().map(|(_, settings)| settings);
+        let runas = runas.zip(&origins.runas_defaults);
+        let cmnd = scoped.cmnd.iter().map(|(_, settings)| settings);
+        let cmnd = cmnd.zip(&origins.cmnd_defaults);
+        for (settings, origin) in runas.chain(cmnd) {
+            for (name, _) in settings {
+                let message = match name.as_str() {
+                    "env_editor" => "env_editor cannot be set for a runas user or command",
+                    "secure_path" => {
+                        "secure_path for a runas user or command is not used to find the command"
+                    }
+                    _ => continue,
+                };
+                lint.warnings.push(origin.error(message.to_string()));
+            }
+        }
+    }
+
+    fn lint_names(&self, lint: &mut Lint) {
+        let Sudoers {
+            rules,
+            aliases,
+            scoped_settings: scoped,
+            origins,
+            ..
+        } = self;
+
+        let mut users = vec![];
+        let mut groups = vec![];
+        for (rule, origin) in rules.iter().zip(&origins.rules) {
+            users.extend(rule.users.iter().map(|spec| (spec, origin)));
+            for (_, runas_cmds) in &rule.permissions {
+                for (runas, _) in runas_cmds {
+                    if let Some(runas) = runas {
+                        users.extend(runas.users.iter().map(|spec| (spec, origin)));
+                        groups.extend(runas.groups.iter().map(|spec| (spec, origin)));
+                    }
+                }
+            }
+        }
+        let user_aliases = aliases.user.1.iter().zip(&origins.user_alias);
+        let runas_aliases = aliases.runas.1.iter().zip(&origins.runas_alias);
+        for (Def(_, members), origin) in user_aliases.chain(runas_aliases) {
+            users.extend(members.iter().map(|spec| (spec, origin)));
+        }
+        let user_defaults = scoped.user.iter().zip(&origins.user_defaults);
+        let runas_defaults = scoped.runas.iter().zip(&origins.runas_defaults);
+        for ((scope, _), origin) in user_defaults.chain(runas_defaults) {
+            users.extend(scope.iter().map(|spec| (spec, origin)));
+        }
+
+        let mut unknown: Vec<(String, &Origin)> = vec![];
+        for (spec, origin) in users {
+            let (Qualified::Allow(Meta::Only(spec)) | Qualified::Forbid(Meta::Only(spec))) = spec
+            else {
+                continue;
+            };
+            match spec {
+                UserSpecifier::User(Identifier::Name(name))
+                    if matches!(User::from_name(name.as_cstr()), Ok(None)) =>
+                {
+                    unknown.push((format!("unknown user \"{name}\""), origin))
+                }
+                UserSpecifier::Group(Identifier::Name(name))
+                    if matches!(Group::from_name(name.as_cstr()), Ok(None)) =>
+                {
+                    unknown.push((format!("unknown group \"{name}\""), origin))
+                }
+                _ => {}
+            }
+        }
+        for (spec, origin) in groups {
+            let (Qualified::Allow(Meta::Only(Identifier::Name(name)))
+            | Qualified::Forbid(Meta::Only(Identifier::Name(name)))) = spec
+            else {
+                continue;
+            };
+            if matches!(Group::from_name(name.as_cstr()), Ok(None)) {
+                unknown.push((format!("unknown group \"{name}\""), origin))
+            }
+        }
+
+        // a name that is mentioned twice on a line only needs to be reported once
+        for (index, (message, origin)) in unknown.iter().enumerate() {
+            if !unknown[..index]
+                .iter()
+                .any(|(seen, seen_at)| seen == message && ptr::eq(*seen_at, *origin))
+            {
+                lint.warnings.push(origin.error(message.clone()));
+            }
+        }
+    }
+
+    fn lint_negated_rules(&self, lint: &mut Lint) {
+        /// A command that a rule allows or forbids, together with whom that applies to
+        struct Grant<'a> {
+            rule: usize,
+            users: &'a SpecList<UserSpecifier>,
+            hosts: &'a SpecList<Hostname>,
+            runas: Option<&'a RunAs>,
+            command: &'a Spec<Command>,
+        }
+
+        let grants = self
+            .rules
+            .iter()
+            .enumerate()
+            .flat_map(|(rule, spec)| {
+                spec.permissions
+                    .iter()
+                    .flat_map(move |(hosts, runas_cmds)| {
+                        distribute_tags(runas_cmds).map(move |(runas, (_, command))| Grant {
+                            rule,
+                            users: &spec.users,
+                            hosts,
+                            runas,
+                            command,
+                        })
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        // since the last matching command decides, a command that is forbidden later on for
+        // (at least) the same users, hosts and runas users and groups has no effect
+        let is_negated_later = |index: usize| {
+            let grant = &grants[index];
+            let Qualified::Allow(command) = grant.command else {
+                return false;
+            };
+            grants[index + 1..].iter().any(|later| match later.command {
+                Qualified::Forbid(negated) => {
+                    (*negated == Meta::All || negated == command)
+                        && covers(later.users, grant.users)
+                        && covers(later.hosts, grant.hosts)
+                        && runas_covers(later.runas, grant.runas)
+                }
+                Qualified::Allow(_) => false,
+            })
+        };
+
+        for (rule, origin) in self.origins.rules.iter().enumerate() {
+            let mut allowed = (0..grants.len())
+                .filter(|&index| grants[index].rule == rule)
+                .filter(|&index| matches!(grants[index].command, Qualified::Allow(_)))
+                .peekable();
+
+            if allowed.peek().is_some() && allowed.all(&is_negated_later) {
+                let message = "all commands of this rule are negated by a later rule".to_string();
+                lint.problems.push(origin.error(message));
+            }
+        }
+    }
+}
+
+fn alias_names<T>(list: &[Spec<T>]) -> impl Iterator<Item = &str> {
+    list.iter().filter_map(|spec| match spec {
+        Qualified::Allow(Meta::Alias(name)) | Qualified::Forbid(Meta::Alias(name)) => {
+            Some(name.as_str())
+        }
+        _ => None,
+    })
+}
+
+fn add_uses<'a, T>(uses: &mut Vec<Use<'a>>, list: &'a [Spec<T>], origin: &'a Origin) {
+    for name in alias_names(list) {
+        // an alias that is mentioned twice on a line only needs to be reported once
+        if !uses
+            .iter()
+            .any(|&(used, seen)| used == name && ptr::eq(seen, origin))
+        {
+            uses.push((name, origin));
+        }
+    }
+}
+
+fn add_scope_uses<'a, T>(uses: &mut Vec<Use<'a>>, scoped: &'a Scoped<T>, origins: &'a [Origin]) {
+    for ((scope, _), origin) in scoped.iter().zip(origins) {
+        add_uses(uses, scope, origin);
+    }
+}
+
+/// Report aliases of a kind that are used without being defined, or defined without being used;
+/// aliases that are undefined but used in other alias definitions are already reported when the
+/// alias table is sanitized.
+fn lint_alias_uses<T>(
+    kind: &str,
+    defs: &[Def<T>],
+    def_origins: &[Origin],
+    uses: &[Use],
+    lint: &mut Lint,
+) {
+    let is_defined = |name: &str| defs.iter().any(|Def(id, _)| id == name);
+
+    for (name, origin) in uses {
+        if !is_defined(name) {
+            let message = format!("{kind} \"{name}\" referenced but not defined");
+            lint.problems.push(origin.error(message));
+        }
+    }
+
+    for (Def(id, _), origin) in defs.iter().zip(def_origins) {
+        let used_by_rules = uses.iter().any(|(name, _)| name == id);
+        let used_by_aliases = defs
+            .iter()
+            .any(|Def(other, members)| other != id && alias_names(members).any(|name| name == id));
+        if !used_by_rules && !used_by_aliases {
+            lint.problems
+                .push(origin.error(format!("unused {kind} \"{id}\"")));
+        }
+    }
+}
+
+/// Whether a `Defaults` scope cannot match anything: i.e. it only consists of negations and
+/// aliases that are not defined
+fn never_matches<T>(scope: &[Spec<T>], defs: &[Def<T>]) -> bool {
+    let definition = |name: &String| defs.iter().find(|Def(id, _)| id == name);
+
+    !scope.iter().any(|spec| match spec {
+        Qualified::Allow(Meta::Alias(name)) => definition(name).is_some(),
+        Qualified::Allow(_) => true,
+        // a negated alias matches something if the alias itself contains a negation
+        Qualified::Forbid(Meta::Alias(name)) => definition(name).is_some_and(|Def(_, members)| {
+            members
+                .iter()
+                .any(|member| matches!(member, Qualified::Forbid(_)))
+        }),
+        Qualified::Forbid(_) => false,
+    })
+}
+
+/// Whether everything that the `earlier` list matches, is also matched by the `later` list
+fn covers<T: PartialEq>(later: &[Spec<T>], earlier: &[Spec<T>]) -> bool {
+    if later
+        .iter()
+        .any(|spec| matches!(spec, Qualified::Forbid(_)))
+    {
+        false
+    } else if later.contains(&Qualified::Allow(Meta::All)) {
+        true
+    } else if earlier.is_empty() {
+        later.is_empty()
+    } else {
+        earlier.iter().all(|spec| match spec {
+            Qualified::Allow(_) => later.contains(spec),
+            Qualified::Forbid(_) => true,
+        })
+    }
+}
+
+/// Whether every target user and group that `earlier` allows, is also allowed by `later`
+fn runas_covers(later: Option<&RunAs>, earlier: Option<&RunAs>) -> bool {
+    match (later, earlier) {
+        (None, None) => true,
+        // without a runas specification, only root is allowed
+        (Some(later), None) => covers(&later.users, &[Qualified::Allow(Meta::All)]),
+        (None, Some(_)) => false,
+        // with an empty list of groups, the group cannot be changed
+        (Some(later), Some(earlier)) => {
+            covers(&later.users, &earlier.users)
+                && (earlier.groups.is_empty() || covers(&later.groups, &earlier.groups))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lint(lines: &[&str]) -> Lint {
+        let text = lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>();
+        let (sudoers, errors) = Sudoers::read(text.as_bytes(), "/etc/sudoers").unwrap();
+        assert!(errors.is_empty());
+
+        sudoers.lint()
+    }
+
+    fn messages(errors: &[Error]) -> Vec<&str> {
+        errors.iter().map(|error| error.message.as_str()).collect()
+    }
+
+    #[test]
+    fn clean_sudoers() {
+        let lint = lint(&[
+            "User_Alias ADMINS = root",
+            "Cmnd_Alias SHELLS = /bin/sh",
+            "Defaults:ADMINS !env_editor",
+            "ADMINS ALL=(ALL:ALL) ALL, !SHELLS",
+            "root ALL=(ALL:ALL) /bin/ls",
+        ]);
+        assert!(lint.problems.is_empty());
+        assert!(lint.warnings.is_empty());
+    }
+
+    #[test]
+    fn undefined_and_unused_aliases() {
+        let lint = lint(&[
+            "User_Alias ADMINS = root",
+            "Host_Alias SERVERS = server",
+            "OPERATORS SERVERS=(BACKUP) ALL",
+            "Defaults!SHELLS noexec",
+        ]);
+        assert_eq!(
+            messages(&lint.problems),
+            [
+                "User_Alias \"OPERATORS\" referenced but not defined",
+                "unused User_Alias \"ADMINS\"",
+                "Cmnd_Alias \"SHELLS\" referenced but not defined",
+                "Runas_Alias \"BACKUP\" referenced but not defined",
+                "Defaults entry cannot apply to any command",
+            ]
+        );
+
+        let location = lint.problems[0].location.clone().unwrap();
+        assert_eq!(location.start, (3, 1));
+        assert_eq!(
+            lint.problems[0].source.as_deref(),
+            Some("/etc/sudoers".as_ref())
+        );
+    }
+
+    #[test]
+    fn aliases_used_by_aliases_are_used() {
+        let lint = lint(&[
+            "User_Alias ADMINS = root",
+            "User_Alias STAFF = ADMINS",
+            "STAFF ALL=(ALL:ALL) ALL",
+        ]);
+        assert!(lint.problems.is_empty());
+    }
+
+    #[test]
+    fn defaults_that_cannot_apply() {
+        let lint = lint(&[
+            "User_Alias NOT_ROOT = !root",
+            "Defaults:!root !env_editor",
+            "Defaults@!server !env_editor",
+            "Defaults:!NOT_ROOT !env_editor",
+            "Defaults>root,!root !env_editor",
+        ]);
+        assert_eq!(
+            messages(&lint.problems),
+            [
+                "Defaults entry cannot apply to any host",
+                "Defaults entry cannot apply to any user",
+            ]
+        );
+    }
+
+    #[test]
+    fn defaults_needed_before_the_command_is_known() {
+        let lint = lint(&[
+            "Cmnd_Alias SHELLS = /bin/sh",
+            "Defaults:root secure_path=/bin, env_editor",
+            "Defaults>root secure_path=/bin",
+            "Defaults!SHELLS !env_editor",
+        ]);
+        assert!(lint.problems.is_empty());
+        assert_eq!(
+            messages(&lint.warnings),
+            [
+                "secure_path for a runas user or command is not used to find the command",
+                "env_editor cannot be set for a runas user or command",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_users_and_groups_are_warnings() {
+        let lint = lint(&[
+            "root, sudo-rs-nobody, %sudo-rs-nogroup ALL=(sudo-rs-nobody:sudo-rs-nogroup) ALL",
+            "Defaults:sudo-rs-nobody !env_editor",
+        ]);
+        assert!(lint.problems.is_empty());
+        assert_eq!(
+            messages(&lint.warnings),
+            [
+                "unknown user \"sudo-rs-nobody\"",
+                "unknown group \"sudo-rs-nogroup\"",
+                "unknown user \"sudo-rs-nobody\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn negated_rules() {
+        let negated = |lines: &[&str]| messages(&lint(lines).problems).len();
+
+        assert_eq!(negated(&["root ALL=/bin/ls", "root ALL=!/bin/ls"]), 1);
+        assert_eq!(negated(&["root ALL=/bin/ls", "ALL ALL=(ALL:ALL) !ALL"]), 1);
+        assert_eq!(negated(&["root ALL=/bin/ls, !/bin/ls"]), 1);
+        assert_eq!(
+            negated(&["root ALL=(ALL) /bin/ls", "ALL ALL=(ALL) !ALL"]),
+            1
+        );
+
+        // only part of what the rule allows is negated
+        assert_eq!(
+            negated(&["root ALL=/bin/ls, /bin/cat", "root ALL=!/bin/ls"]),
+            0
+        );
+        assert_eq!(
+            negated(&["root, daemon ALL=/bin/ls", "root ALL=!/bin/ls"]),
+            0
+        );
+        assert_eq!(negated(&["root ALL=(ALL) /bin/ls", "root ALL=!/bin/ls"]), 0);
+        assert_eq!(negated(&["root ALL=/bin/ls", "ALL,!daemon ALL=!ALL"]), 0);
+        // an earlier negation does not override anything
+        assert_eq!(negated(&["root ALL=!/bin/ls", "root ALL=/bin/ls"]), 0);
+    }
+}
@@ -7,17 +7,21 @@ mod ast_names;
 mod basic_parser;
 mod char_stream;
 mod entry;
+mod export;
+mod group_provider;
+mod lint;
 mod tokens;
 
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::{io, mem};
 
-use crate::common::resolve::resolve_path;
+use crate::common::digest::DigestAlgorithm;
 use crate::log::auth_warn;
+use crate::system;
 use crate::system::interface::{UnixGroup, UnixUser};
-use crate::system::{self, can_execute};
 use ast::*;
+use group_provider::{GroupFile, GroupProvider};
 use tokens::*;
 
 /// How many nested include files do we allow?
@@ -36,16 +40,71 @@ pub struct Sudoers {
     rules: Vec<PermissionSpec>,
     aliases: AliasTable,
     settings: Settings,
+    scoped_settings: ScopedSettings,
+    group_provider: Option<Box<dyn GroupProvider>>,
+    origins: Origins,
 }
 
+/// Where the rules, aliases and scoped `Defaults` of a [`Sudoers`] were found; every field lines
+/// up with the corresponding list in the `Sudoers` itself
+#[derive(Default)]
+struct Origins {
+    /// all files that were read, in the order in which they were included
+    files: Vec<PathBuf>,
+    rules: Vec<Origin>,
+    user_alias: Vec<Origin>,
+    host_alias: Vec<Origin>,
+    cmnd_alias: Vec<Origin>,
+    runas_alias: Vec<Origin>,
+    host_defaults: Vec<Origin>,
+    user_defaults: Vec<Origin>,
+    runas_defaults: Vec<Origin>,
+    cmnd_defaults: Vec<Origin>,
+}
+
+/// The file and the line in it that an item of a sudoers file was read from
+#[derive(Clone)]
+struct Origin {
+    path: PathBuf,
+    position: basic_parser::Position,
+}
+
+impl Origin {
+    fn error(&self, message: String) -> Error {
+        Error {
+            source: Some(self.path.clone()),
+            location: Some(self.position.clone()),
+            message,
+        }
+    }
+}
+
+/// `Defaults` that only apply to certain hosts, users, runas users or commands
+#[derive(Default)]
+struct ScopedSettings {
+    host: Scoped<tokens::Hostname>,
+    user: Scoped<UserSpecifier>,
+    runas: Scoped<UserSpecifier>,
+    cmnd: Scoped<Command>,
+}
+
+type Scoped<T> = Vec<(SpecList<T>, Vec<(String, ConfigValue)>)>;
+
+/// The `Cmnd_Spec`s of a `User_Spec` that apply to one list of hosts
+type CmndSpecList = [(Option<RunAs>, CommandSpec)];
+
 /// A structure that represents what the user wants to do
 pub struct Request<'a, User: UnixUser, Group: UnixGroup> {
     pub user: &'a User,
     pub group: &'a Group,
     pub command: &'a Path,
     pub arguments: &'a [String],
+    /// computes the digest of the executable of the command (if there is one)
+    pub digest: &'a DigestFn<'a>,
 }
 
+pub type DigestFn<'a> = dyn Fn(DigestAlgorithm) -> Option<Box<[u8]>> + 'a;
+
 pub struct ListRequest<'a, User: UnixUser, Group: UnixGroup> {
     pub target_user: &'a User,
     pub target_group: &'a Group,
@@ -55,14 +114,19 @@ pub struct ListRequest<'a, User: UnixUser, Group: UnixGroup> {
 pub struct Judgement {
     flags: Option<Tag>,
     settings: Settings,
+    user_listed: bool,
 }
 
 mod policy;
 
+pub use lint::Lint;
 pub use policy::{Authorization, AuthorizationAllowed, DirChange, Policy, PreJudgementPolicy};
 
 pub use self::entry::Entry;
 
+/// Reads the contents of a file that is included by a sudoers file
+pub type IncludeReader<'a> = dyn FnMut(&Path) -> io::Result<Vec<u8>> + 'a;
+
 /// This function takes a file argument for a sudoers file and processes it.
 impl Sudoers {
     pub fn open(path: impl AsRef<Path>) -> Result<(Sudoers, Vec<Error>), io::Error> {
@@ -78,6 +142,25 @@ impl Sudoers {
         Ok(analyze(path.as_ref(), sudoers))
     }
 
+    /// Like `read`, but the files that are included are read with `read_include` instead of
+    /// [`Sudoers::read_include`]; so that e.g. `visudo` can check edited copies of them.
+    pub fn read_with_includes<R: io::Read, P: AsRef<Path>>(
+        reader: R,
+        path: P,
+        read_include: &mut IncludeReader,
+    ) -> Result<(Sudoers, Vec<Error>), io::Error> {
+        let sudoers = read_sudoers(reader)?;
+        Ok(analyze_with_includes(path.as_ref(), sudoers, read_include))
+    }
+
+    /// Read a file that is included by a sudoers file, refusing files that are not secure
+    pub fn read_include(path: &Path) -> io::Result<Vec<u8>> {
+        let mut source = crate::system::secure_open(path, true)?;
+        let mut contents = Vec::new();
+        io::Read::read_to_end(&mut source, &mut contents)?;
+        Ok(contents)
+    }
+
     pub fn check<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
         &self,
         am_user: &User,
@@ -88,16 +171,60 @@ impl Sudoers {
         let skip_passwd =
             am_user.is_root() || (request.user == am_user && in_group(am_user, request.group));
 
-        let mut flags = check_permission(self, am_user, on_host, request);
+        let aliases = &self.aliases;
+        let mut settings = self.specific_settings(am_user, on_host);
+        let runas_match = match_user(request.user, self.nonunix_groups());
+        apply_scoped_settings(
+            &mut settings,
+            &self.scoped_settings.runas,
+            &runas_match,
+            &get_aliases(&aliases.runas, &runas_match),
+        );
+        let cmnd_match = match_command((request.command, request.arguments), request.digest);
+        apply_scoped_settings(
+            &mut settings,
+            &self.scoped_settings.cmnd,
+            &cmnd_match,
+            &get_aliases(&aliases.cmnd, &cmnd_match),
+        );
+
+        let mut flags = if request.command == Path::new("sudoedit") {
+            // every file is judged on its own; otherwise a rule that forbids editing a file could
+            // be sidestepped by editing it together with a file that is allowed
+            request
+                .arguments
+                .iter()
+                .map(|file| {
+                    let request = Request {
+                        arguments: std::slice::from_ref(file),
+                        ..request
+                    };
+                    check_permission(self, am_user, on_host, request)
+                })
+                .reduce(|outcome, tag| {
+                    let (outcome, tag) = (outcome?, tag?);
+                    Some(if outcome.needs_passwd() { outcome } else { tag })
+                })
+                .flatten()
+        } else {
+            check_permission(self, am_user, on_host, request)
+        };
         if let Some(Tag { authenticate, .. }) = flags.as_mut() {
             if skip_passwd {
                 *authenticate = Authenticate::Nopasswd;
             }
         }
 
+        let user_listed = self
+            .matching_user_specs(am_user, on_host)
+            .flatten()
+            .next()
+            .is_some();
+
         Judgement {
             flags,
-            settings: self.settings.clone(), // this is wasteful, but in the future this will not be a simple clone and it avoids a lifetime
+            settings,
+            user_listed,
         }
     }
 
@@ -112,18 +239,8 @@ impl Sudoers {
             || (request.target_user == invoking_user
                 && in_group(invoking_user, request.target_group));
 
-        let mut flags = self
-            .matching_user_specs(invoking_user, hostname)
-            .flatten()
-            .fold(None::<Tag>, |outcome, (_, (tag, _))| {
-                if let Some(outcome) = outcome {
-                    let new_outcome = if outcome.needs_passwd() { tag } else { outcome };
-
-                    Some(new_outcome)
-                } else {
-                    Some(tag)
-                }
-            });
+        let settings = self.specific_settings(invoking_user, hostname);
+        let mut flags = self.pseudo_command_tag(invoking_user, hostname, &settings, "listpw");
 
         if let Some(Tag { authenticate, .. }) = flags.as_mut() {
             if skip_passwd {
@@ -132,8 +249,109 @@ impl Sudoers {
         }
 
         Judgement {
+            user_listed: flags.is_some(),
+            flags,
+            settings,
+        }
+    }
+
+    pub fn check_validate_permission<User: UnixUser + PartialEq<User>>(
+        &self,
+        invoking_user: &User,
+        hostname: &system::Hostname,
+    ) -> Judgement {
+        let settings = self.specific_settings(invoking_user, hostname);
+        let mut flags = self.pseudo_command_tag(invoking_user, hostname, &settings, "verifypw");
+
+        // exception: if user is root, NOPASSWD is implied
+        if let Some(Tag { authenticate, .. }) = flags.as_mut() {
+            if invoking_user.is_root() {
+                *authenticate = Authenticate::Nopasswd;
+            }
+        }
+
+        Judgement {
+            user_listed: flags.is_some(),
             flags,
-            settings: self.settings.clone(), // this is wasteful, but in the future this will not be a simple clone and it avoids a lifetime
+            settings,
+        }
+    }
+
+    /// determines the tag for a pseudo-command like `sudo -v` or `sudo -l`, based on all the
+    /// entries that apply to the invoking user and on the setting `policy`, which is `verifypw`
+    /// or `listpw`: with "all", every entry must be NOPASSWD to not require a password; with
+    /// "any", one NOPASSWD entry suffices.
+    fn pseudo_command_tag<User: UnixUser + PartialEq<User>>(
+        &self,
+        invoking_user: &User,
+        hostname: &system::Hostname,
+        settings: &Settings,
+        policy: &str,
+    ) -> Option<Tag> {
+        let tags = self
+            .matching_user_specs(invoking_user, hostname)
+            .flatten()
+            .map(|(_, (tag, _))| tag);
+
+        let policy = settings.enum_value[policy].get();
+        let mut outcome = tags.reduce(|outcome, tag| {
+            let prefer_new = match policy {
+                "any" => outcome.needs_passwd(),
+                _ => !outcome.needs_passwd(),
+            };
+            if prefer_new {
+                tag
+            } else {
+                outcome
+            }
+        })?;
+
+        match policy {
+            "always" => outcome.authenticate = Authenticate::Passwd,
+            "never" => outcome.authenticate = Authenticate::Nopasswd,
+            _ => {}
+        }
+
+        Some(outcome)
+    }
+
+    /// returns the settings for `invoking_user` on `hostname`; i.e. the generic `Defaults`,
+    /// overridden by the `Defaults@host` and then the `Defaults:user` that match
+    fn specific_settings<User: UnixUser>(
+        &self,
+        invoking_user: &User,
+        hostname: &system::Hostname,
+    ) -> Settings {
+        let Self {
+            aliases,
+            settings,
+            scoped_settings,
+            ..
+        } = self;
+        let mut settings = settings.clone();
+
+        let host_match = match_host(hostname);
+        apply_scoped_settings(
+            &mut settings,
+            &scoped_settings.host,
+            &host_match,
+            &get_aliases(&aliases.host, &host_match),
+        );
+        let user_match = match_user(invoking_user, self.nonunix_groups());
+        apply_scoped_settings(
+            &mut settings,
+            &scoped_settings.user,
+            &user_match,
+            &get_aliases(&aliases.user, &user_match),
+        );
+
+        settings
+    }
+
+    fn nonunix_groups(&self) -> NonunixGroups<'_> {
+        NonunixGroups {
+            provider: self.group_provider.as_deref(),
+            always_query: self.settings.flags.contains("always_query_group_plugin"),
         }
     }
 
@@ -149,21 +367,74 @@ impl Sudoers {
         hostname: &'c system::Hostname,
     ) -> impl Iterator<Item = impl Iterator<Item = (Option<&'a RunAs>, (Tag, &'a Spec<Command>))> + 'b>
            + 'c {
+        self.matching_permissions(Some(invoking_user), Some(hostname))
+            .map(|(_, runas_cmds)| distribute_tags(runas_cmds))
+    }
+
+    /// returns the host and `Cmnd_Spec` lists of the `User_Spec`s that match `invoking_user` and
+    /// that apply on `hostname`; when either is not given, it matches anything
+    ///
+    /// every list comes with the index of its `User_Spec` and its index within it
+    fn matching_permissions<'a: 'b + 'c, 'b: 'c, 'c, User: UnixUser + PartialEq<User>>(
+        &'a self,
+        invoking_user: Option<&'b User>,
+        hostname: Option<&'c system::Hostname>,
+    ) -> impl Iterator<Item = ((usize, usize), &'a CmndSpecList)> + 'c {
         let Self { rules, aliases, .. } = self;
-        let user_aliases = get_aliases(&aliases.user, &match_user(invoking_user));
-        let host_aliases = get_aliases(&aliases.host, &match_token(hostname));
+        let nonunix_groups = self.nonunix_groups();
+        let user_aliases =
+            invoking_user.map(|user| get_aliases(&aliases.user, &match_user(user, nonunix_groups)));
+        let host_aliases =
+            hostname.map(|hostname| get_aliases(&aliases.host, &match_host(hostname)));
 
         rules
             .iter()
-            .filter_map(move |sudo| {
-                find_item(&sudo.users, &match_user(invoking_user), &user_aliases)?;
-                Some(&sudo.permissions)
+            .enumerate()
+            .filter(move |(_, sudo)| match (invoking_user, &user_aliases) {
+                (Some(user), Some(user_aliases)) => {
+                    find_item(&sudo.users, &match_user(user, nonunix_groups), user_aliases)
+                        .is_some()
+                }
+                _ => true,
             })
-            .flatten()
-            .filter_map(move |(hosts, runas_cmds)| {
-                find_item(hosts, &match_token(hostname), &host_aliases)?;
-                Some(distribute_tags(runas_cmds))
+            .flat_map(|(i, sudo)| {
+                (sudo.permissions.iter().enumerate())
+                    .map(move |(j, permission)| ((i, j), permission))
+            })
+            .filter(move |(_, (hosts, _))| match (hostname, &host_aliases) {
+                (Some(hostname), Some(host_aliases)) => {
+                    find_item(hosts, &match_host(hostname), host_aliases).is_some()
+                }
+                _ => true,
             })
+            .map(|(position, (_, runas_cmds))| (position, runas_cmds.as_slice()))
+    }
+
+    /// Keep only the rules that match `invoking_user`, and of those only the parts that apply on
+    /// `hostname`; e.g. to extract the policy for one host from a shared sudoers file
+    pub fn retain_matching<User: UnixUser + PartialEq<User>>(
+        &mut self,
+        invoking_user: Option<&User>,
+        hostname: Option<&system::Hostname>,
+    ) {
+        let matching = self
+            .matching_permissions(invoking_user, hostname)
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        let rules = std::mem::take(&mut self.rules);
+        let origins = std::mem::take(&mut self.origins.rules);
+        for (i, (mut rule, origin)) in rules.into_iter().zip(origins).enumerate() {
+            let mut j = 0;
+            rule.permissions.retain(|_| {
+                j += 1;
+                matching.contains(&(i, j - 1))
+            });
+            if !rule.permissions.is_empty() {
+                self.rules.push(rule);
+                self.origins.rules.push(origin);
+            }
+        }
     }
 
     /// returns `User_Spec`s that match `invoking_user` and `hostname` in a print-able format
@@ -184,28 +455,6 @@ impl Sudoers {
 
         entries
     }
-
-    pub(crate) fn solve_editor_path(&self) -> Option<PathBuf> {
-        if self.settings.flags.contains("env_editor") {
-            for key in ["SUDO_EDITOR", "VISUAL", "EDITOR"] {
-                if let Some(var) = std::env::var_os(key) {
-                    let path = Path::new(&var);
-                    if can_execute(path) {
-                        return Some(path.to_owned());
-                    }
-                    let path = resolve_path(
-                        path,
-                        &std::env::var("PATH").unwrap_or(env!("DEFAULT_PATH").to_string()),
-                    );
-                    if let Some(path) = path {
-                        return Some(path);
-                    }
-                }
-            }
-        }
-
-        None
-    }
 }
 
 fn group_cmd_specs_per_runas<'a>(
@@ -257,26 +506,26 @@ fn group_cmd_specs_per_runas<'a>(
     }
 }
 
-fn read_sudoers<R: io::Read>(mut reader: R) -> io::Result<Vec<basic_parser::Parsed<Sudo>>> {
+/// A line of a sudoers file, together with its position
+type Line = (basic_parser::Position, basic_parser::Parsed<Sudo>);
+
+fn read_sudoers<R: io::Read>(mut reader: R) -> io::Result<Vec<Line>> {
     // it's a bit frustrating that BufReader.chars() does not exist
     let mut buffer = String::new();
     reader.read_to_string(&mut buffer)?;
 
-    use basic_parser::parse_lines;
+    use basic_parser::parse_lines_with_pos;
     use char_stream::*;
-    Ok(parse_lines(&mut PeekableWithPos::new(buffer.chars())))
+    Ok(parse_lines_with_pos(&mut PeekableWithPos::new(
+        buffer.chars(),
+    )))
 }
 
-fn open_sudoers(path: &Path) -> io::Result<Vec<basic_parser::Parsed<Sudo>>> {
+fn open_sudoers(path: &Path) -> io::Result<Vec<Line>> {
     let source = crate::system::secure_open(path, false)?;
     read_sudoers(source)
 }
 
-fn open_subsudoers(path: &Path) -> io::Result<Vec<basic_parser::Parsed<Sudo>>> {
-    let source = crate::system::secure_open(path, true)?;
-    read_sudoers(source)
-}
-
 #[derive(Default)]
 pub(super) struct AliasTable {
     user: VecOrd<Def<UserSpecifier>>,
@@ -309,8 +558,9 @@ fn check_permission<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
     let cmdline = (request.command, request.arguments);
 
     let aliases = &sudoers.aliases;
-    let cmnd_aliases = get_aliases(&aliases.cmnd, &match_command(cmdline));
-    let runas_user_aliases = get_aliases(&aliases.runas, &match_user(request.user));
+    let cmnd_aliases = get_aliases(&aliases.cmnd, &match_command(cmdline, request.digest));
+    let nonunix_groups = sudoers.nonunix_groups();
+    let runas_user_aliases = get_aliases(&aliases.runas, &match_user(request.user, nonunix_groups));
     let runas_group_aliases = get_aliases(&aliases.runas, &match_group_alias(request.group));
 
     // NOTE to ensure `sudo $command` and `sudo --list` behave the same, both this function and
@@ -321,7 +571,11 @@ fn check_permission<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
         if let Some(RunAs { users, groups }) = runas {
             let stays_in_group = in_group(request.user, request.group);
             if request.user != am_user || (stays_in_group && !users.is_empty()) {
-                find_item(users, &match_user(request.user), &runas_user_aliases)?
+                find_item(
+                    users,
+                    &match_user(request.user, nonunix_groups),
+                    &runas_user_aliases,
+                )?
             }
             if !stays_in_group {
                 find_item(groups, &match_group(request.group), &runas_group_aliases)?
@@ -330,10 +584,23 @@ fn check_permission<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
             None?;
         }
 
-        Some(cmdspec)
+        Some(implied_setenv(cmdspec))
     });
 
-    find_item(allowed_commands, &match_command(cmdline), &cmnd_aliases)
+    find_item(
+        allowed_commands,
+        &match_command(cmdline, request.digest),
+        &cmnd_aliases,
+    )
+}
+
+/// A command specification of "ALL" implies the SETENV tag, unless NOSETENV is given explicitly
+fn implied_setenv((mut tag, spec): (Tag, &Spec<Command>)) -> (Tag, &Spec<Command>) {
+    if matches!(spec, Qualified::Allow(Meta::All)) && tag.env == EnvironmentControl::Implicit {
+        tag.env = EnvironmentControl::Setenv;
+    }
+
+    (tag, spec)
 }
 
 /// Process a raw parsed AST bit of RunAs + Command specifications:
@@ -345,17 +612,36 @@ fn distribute_tags(
 ) -> impl Iterator<Item = (Option<&RunAs>, (Tag, &Spec<Command>))> {
     runas_cmds.iter().scan(
         (None, Default::default()),
-        |(mut last_runas, tag), (runas, CommandSpec(mods, cmd))| {
-            last_runas = runas.as_ref().or(last_runas);
+        |(last_runas, tag), (runas, CommandSpec(mods, cmd))| {
+            // a command without a runas specifier inherits the one of the previous command
+            *last_runas = runas.as_ref().or(*last_runas);
             for f in mods {
                 f(tag);
             }
 
-            Some((last_runas, (tag.clone(), cmd)))
+            Some((*last_runas, (tag.clone(), cmd)))
         },
     )
 }
 
+/// Apply the settings of the scoped `Defaults` whose scope matches, in the order of the sudoers file
+fn apply_scoped_settings<Predicate, T>(
+    settings: &mut Settings,
+    scoped: &Scoped<T>,
+    matches: &Predicate,
+    aliases: &FoundAliases,
+) where
+    Predicate: Fn(&T) -> bool,
+{
+    for (scope, params) in scoped {
+        if find_item(scope, matches, aliases).is_some() {
+            for (name, value) in params {
+                settings.set(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
 /// A type to represent positive or negative association with an alias; i.e. if a key maps to true,
 /// the alias affirms membership, if a key maps to false, the alias denies membership; if a key
 /// isn't present membership is affirmed nor denied
@@ -432,12 +718,36 @@ impl<'a> WithInfo for (Tag, &'a Spec<Command>) {
 }
 
 /// Now follow a collection of functions used as closures for `find_item`
-fn match_user(user: &impl UnixUser) -> impl Fn(&UserSpecifier) -> bool + '_ {
+fn match_user<'a>(
+    user: &'a impl UnixUser,
+    groups: NonunixGroups<'a>,
+) -> impl Fn(&UserSpecifier) -> bool + 'a {
     move |spec| match spec {
         UserSpecifier::User(id) => match_identifier(user, id),
-        UserSpecifier::Group(Identifier::Name(name)) => user.in_group_by_name(name.as_cstr()),
+        UserSpecifier::Group(Identifier::Name(name)) => {
+            user.in_group_by_name(name.as_cstr())
+                || (groups.always_query
+                    && matches!(system::Group::from_name(name.as_cstr()), Ok(None))
+                    && groups.contains(user, &Identifier::Name(name.clone())))
+        }
         UserSpecifier::Group(Identifier::ID(num)) => user.in_group_by_gid(*num),
-        _ => todo!(), // nonunix-groups, netgroups, etc.
+        UserSpecifier::NonunixGroup(group) => groups.contains(user, group),
+        UserSpecifier::Netgroup(name) => user.in_netgroup(name.as_cstr()),
+    }
+}
+
+/// The provider that is consulted for `%:group`; if `always_query` is set, it is also consulted
+/// for `%group` if there is no system group of that name.
+#[derive(Clone, Copy)]
+struct NonunixGroups<'a> {
+    provider: Option<&'a dyn GroupProvider>,
+    always_query: bool,
+}
+
+impl NonunixGroups<'_> {
+    fn contains(&self, user: &impl UnixUser, group: &Identifier) -> bool {
+        self.provider
+            .is_some_and(|provider| provider.is_member(user, group))
     }
 }
 
@@ -464,20 +774,41 @@ fn match_group_alias(group: &impl UnixGroup) -> impl Fn(&UserSpecifier) -> bool
     }
 }
 
-fn match_token<T: basic_parser::Token + std::ops::Deref<Target = String>>(
-    text: &str,
-) -> (impl Fn(&T) -> bool + '_) {
-    move |token| token.as_str() == text
+fn match_host(hostname: &system::Hostname) -> impl Fn(&tokens::Hostname) -> bool + '_ {
+    move |token| match token.netgroup() {
+        Some(netgroup) => {
+            std::ffi::CString::new(netgroup).is_ok_and(|netgroup| hostname.in_netgroup(&netgroup))
+        }
+        None => token.as_str() == &hostname[..],
+    }
 }
 
-fn match_command<'a>((cmd, args): (&'a Path, &'a [String])) -> (impl Fn(&Command) -> bool + 'a) {
+fn match_command<'a>(
+    (cmd, args): (&'a Path, &'a [String]),
+    digest: &'a DigestFn<'a>,
+) -> (impl Fn(&Command) -> bool + 'a) {
     let opts = glob::MatchOptions {
         require_literal_separator: true,
         ..glob::MatchOptions::new()
     };
-    move |(cmdpat, argpat)| {
+    move |((cmdpat, argpat), digests)| {
         cmdpat.matches_path_with(cmd, opts)
-            && argpat.as_ref().map_or(true, |vec| args == vec.as_ref())
+            && (digests.is_empty()
+                || digests
+                    .iter()
+                    .any(|Digest(algorithm, value)| digest(*algorithm).as_ref() == Some(value)))
+            && argpat.as_ref().map_or(true, |vec| {
+                if cmdpat.as_str() == "sudoedit" {
+                    // the arguments of sudoedit are wildcard patterns for the files to edit
+                    args.iter().all(|file| {
+                        vec.iter().any(|pat| {
+                            glob::Pattern::new(pat).is_ok_and(|pat| pat.matches_with(file, opts))
+                        })
+                    })
+                } else {
+                    args == vec.as_ref()
+                }
+            })
     }
 }
 
@@ -568,12 +899,54 @@ impl Default for Settings {
     }
 }
 
+impl Settings {
+    /// Apply a single `Defaults` parameter
+    fn set(&mut self, name: String, value: ConfigValue) {
+        use ConfigValue::*;
+        match value {
+            Flag(value) => {
+                if value {
+                    self.flags.insert(name);
+                } else {
+                    self.flags.remove(&name);
+                }
+            }
+            List(mode, values) => {
+                let slot: &mut _ = self.list.entry(name).or_default();
+                match mode {
+                    Mode::Set => *slot = values.into_iter().collect(),
+                    Mode::Add => slot.extend(values),
+                    Mode::Del => {
+                        for key in values {
+                            slot.remove(&key);
+                        }
+                    }
+                }
+            }
+            Text(value) => {
+                self.str_value.insert(name, value);
+            }
+            Enum(value) => {
+                self.enum_value.insert(name, value);
+            }
+            Num(value) => {
+                self.int_value.insert(name, value);
+            }
+        }
+    }
+}
+
 /// Process a sudoers-parsing file into a workable AST
-fn analyze(
+fn analyze(path: &Path, sudoers: impl IntoIterator<Item = Line>) -> (Sudoers, Vec<Error>) {
+    analyze_with_includes(path, sudoers, &mut Sudoers::read_include)
+}
+
+/// Process a sudoers-parsing file into a workable AST, reading included files with `read_include`
+fn analyze_with_includes(
     path: &Path,
-    sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>,
+    sudoers: impl IntoIterator<Item = Line>,
+    read_include: &mut IncludeReader,
 ) -> (Sudoers, Vec<Error>) {
-    use ConfigValue::*;
     use Directive::*;
 
     let mut result: Sudoers = Default::default();
@@ -594,6 +967,7 @@ fn analyze(
         cfg: &mut Sudoers,
         parent: &Path,
         path: &Path,
+        read_include: &mut IncludeReader,
         diagnostics: &mut Vec<Error>,
         count: &mut u8,
     ) {
@@ -606,10 +980,10 @@ fn analyze(
         // FIXME: this will cause an error in `visudo` if we open a non-privileged sudoers file
         // that includes another non-privileged sudoer files.
         } else {
-            match open_subsudoers(path) {
+            match read_include(path).and_then(|contents| read_sudoers(contents.as_slice())) {
                 Ok(subsudoer) => {
                     *count += 1;
-                    process(cfg, path, subsudoer, diagnostics, count)
+                    process(cfg, path, subsudoer, read_include, diagnostics, count)
                 }
                 Err(e) => {
                     let message = if e.kind() == io::ErrorKind::NotFound {
@@ -632,46 +1006,101 @@ fn analyze(
     fn process(
         cfg: &mut Sudoers,
         cur_path: &Path,
-        sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>,
+        sudoers: impl IntoIterator<Item = Line>,
+        read_include: &mut IncludeReader,
         diagnostics: &mut Vec<Error>,
         safety_count: &mut u8,
     ) {
-        for item in sudoers {
+        fn define<T>(
+            table: &mut Vec<Def<T>>,
+            origins: &mut Vec<Origin>,
+            mut defs: Vec<Def<T>>,
+            origin: Origin,
+        ) {
+            origins.extend(std::iter::repeat(origin).take(defs.len()));
+            table.append(&mut defs);
+        }
+
+        cfg.origins.files.push(cur_path.to_owned());
+
+        for (position, item) in sudoers {
+            let origin = Origin {
+                path: cur_path.to_owned(),
+                position,
+            };
+            let origins = &mut cfg.origins;
             match item {
                 Ok(line) => match line {
                     Sudo::LineComment => {}
 
-                    Sudo::Spec(permission) => cfg.rules.push(permission),
+                    Sudo::Spec(permission) => {
+                        cfg.rules.push(permission);
+                        origins.rules.push(origin);
+                    }
 
-                    Sudo::Decl(UserAlias(mut def)) => cfg.aliases.user.1.append(&mut def),
-                    Sudo::Decl(HostAlias(mut def)) => cfg.aliases.host.1.append(&mut def),
-                    Sudo::Decl(CmndAlias(mut def)) => cfg.aliases.cmnd.1.append(&mut def),
-                    Sudo::Decl(RunasAlias(mut def)) => cfg.aliases.runas.1.append(&mut def),
+                    Sudo::Decl(UserAlias(def)) => define(
+                        &mut cfg.aliases.user.1,
+                        &mut origins.user_alias,
+                        def,
+                        origin,
+                    ),
+                    Sudo::Decl(HostAlias(def)) => define(
+                        &mut cfg.aliases.host.1,
+                        &mut origins.host_alias,
+                        def,
+                        origin,
+                    ),
+                    Sudo::Decl(CmndAlias(def)) => define(
+                        &mut cfg.aliases.cmnd.1,
+                        &mut origins.cmnd_alias,
+                        def,
+                        origin,
+                    ),
+                    Sudo::Decl(RunasAlias(def)) => define(
+                        &mut cfg.aliases.runas.1,
+                        &mut origins.runas_alias,
+                        def,
+                        origin,
+                    ),
 
-                    Sudo::Decl(Defaults(params)) => {
-                        for (name, value) in params {
-                            set_default(cfg, name, value)
+                    Sudo::Decl(Defaults(params, scope)) => {
+                        let scoped = &mut cfg.scoped_settings;
+                        match scope {
+                            ConfigScope::Generic => {
+                                for (name, value) in params {
+                                    cfg.settings.set(name, value)
+                                }
+                            }
+                            ConfigScope::Host(hosts) => {
+                                scoped.host.push((hosts, params));
+                                origins.host_defaults.push(origin);
+                            }
+                            ConfigScope::User(users) => {
+                                scoped.user.push((users, params));
+                                origins.user_defaults.push(origin);
+                            }
+                            ConfigScope::RunAs(users) => {
+                                scoped.runas.push((users, params));
+                                origins.runas_defaults.push(origin);
+                            }
+                            ConfigScope::Command(cmnds) => {
+                                scoped.cmnd.push((cmnds, params));
+                                origins.cmnd_defaults.push(origin);
+                            }
                         }
                     }
 
                     Sudo::Include(path) => include(
                         cfg,
                         cur_path,
-                        &resolve_relative(cur_path, path),
+                        &resolve_relative(cur_path, expand_include_path(&path)),
+                        read_include,
                         diagnostics,
                         safety_count,
                     ),
 
                     Sudo::IncludeDir(path) => {
-                        if path.contains("%h") {
-                            diagnostics.push(Error{
-                                    source: Some(cur_path.to_owned()),
-                                    location: None,
-                                    message: format!("cannot open sudoers file {path}: percent escape %h in includedir is unsupported")});
-                            continue;
-                        }
-
-                        let path = resolve_relative(cur_path, path);
+                        let path = resolve_relative(cur_path, expand_include_path(&path));
                         let Ok(files) = std::fs::read_dir(&path) else {
                             diagnostics.push(Error {
                                 source: Some(cur_path.to_owned()),
@@ -693,7 +1122,14 @@ fn analyze(
                             .collect::<Vec<_>>();
                         safe_files.sort();
                         for file in safe_files {
-                            include(cfg, cur_path, file.as_ref(), diagnostics, safety_count)
+                            include(
+                                cfg,
+                                cur_path,
+                                file.as_ref(),
+                                read_include,
+                                diagnostics,
+                                safety_count,
+                            )
                         }
                     }
                 },
@@ -708,56 +1144,86 @@ fn analyze(
         }
     }
 
-    fn set_default(cfg: &mut Sudoers, name: String, value: ConfigValue) {
-        match value {
-            Flag(value) => {
-                if value {
-                    cfg.settings.flags.insert(name);
-                } else {
-                    cfg.settings.flags.remove(&name);
-                }
-            }
-            List(mode, values) => {
-                let slot: &mut _ = cfg.settings.list.entry(name).or_default();
-                match mode {
-                    Mode::Set => *slot = values.into_iter().collect(),
-                    Mode::Add => slot.extend(values),
-                    Mode::Del => {
-                        for key in values {
-                            slot.remove(&key);
-                        }
-                    }
-                }
-            }
-            Text(value) => {
-                cfg.settings.str_value.insert(name, value);
-            }
-            Enum(value) => {
-                cfg.settings.enum_value.insert(name, value);
-            }
-            Num(value) => {
-                cfg.settings.int_value.insert(name, value);
-            }
+    let mut diagnostics = vec![];
+    process(
+        &mut result,
+        path,
+        sudoers,
+        read_include,
+        &mut diagnostics,
+        &mut 0,
+    );
+
+    let (alias, origins) = (&mut result.aliases, &result.origins);
+    alias.user.0 = sanitize_alias_table(&alias.user.1, &origins.user_alias, &mut diagnostics);
+    alias.host.0 = sanitize_alias_table(&alias.host.1, &origins.host_alias, &mut diagnostics);
+    alias.cmnd.0 = sanitize_alias_table(&alias.cmnd.1, &origins.cmnd_alias, &mut diagnostics);
+    alias.runas.0 = sanitize_alias_table(&alias.runas.1, &origins.runas_alias, &mut diagnostics);
+
+    if let Some(group_file) = result.settings.str_value["group_file"].as_deref() {
+        // a relative path would depend on the directory sudo happens to be started from
+        let opened = if Path::new(group_file).is_absolute() {
+            GroupFile::open(Path::new(group_file))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not an absolute path",
+            ))
+        };
+        match opened {
+            Ok(groups) => result.group_provider = Some(Box::new(groups)),
+            Err(e) => diagnostics.push(Error {
+                source: Some(path.to_owned()),
+                location: None,
+                message: format!("cannot open group file '{group_file}': {e}"),
+            }),
         }
     }
 
-    let mut diagnostics = vec![];
-    process(&mut result, path, sudoers, &mut diagnostics, &mut 0);
+    (result, diagnostics)
+}
 
-    let alias = &mut result.aliases;
-    alias.user.0 = sanitize_alias_table(&alias.user.1, &mut diagnostics);
-    alias.host.0 = sanitize_alias_table(&alias.host.1, &mut diagnostics);
-    alias.cmnd.0 = sanitize_alias_table(&alias.cmnd.1, &mut diagnostics);
-    alias.runas.0 = sanitize_alias_table(&alias.runas.1, &mut diagnostics);
+/// Expand the escapes in the path of an `@include` or `@includedir`: `%h` is replaced by the short
+/// host name (i.e. without the domain) and `%%` by a single percent sign.
+fn expand_include_path(path: &str) -> String {
+    if path.contains('%') {
+        expand_percent_escapes(path, &system::Hostname::resolve())
+    } else {
+        path.to_string()
+    }
+}
 
-    (result, diagnostics)
+fn expand_percent_escapes(path: &str, hostname: &system::Hostname) -> String {
+    let short_name = hostname.split('.').next().unwrap_or_default();
+
+    let mut result = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('%', Some('h')) => {
+                chars.next();
+                result.push_str(short_name);
+            }
+            ('%', Some('%')) => {
+                chars.next();
+                result.push('%');
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
 }
 
 /// Alias definition inin a Sudoers file can come in any order; and aliases can refer to other aliases, etc.
 /// It is much easier if they are presented in a "definitional order" (i.e. aliases that use other aliases occur later)
 /// At the same time, this is a good place to detect problems in the aliases, such as unknown aliases and cycles.
 
-fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) -> Vec<usize> {
+fn sanitize_alias_table<T>(
+    table: &Vec<Def<T>>,
+    origins: &[Origin],
+    diagnostics: &mut Vec<Error>,
+) -> Vec<usize> {
     fn remqualify<U>(item: &Qualified<U>) -> &U {
         match item {
             Qualified::Allow(x) => x,
@@ -769,16 +1235,20 @@ fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) ->
     struct Visitor<'a, T> {
         seen: HashSet<usize>,
         table: &'a Vec<Def<T>>,
+        origins: &'a [Origin],
         order: Vec<usize>,
         diagnostics: &'a mut Vec<Error>,
     }
 
     impl<T> Visitor<'_, T> {
-        fn complain(&mut self, text: String) {
-            self.diagnostics.push(Error {
-                source: None,
-                location: None,
-                message: text,
+        fn complain(&mut self, pos: usize, text: String) {
+            self.diagnostics.push(match self.origins.get(pos) {
+                Some(origin) => origin.error(text),
+                None => Error {
+                    source: None,
+                    location: None,
+                    message: text,
+                },
             })
         }
 
@@ -787,11 +1257,11 @@ fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) ->
                 let Def(_, members) = &self.table[pos];
                 for elem in members {
                     let Meta::Alias(name) = remqualify(elem) else {
-                        break;
+                        continue;
                     };
                     let Some(dependency) = self.table.iter().position(|Def(id, _)| id == name)
                     else {
-                        self.complain(format!("undefined alias: '{name}'"));
+                        self.complain(pos, format!("undefined alias: '{name}'"));
                         continue;
                     };
                     self.visit(dependency);
@@ -799,7 +1269,7 @@ fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) ->
                 self.order.push(pos);
             } else if !self.order.contains(&pos) {
                 let Def(id, _) = &self.table[pos];
-                self.complain(format!("recursive alias: '{id}'"));
+                self.complain(pos, format!("recursive alias: '{id}'"));
             }
         }
     }
@@ -807,6 +1277,7 @@ fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) ->
     let mut visitor = Visitor {
         seen: HashSet::new(),
         table,
+        origins,
         order: Vec::with_capacity(table.len()),
         diagnostics,
     };
@@ -814,7 +1285,7 @@ fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) ->
     let mut dupe = HashSet::new();
     for (i, Def(name, _)) in table.iter().enumerate() {
         if !dupe.insert(name) {
-            visitor.complain(format!("multiple occurrences of '{name}'"));
+            visitor.complain(i, format!("multiple occurrences of '{name}'"));
         } else {
             visitor.visit(i);
         }
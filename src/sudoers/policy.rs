@@ -1,14 +1,20 @@
 use super::Sudoers;
 
-use super::Judgement;
+use super::ast::EnvironmentControl;
+use super::{Judgement, Settings};
+use crate::common::resolve::resolve_path;
 use crate::common::{SudoPath, HARDENED_ENUM_VALUE_0, HARDENED_ENUM_VALUE_1};
-use crate::system::time::Duration;
+use crate::iolog::IoLogOptions;
+use crate::log::mail::{Incident, MailOptions};
+use crate::system::{can_execute, Hostname, User};
+use crate::system::{time::Duration, timestamp::TimestampType};
 /// Data types and traits that represent what the "terms and conditions" are after a succesful
 /// permission check.
 ///
 /// The trait definitions can be part of some global crate in the future, if we support more
 /// than just the sudoers file.
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 pub trait Policy {
     fn authorization(&self) -> Authorization {
@@ -19,12 +25,55 @@ pub trait Policy {
         DirChange::Strict(None)
     }
 
+    /// Whether the environment of the command is built from scratch, instead of starting from
+    /// the environment of the invoking user (minus the variables matching `env_delete`)
+    fn env_reset(&self) -> bool {
+        true
+    }
+
     fn env_keep(&self) -> &HashSet<String>;
     fn env_check(&self) -> &HashSet<String>;
+    fn env_delete(&self) -> &HashSet<String>;
+
+    /// Whether `HOME` is always set to the home directory of the target user
+    fn always_set_home(&self) -> bool {
+        false
+    }
+
+    /// Whether the user may set arbitrary environment variables for the command (using `VAR=value`
+    /// or `--preserve-env`), without them being subject to `env_keep` and `env_check`
+    fn allows_setenv(&self) -> bool {
+        false
+    }
 
     fn secure_path(&self) -> Option<String>;
 
     fn use_pty(&self) -> bool;
+
+    /// Where and which streams of the command should be recorded, if any
+    fn iolog(&self) -> Option<IoLogOptions> {
+        None
+    }
+
+    /// Whether the command should be prevented from executing further commands
+    fn noexec(&self) -> bool {
+        false
+    }
+
+    /// Whether the user may choose which file descriptors are closed (using `--close-from`)
+    fn closefrom_override(&self) -> bool {
+        false
+    }
+
+    /// Whether any rule applies to the invoking user on this host
+    fn user_listed(&self) -> bool {
+        false
+    }
+
+    /// How to notify the administrator of `incident`, if that is wanted
+    fn mail(&self, _incident: Incident) -> Option<MailOptions> {
+        None
+    }
 }
 
 #[must_use]
@@ -40,6 +89,18 @@ pub struct AuthorizationAllowed {
     pub must_authenticate: bool,
     pub allowed_attempts: u16,
     pub prior_validity: Duration,
+    /// The prompt to ask for a password with, instead of the one PAM provides
+    pub prompt: Option<String>,
+    /// Whether `prompt` replaces any PAM prompt, instead of only the standard password prompt
+    pub prompt_override: bool,
+    /// Whether typing a password shows an asterisk for every character
+    pub pwfeedback: bool,
+    /// Whether a password may be asked for if it cannot be hidden
+    pub visiblepw: bool,
+    /// How long a password prompt waits for an answer, if at all limited
+    pub password_timeout: Option<std::time::Duration>,
+    /// Which sessions share a successful authentication
+    pub timestamp_type: TimestampType,
 }
 
 #[must_use]
@@ -59,12 +120,32 @@ impl Policy for Judgement {
                 must_authenticate: tag.needs_passwd(),
                 allowed_attempts,
                 prior_validity: Duration::seconds(valid_seconds),
+                prompt: self.settings.str_value["passprompt"]
+                    .as_deref()
+                    .map(String::from),
+                prompt_override: self.settings.flags.contains("passprompt_override"),
+                pwfeedback: self.settings.flags.contains("pwfeedback"),
+                visiblepw: self.settings.flags.contains("visiblepw"),
+                password_timeout: u64::try_from(self.settings.int_value["passwd_timeout"])
+                    .ok()
+                    .filter(|&secs| secs > 0)
+                    .map(std::time::Duration::from_secs),
+                timestamp_type: match self.settings.enum_value["timestamp_type"].get() {
+                    "global" => TimestampType::Global,
+                    "ppid" => TimestampType::Ppid,
+                    "kernel" => TimestampType::Kernel,
+                    _ => TimestampType::Tty,
+                },
             })
         } else {
             Authorization::Forbidden
         }
     }
 
+    fn env_reset(&self) -> bool {
+        self.settings.flags.contains("env_reset")
+    }
+
     fn env_keep(&self) -> &HashSet<String> {
         &self.settings.list["env_keep"]
     }
@@ -73,6 +154,22 @@ impl Policy for Judgement {
         &self.settings.list["env_check"]
     }
 
+    fn env_delete(&self) -> &HashSet<String> {
+        &self.settings.list["env_delete"]
+    }
+
+    fn always_set_home(&self) -> bool {
+        self.settings.flags.contains("always_set_home")
+    }
+
+    fn allows_setenv(&self) -> bool {
+        match self.flags.as_ref().map(|tag| tag.env) {
+            Some(EnvironmentControl::Setenv) => true,
+            Some(EnvironmentControl::Nosetenv) | None => false,
+            Some(EnvironmentControl::Implicit) => self.settings.flags.contains("setenv"),
+        }
+    }
+
     fn chdir(&self) -> DirChange {
         match self.flags.as_ref().expect("not authorized").cwd.as_ref() {
             None => DirChange::Strict(None),
@@ -90,26 +187,131 @@ impl Policy for Judgement {
     fn use_pty(&self) -> bool {
         self.settings.flags.contains("use_pty")
     }
+
+    fn iolog(&self) -> Option<IoLogOptions> {
+        let tag = self.flags.as_ref()?;
+        let log_input = tag
+            .log_input
+            .unwrap_or_else(|| self.settings.flags.contains("log_input"));
+        let log_output = tag
+            .log_output
+            .unwrap_or_else(|| self.settings.flags.contains("log_output"));
+
+        if !log_input && !log_output {
+            return None;
+        }
+
+        let dir = self.settings.str_value["iolog_dir"].as_deref()?;
+
+        Some(IoLogOptions {
+            dir: dir.into(),
+            log_input,
+            log_output,
+        })
+    }
+
+    fn noexec(&self) -> bool {
+        let noexec = self.flags.as_ref().and_then(|tag| tag.noexec);
+
+        noexec.unwrap_or_else(|| self.settings.flags.contains("noexec"))
+    }
+
+    fn closefrom_override(&self) -> bool {
+        self.settings.flags.contains("closefrom_override")
+    }
+
+    fn user_listed(&self) -> bool {
+        self.user_listed
+    }
+
+    fn mail(&self, incident: Incident) -> Option<MailOptions> {
+        mail_options(&self.settings, incident)
+    }
+}
+
+fn mail_options(settings: &Settings, incident: Incident) -> Option<MailOptions> {
+    let flag = match incident {
+        Incident::AuthFailure => "mail_badpass",
+        Incident::NotInSudoers => "mail_no_user",
+        Incident::CommandDenied => "mail_no_perms",
+    };
+    if !settings.flags.contains(flag) {
+        return None;
+    }
+
+    let mailer = settings.str_value["mailerpath"].as_deref()?;
+    let mailto = settings.str_value["mailto"].as_deref()?;
+    let flags = settings.str_value["mailerflags"]
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|flag| flag.to_string())
+        .collect();
+
+    Some(MailOptions {
+        mailer: mailer.into(),
+        flags,
+        mailto: mailto.to_string(),
+    })
 }
 
+/// The parts of the policy that are needed before the command and target user are known; these
+/// take the `Defaults@host` and `Defaults:user` into account, but not `Defaults>runas` and
+/// `Defaults!cmnd` (these are reported by `visudo`, see [`Sudoers::lint`]).
 pub trait PreJudgementPolicy {
-    fn secure_path(&self) -> Option<String>;
-    fn validate_authorization(&self) -> Authorization;
+    fn secure_path(&self, invoking_user: &User, hostname: &Hostname) -> Option<String>;
+    fn validate_authorization(&self, invoking_user: &User, hostname: &Hostname) -> Authorization;
+    fn solve_editor_path(&self, invoking_user: &User, hostname: &Hostname) -> Option<PathBuf>;
+    fn mail(
+        &self,
+        invoking_user: &User,
+        hostname: &Hostname,
+        incident: Incident,
+    ) -> Option<MailOptions>;
 }
 
 impl PreJudgementPolicy for Sudoers {
-    fn secure_path(&self) -> Option<String> {
-        self.settings.str_value["secure_path"]
+    fn secure_path(&self, invoking_user: &User, hostname: &Hostname) -> Option<String> {
+        self.specific_settings(invoking_user, hostname).str_value["secure_path"]
             .as_ref()
             .map(|s| s.to_string())
     }
 
-    fn validate_authorization(&self) -> Authorization {
-        Authorization::Allowed(AuthorizationAllowed {
-            must_authenticate: true,
-            allowed_attempts: self.settings.int_value["passwd_tries"].try_into().unwrap(),
-            prior_validity: Duration::seconds(self.settings.int_value["timestamp_timeout"]),
-        })
+    fn validate_authorization(&self, invoking_user: &User, hostname: &Hostname) -> Authorization {
+        self.check_validate_permission(invoking_user, hostname)
+            .authorization()
+    }
+
+    fn solve_editor_path(&self, invoking_user: &User, hostname: &Hostname) -> Option<PathBuf> {
+        let settings = self.specific_settings(invoking_user, hostname);
+        if settings.flags.contains("env_editor") {
+            for key in ["SUDO_EDITOR", "VISUAL", "EDITOR"] {
+                if let Some(var) = std::env::var_os(key) {
+                    let path = Path::new(&var);
+                    if can_execute(path) {
+                        return Some(path.to_owned());
+                    }
+                    let path = resolve_path(
+                        path,
+                        &std::env::var("PATH").unwrap_or(env!("DEFAULT_PATH").to_string()),
+                    );
+                    if let Some(path) = path {
+                        return Some(path);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn mail(
+        &self,
+        invoking_user: &User,
+        hostname: &Hostname,
+        incident: Incident,
+    ) -> Option<MailOptions> {
+        mail_options(&self.specific_settings(invoking_user, hostname), incident)
     }
 }
 
@@ -141,6 +343,12 @@ mod test {
                 must_authenticate: true,
                 allowed_attempts: 3,
                 prior_validity: Duration::minutes(15),
+                prompt: None,
+                prompt_override: false,
+                pwfeedback: false,
+                visiblepw: false,
+                password_timeout: Some(std::time::Duration::from_secs(5 * 60)),
+                timestamp_type: TimestampType::Tty,
             })
         );
         judge.mod_flag(|tag| tag.authenticate = Authenticate::Nopasswd);
@@ -150,6 +358,12 @@ mod test {
                 must_authenticate: false,
                 allowed_attempts: 3,
                 prior_validity: Duration::minutes(15),
+                prompt: None,
+                prompt_override: false,
+                pwfeedback: false,
+                visiblepw: false,
+                password_timeout: Some(std::time::Duration::from_secs(5 * 60)),
+                timestamp_type: TimestampType::Tty,
             })
         );
     }
@@ -168,4 +382,110 @@ mod test {
         judge.mod_flag(|tag| tag.cwd = Some(ChDir::Path("/bin".into())));
         assert_eq!(judge.chdir(), (DirChange::Strict(Some(&"/bin".into()))));
     }
+
+    #[test]
+    fn setenv_test() {
+        let mut judge: Judgement = Default::default();
+        assert!(!judge.allows_setenv());
+        judge.mod_flag(|_| {});
+        assert!(!judge.allows_setenv());
+        judge.settings.flags.insert("setenv".to_string());
+        assert!(judge.allows_setenv());
+        judge.mod_flag(|tag| tag.env = EnvironmentControl::Nosetenv);
+        assert!(!judge.allows_setenv());
+        judge.settings.flags.remove("setenv");
+        judge.mod_flag(|tag| tag.env = EnvironmentControl::Setenv);
+        assert!(judge.allows_setenv());
+    }
+
+    #[test]
+    fn iolog_test() {
+        let mut judge: Judgement = Default::default();
+        judge.settings.flags.insert("log_output".to_string());
+        assert_eq!(judge.iolog(), None);
+        judge.mod_flag(|_| {});
+        assert_eq!(
+            judge.iolog(),
+            Some(IoLogOptions {
+                dir: "/var/log/sudo-io".into(),
+                log_input: false,
+                log_output: true,
+            })
+        );
+        judge.mod_flag(|tag| tag.log_output = Some(false));
+        assert_eq!(judge.iolog(), None);
+        judge.mod_flag(|tag| tag.log_input = Some(true));
+        judge
+            .settings
+            .str_value
+            .insert("iolog_dir".to_string(), Some("/var/log/sudo-rs-io".into()));
+        assert_eq!(
+            judge.iolog(),
+            Some(IoLogOptions {
+                dir: "/var/log/sudo-rs-io".into(),
+                log_input: true,
+                log_output: false,
+            })
+        );
+    }
+
+    #[test]
+    fn noexec_test() {
+        let mut judge: Judgement = Default::default();
+        assert!(!judge.noexec());
+        judge.settings.flags.insert("noexec".to_string());
+        assert!(judge.noexec());
+        judge.mod_flag(|tag| tag.noexec = Some(false));
+        assert!(!judge.noexec());
+        judge.settings.flags.remove("noexec");
+        judge.mod_flag(|tag| tag.noexec = Some(true));
+        assert!(judge.noexec());
+    }
+
+    #[test]
+    fn env_settings_test() {
+        let mut judge: Judgement = Default::default();
+        assert!(judge.env_reset());
+        assert!(!judge.always_set_home());
+        judge.settings.flags.remove("env_reset");
+        judge.settings.flags.insert("always_set_home".to_string());
+        assert!(!judge.env_reset());
+        assert!(judge.always_set_home());
+    }
+
+    #[test]
+    fn mail_test() {
+        let mut judge: Judgement = Default::default();
+        let sendmail = Some(MailOptions {
+            mailer: "/usr/sbin/sendmail".into(),
+            flags: vec!["-t".to_string()],
+            mailto: "root".to_string(),
+        });
+        assert_eq!(judge.mail(Incident::AuthFailure), None);
+        assert_eq!(judge.mail(Incident::NotInSudoers), sendmail);
+        assert_eq!(judge.mail(Incident::CommandDenied), None);
+
+        judge.settings.flags.insert("mail_badpass".to_string());
+        assert_eq!(judge.mail(Incident::AuthFailure), sendmail);
+
+        judge.settings.flags.remove("mail_badpass");
+        judge.settings.flags.insert("mail_no_perms".to_string());
+        judge
+            .settings
+            .str_value
+            .insert("mailto".to_string(), Some("admin@example.org".into()));
+        judge
+            .settings
+            .str_value
+            .insert("mailerflags".to_string(), Some("-i -t".into()));
+        assert_eq!(judge.mail(Incident::AuthFailure), None);
+        assert_eq!(
+            judge.mail(Incident::CommandDenied),
+            Some(MailOptions {
+                mailer: "/usr/sbin/sendmail".into(),
+                flags: vec!["-i".to_string(), "-t".to_string()],
+                mailto: "admin@example.org".to_string(),
+            })
+        );
+    }
 }
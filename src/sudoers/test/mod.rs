@@ -3,6 +3,7 @@ use std::ffi::CStr;
 use super::ast;
 use super::*;
 use basic_parser::{parse_eval, parse_lines, parse_string};
+use char_stream::PeekableWithPos;
 
 #[derive(PartialEq)]
 struct Named(&'static str);
@@ -35,6 +36,10 @@ impl UnixUser for Named {
     fn is_root(&self) -> bool {
         self.0 == "root"
     }
+
+    fn in_netgroup(&self, netgroup: &CStr) -> bool {
+        netgroup.to_str() == Ok("admins") && ["user", "root"].contains(&self.0)
+    }
 }
 
 impl UnixGroup for Named {
@@ -57,12 +62,23 @@ macro_rules! request {
 
 macro_rules! sudoer {
     ($($e:expr),*) => {
-        parse_lines(&mut [$($e),*, ""].join("\n").chars().peekable())
+        parse_lines(&mut PeekableWithPos::new([$($e),*, ""].join("\n").chars()))
             .into_iter()
             .map(|x| Ok::<_,basic_parser::Status>(x.unwrap()))
     }
 }
 
+/// Like `super::analyze`, for lines that were not read from a file and so have no position
+fn analyze(
+    path: &Path,
+    sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>,
+) -> (Sudoers, Vec<Error>) {
+    super::analyze(
+        path,
+        sudoers.into_iter().map(|line| (Default::default(), line)),
+    )
+}
+
 // alternative to parse_eval, but goes through sudoer! directly
 #[must_use]
 fn parse_line(s: &str) -> Sudo {
@@ -71,7 +87,7 @@ fn parse_line(s: &str) -> Sudo {
 
 /// Returns `None` if a syntax error is encountered
 fn try_parse_line(s: &str) -> Option<Sudo> {
-    parse_lines(&mut [s, ""].join("").chars().peekable())
+    parse_lines(&mut PeekableWithPos::new([s, ""].join("").chars()))
         .into_iter()
         .next()?
         .ok()
@@ -91,19 +107,19 @@ fn permission_test() {
 
     macro_rules! FAIL {
         ([$($sudo:expr),*], $user:expr => $req:expr, $server:expr; $command:expr) => {
-            let (Sudoers { rules,aliases,settings }, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
             let cmdvec = $command.split_whitespace().map(String::from).collect::<Vec<_>>();
-            let req = Request { user: $req.0, group: $req.1, command: &realpath(cmdvec[0].as_ref()), arguments: &cmdvec[1..].to_vec() };
-            assert_eq!(Sudoers { rules, aliases, settings }.check(&Named($user), &system::Hostname::fake($server), req).flags, None);
+            let req = Request { user: $req.0, group: $req.1, command: &realpath(cmdvec[0].as_ref()), arguments: &cmdvec[1..].to_vec(), digest: &|algorithm| algorithm.digest(cmdvec[0].as_bytes()).ok() };
+            assert_eq!(sudoers.check(&Named($user), &system::Hostname::fake($server), req).flags, None);
         }
     }
 
     macro_rules! pass {
         ([$($sudo:expr),*], $user:expr => $req:expr, $server:expr; $command:expr $(=> [$($key:ident : $val:expr),*])?) => {
-            let (Sudoers { rules,aliases,settings }, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
             let cmdvec = $command.split_whitespace().map(String::from).collect::<Vec<_>>();
-            let req = Request { user: $req.0, group: $req.1, command: &realpath(cmdvec[0].as_ref()), arguments: &cmdvec[1..].to_vec() };
-            let result = Sudoers { rules, aliases, settings }.check(&Named($user), &system::Hostname::fake($server), req).flags;
+            let req = Request { user: $req.0, group: $req.1, command: &realpath(cmdvec[0].as_ref()), arguments: &cmdvec[1..].to_vec(), digest: &|algorithm| algorithm.digest(cmdvec[0].as_bytes()).ok() };
+            let result = sudoers.check(&Named($user), &system::Hostname::fake($server), req).flags;
             assert!(!result.is_none());
             $(
                 let result = result.unwrap();
@@ -134,6 +150,17 @@ fn permission_test() {
     pass!(["user ALL=(ALL:ALL) CWD=/usr/bin NOPASSWD: /bin/foo"], "user" => root(), "server"; "/bin/foo" => [authenticate: Authenticate::Nopasswd, cwd: Some(ChDir::Path("/usr/bin".into()))]);
     //note: original sudo does not allow the below
     pass!(["user ALL=(ALL:ALL) NOPASSWD: CWD=/usr/bin /bin/foo"], "user" => root(), "server"; "/bin/foo" => [authenticate: Authenticate::Nopasswd, cwd: Some(ChDir::Path("/usr/bin".into()))]);
+    pass!(["user ALL=(ALL:ALL) /bin/foo"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Implicit]);
+    pass!(["user ALL=(ALL:ALL) SETENV: /bin/foo"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Setenv]);
+    pass!(["user ALL=(ALL:ALL) SETENV: NOSETENV: /bin/foo"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Nosetenv]);
+    pass!(["user ALL=(ALL:ALL) SETENV: /bin/foo, NOPASSWD: /bin/bar"], "user" => root(), "server"; "/bin/bar" => [authenticate: Authenticate::Nopasswd, env: EnvironmentControl::Setenv]);
+    pass!(["user ALL=(ALL:ALL) ALL"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Setenv]);
+    pass!(["user ALL=(ALL:ALL) NOSETENV: ALL"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Nosetenv]);
+    pass!(["user ALL=(ALL:ALL) /bin/foo, ALL"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Setenv]);
+    pass!(["user ALL=(ALL:ALL) ALL, !/bin/foo, /bin/foo"], "user" => root(), "server"; "/bin/foo" => [env: EnvironmentControl::Implicit]);
+    pass!(["user ALL=(ALL:ALL) NOEXEC: /bin/foo, /bin/bar"], "user" => root(), "server"; "/bin/bar" => [noexec: Some(true)]);
+    pass!(["user ALL=(ALL:ALL) NOEXEC: /bin/foo, EXEC: /bin/bar"], "user" => root(), "server"; "/bin/bar" => [noexec: Some(false)]);
+    pass!(["user ALL=(ALL:ALL) /bin/foo"], "user" => root(), "server"; "/bin/foo" => [noexec: None]);
 
     pass!(["user ALL=/bin/e##o"], "user" => root(), "vm"; "/bin/e");
     SYNTAX!(["ALL ALL=(ALL) /bin/\n/echo"]);
@@ -214,6 +241,8 @@ fn permission_test() {
     pass!(["user ALL=(root) NOPASSWD: /bin/ls, (sudo) /bin/true"], "user" => request! { sudo }, "server"; "/bin/true" => [authenticate: Authenticate::Nopasswd]);
     FAIL!(["user ALL=(root) /bin/ls, (sudo) /bin/true"], "user" => request! { sudo }, "server"; "/bin/ls");
     FAIL!(["user ALL=(root) /bin/ls, (sudo) /bin/true"], "user" => request! { root }, "server"; "/bin/true");
+    pass!(["user ALL=(sudo) /bin/ls, /bin/true"], "user" => request! { sudo }, "server"; "/bin/true");
+    FAIL!(["user ALL=(sudo) /bin/ls, /bin/true"], "user" => request! { root }, "server"; "/bin/true");
 
     SYNTAX!(["User_Alias, marc ALL = ALL"]);
 
@@ -243,6 +272,72 @@ fn permission_test() {
     // test the less-intuitive "substition-like" alias mechanism
     FAIL!(["User_Alias FOO=!user", "ALL, FOO ALL=ALL"], "user" => root(), "vm"; "/bin/ls");
     pass!(["User_Alias FOO=!user", "!FOO ALL=ALL"], "user" => root(), "vm"; "/bin/ls");
+    // aliases that are defined later must be resolved, even after a member that is not an alias
+    FAIL!(["User_Alias FOO=ALL, !BAR", "User_Alias BAR=user", "FOO ALL=ALL"], "user" => root(), "vm"; "/bin/ls");
+    pass!(["User_Alias FOO=ALL, !BAR", "User_Alias BAR=user", "FOO ALL=ALL"], "other" => root(), "vm"; "/bin/ls");
+
+    // sudoedit
+    pass!(["user ALL=sudoedit /etc/hosts"], "user" => root(), "server"; "sudoedit /etc/hosts");
+    FAIL!(["user ALL=sudoedit /etc/hosts"], "user" => root(), "server"; "sudoedit /etc/passwd");
+    FAIL!(["user ALL=sudoedit /etc/hosts"], "user" => root(), "server"; "/etc/hosts");
+    pass!(["user ALL=sudoedit"], "user" => root(), "server"; "sudoedit /etc/hosts");
+    pass!(["user ALL=ALL"], "user" => root(), "server"; "sudoedit /etc/hosts");
+    FAIL!(["user ALL=/usr/bin/*"], "user" => root(), "server"; "sudoedit /etc/hosts");
+    pass!(["user ALL=sudoedit /etc/*"], "user" => root(), "server"; "sudoedit /etc/hosts");
+    FAIL!(["user ALL=sudoedit /etc/*"], "user" => root(), "server"; "sudoedit /etc/ssh/sshd_config");
+    pass!(["user ALL=sudoedit /etc/hosts /etc/motd"], "user" => root(), "server"; "sudoedit /etc/motd");
+    pass!(["user ALL=sudoedit /etc/hosts, sudoedit /etc/motd"], "user" => root(), "server"; "sudoedit /etc/hosts /etc/motd");
+    FAIL!(["user ALL=sudoedit /etc/hosts"], "user" => root(), "server"; "sudoedit /etc/hosts /etc/motd");
+    FAIL!(["user ALL=sudoedit /etc/*, !sudoedit /etc/shadow"], "user" => root(), "server"; "sudoedit /etc/hosts /etc/shadow");
+    pass!(["user ALL=sudoedit /etc/hosts, NOPASSWD: sudoedit /etc/motd"], "user" => root(), "server"; "sudoedit /etc/motd" => [authenticate: Authenticate::Nopasswd]);
+    pass!(["user ALL=sudoedit /etc/hosts, NOPASSWD: sudoedit /etc/motd"], "user" => root(), "server"; "sudoedit /etc/hosts /etc/motd" => [authenticate: Authenticate::None]);
+    pass!(["user ALL=sudoedit /etc/hosts, /bin/ls"], "user" => root(), "server"; "/bin/ls");
+    SYNTAX!(["user ALL=sudoedit etc/hosts"]);
+    SYNTAX!(["user ALL=!sudoedit etc/hosts"]);
+
+    // list
+    pass!(["user ALL=list"], "user" => root(), "server"; "list");
+    pass!(["user ALL=ALL"], "user" => root(), "server"; "list");
+    FAIL!(["user ALL=/usr/bin/*"], "user" => root(), "server"; "list");
+    FAIL!(["user ALL=list"], "user" => root(), "server"; "/usr/bin/list");
+    FAIL!(["user ALL=ALL, !list"], "user" => root(), "server"; "list");
+
+    // netgroups
+    pass!(["+admins ALL=ALL"], "user" => root(), "server"; "/bin/ls");
+    FAIL!(["+admins ALL=ALL"], "other" => root(), "server"; "/bin/ls");
+    FAIL!(["+staff ALL=ALL"], "user" => root(), "server"; "/bin/ls");
+    FAIL!(["ALL,!+admins ALL=ALL"], "user" => root(), "server"; "/bin/ls");
+    pass!(["User_Alias ADMINS=+admins", "ADMINS ALL=ALL"], "user" => root(), "server"; "/bin/ls");
+    pass!(["user ALL=(+admins) ALL"], "user" => request! { root, root }, "server"; "/bin/ls");
+    FAIL!(["user ALL=(+admins) ALL"], "user" => request! { other, other }, "server"; "/bin/ls");
+    FAIL!(["user +nonexistent_hosts=ALL"], "user" => root(), "server"; "/bin/ls");
+    pass!(["user server,+nonexistent_hosts=ALL"], "user" => root(), "server"; "/bin/ls");
+    pass!(["Host_Alias SERVERS=+nonexistent_hosts,server", "user SERVERS=ALL"], "user" => root(), "server"; "/bin/ls");
+    SYNTAX!(["+ ALL=ALL"]);
+    SYNTAX!(["user + = ALL"]);
+
+    // digests; the fake contents of an executable is its path
+    let sha256_ls = "a4f0025445a8d5908945e7bc5a9cf01724dcbb59d2b0191bebd96ab0d548c8d7";
+    let sha224_ls = "gUgr6yDpH+QdBbh3yRyUblQuqMhfrzRe777UOQ==";
+    pass!([&format!("user ALL=sha256:{sha256_ls} /fake/ls")], "user" => root(), "server"; "/fake/ls");
+    pass!([&format!("user ALL=sha256:{} /fake/ls", sha256_ls.to_uppercase())], "user" => root(), "server"; "/fake/ls");
+    pass!([&format!("user ALL=sha224:{sha224_ls} /fake/ls")], "user" => root(), "server"; "/fake/ls");
+    pass!([&format!("user ALL=sha224:{sha224_ls} /fake/*")], "user" => root(), "server"; "/fake/ls");
+    FAIL!([&format!("user ALL=sha224:{sha224_ls} /fake/*")], "user" => root(), "server"; "/fake/cat");
+    FAIL!([&format!("user ALL=sha256:{sha256_ls} /fake/cat")], "user" => root(), "server"; "/fake/cat");
+    pass!([&format!("user ALL=sha256:{sha256_ls}, sha256:cb8e0ef4a48c5eb7059e885d263ede7e6aae8ae7118b09c80ae080f9600b4cd1 /fake/*")], "user" => root(), "server"; "/fake/cat");
+    pass!([&format!("user ALL=sha256:{sha256_ls} /fake/ls, /fake/cat")], "user" => root(), "server"; "/fake/cat");
+    pass!([&format!("Cmnd_Alias LS=sha256:{sha256_ls} /fake/ls"), "user ALL=LS"], "user" => root(), "server"; "/fake/ls");
+    FAIL!([&format!("Cmnd_Alias LS=sha256:{sha256_ls} /fake/*"), "user ALL=LS"], "user" => root(), "server"; "/fake/cat");
+    FAIL!(["user ALL=ALL", &format!("user ALL=!sha256:{sha256_ls} /fake/ls")], "user" => root(), "server"; "/fake/ls");
+    pass!(["user ALL=ALL", &format!("user ALL=!sha256:{sha256_ls} /fake/ls")], "user" => root(), "server"; "/fake/cat");
+    SYNTAX!(["user ALL=sha256:abcd /bin/ls"]);
+    SYNTAX!(["user ALL=sha256:/bin/ls"]);
+    SYNTAX!([&format!("user ALL=sha256:{sha256_ls}")]);
+    SYNTAX!([&format!("user ALL=sha256:{sha256_ls} ALL")]);
+    SYNTAX!([&format!("user ALL=sha256:{sha256_ls} sudoedit /etc/motd")]);
+    SYNTAX!([&format!("user ALL=md5:{sha256_ls} /bin/ls")]);
+    SYNTAX!(["user ALL=ls"]);
 }
 
 #[test]
@@ -271,6 +366,8 @@ fn default_set_test() {
         sudoer![
             "Defaults env_keep = \"FOO HUK BAR\"",
             "Defaults env_keep -= HUK",
+            "Defaults env_keep += \"PAGER=less EDITOR=vi*\"",
+            "Defaults env_keep -= \"EDITOR=vi*\"",
             "Defaults !env_check",
             "Defaults env_check += \"FOO\"",
             "Defaults env_check += \"XYZZY\"",
@@ -280,7 +377,10 @@ fn default_set_test() {
     );
     assert_eq!(
         settings.list["env_keep"],
-        ["FOO", "BAR"].into_iter().map(|x| x.to_string()).collect()
+        ["FOO", "BAR", "PAGER=less"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect()
     );
     assert_eq!(
         settings.list["env_check"],
@@ -292,6 +392,8 @@ fn default_set_test() {
     assert_eq!(settings.str_value["secure_path"].as_deref(), Some("/etc"));
     assert_eq!(settings.int_value["passwd_tries"], 5);
 
+    assert!(parse_string::<Sudo>("Defaults env_keep = \"FOO=\"").is_err());
+    assert!(parse_string::<Sudo>("Defaults env_keep = FOO=bar").is_err());
     assert!(parse_string::<Sudo>("Defaults verifypw = \"sometimes\"").is_err());
     assert!(parse_string::<Sudo>("Defaults verifypw = sometimes").is_err());
     assert!(parse_string::<Sudo>("Defaults verifypw = never").is_ok());
@@ -314,6 +416,277 @@ fn default_multi_test() {
     );
 }
 
+#[test]
+fn default_scope_test() {
+    let (sudoers, _) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Host_Alias SERVERS = server",
+            "Cmnd_Alias SHELLS = /bin/sh, /bin/bash",
+            "Defaults timestamp_timeout = 5",
+            "Defaults:user timestamp_timeout = 10",
+            "Defaults@SERVERS timestamp_timeout = 15, !use_pty",
+            "Defaults:%wheel,!user env_keep += WHEEL",
+            "Defaults>root env_keep += ROOT",
+            "Defaults!SHELLS,/bin/ls noexec",
+            "Defaults!/bin/ls !noexec",
+            "Defaults !env_editor",
+            "ALL ALL=(ALL:ALL) ALL"
+        ],
+    );
+
+    let check = |user, host, runas, command: &str| {
+        let cmdvec = command
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let path = Path::new(&cmdvec[0]);
+        let req = Request {
+            user: &Named(runas),
+            group: &Named(runas),
+            command: &crate::common::resolve::canonicalize(path).unwrap_or(path.to_path_buf()),
+            arguments: &cmdvec[1..],
+            digest: &|_| None,
+        };
+        sudoers
+            .check(&Named(user), &system::Hostname::fake(host), req)
+            .settings
+    };
+
+    let settings = check("other", "machine", "other", "/bin/true");
+    assert_eq!(settings.int_value["timestamp_timeout"], 300);
+    assert!(settings.flags.contains("use_pty"));
+    assert!(!settings.flags.contains("env_editor"));
+    assert!(!settings.list["env_keep"].contains("ROOT"));
+
+    // user scopes are applied after host scopes
+    assert_eq!(
+        check("user", "machine", "user", "/bin/true").int_value["timestamp_timeout"],
+        600
+    );
+    let settings = check("user", "server", "user", "/bin/true");
+    assert_eq!(settings.int_value["timestamp_timeout"], 600);
+    assert!(!settings.flags.contains("use_pty"));
+    assert_eq!(
+        check("other", "server", "other", "/bin/true").int_value["timestamp_timeout"],
+        900
+    );
+
+    assert!(check("wheel", "machine", "root", "/bin/true").list["env_keep"].contains("WHEEL"));
+    assert!(!check("user", "machine", "root", "/bin/true").list["env_keep"].contains("WHEEL"));
+    assert!(check("user", "machine", "root", "/bin/true").list["env_keep"].contains("ROOT"));
+
+    // commands in a scope match any arguments
+    assert!(check("user", "machine", "root", "/bin/sh -c true")
+        .flags
+        .contains("noexec"));
+    assert!(!check("user", "machine", "root", "/bin/ls -l")
+        .flags
+        .contains("noexec"));
+
+    // listing only takes host and user scopes into account
+    let settings = sudoers
+        .check_list_permission(
+            &Named("user"),
+            &system::Hostname::fake("server"),
+            ListRequest {
+                target_user: &Named("root"),
+                target_group: &Named("root"),
+            },
+        )
+        .settings;
+    assert_eq!(settings.int_value["timestamp_timeout"], 600);
+    assert!(!settings.list["env_keep"].contains("ROOT"));
+}
+
+#[test]
+fn pre_judgement_scope_test() {
+    let (sudoers, _) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults secure_path = /bin",
+            "Defaults@server secure_path = /usr/bin, !mail_badpass",
+            "Defaults:root secure_path = /sbin",
+            "Defaults>root secure_path = /usr/sbin, env_editor",
+            "Defaults mail_badpass, !env_editor"
+        ],
+    );
+    let root = system::User::from_uid(0).unwrap().unwrap();
+    let (machine, server) = (
+        system::Hostname::fake("machine"),
+        system::Hostname::fake("server"),
+    );
+
+    // only the host and user scopes apply before the command is known
+    assert_eq!(
+        sudoers.secure_path(&root, &machine).as_deref(),
+        Some("/sbin")
+    );
+    assert!(sudoers.solve_editor_path(&root, &machine).is_none());
+    assert!(sudoers
+        .mail(&root, &machine, crate::log::mail::Incident::AuthFailure)
+        .is_some());
+    assert!(sudoers
+        .mail(&root, &server, crate::log::mail::Incident::AuthFailure)
+        .is_none());
+}
+
+#[test]
+fn nonunix_group_test() {
+    struct Admins;
+
+    impl GroupProvider for Admins {
+        fn is_member(&self, user: &dyn UnixUser, group: &Identifier) -> bool {
+            matches!(group, Identifier::Name(name) if name == "sudo_rs_admins")
+                && user.has_name("user")
+        }
+    }
+
+    let allowed = |lines: &[&str], provider: Option<Box<dyn GroupProvider>>, user| {
+        let (mut sudoers, _) = analyze(
+            Path::new("/etc/fakesudoers"),
+            lines
+                .iter()
+                .map(|text| Ok::<_, basic_parser::Status>(parse_line(text))),
+        );
+        sudoers.group_provider = provider;
+        let req = Request {
+            user: &Named("root"),
+            group: &Named("root"),
+            command: Path::new("/bin/true"),
+            arguments: &[],
+            digest: &|_| None,
+        };
+        sudoers
+            .check(&Named(user), &system::Hostname::fake("server"), req)
+            .flags
+            .is_some()
+    };
+
+    let nonunix = &["%:sudo_rs_admins ALL=(ALL:ALL) ALL"];
+    assert!(!allowed(nonunix, None, "user"));
+    assert!(allowed(nonunix, Some(Box::new(Admins)), "user"));
+    assert!(!allowed(nonunix, Some(Box::new(Admins)), "other"));
+
+    // only with always_query_group_plugin, %group is passed to the provider
+    let unix = &["%sudo_rs_admins ALL=(ALL:ALL) ALL"];
+    assert!(!allowed(unix, Some(Box::new(Admins)), "user"));
+    let always_query = &[
+        "Defaults always_query_group_plugin",
+        "%sudo_rs_admins ALL=(ALL:ALL) ALL",
+    ];
+    assert!(allowed(always_query, Some(Box::new(Admins)), "user"));
+    assert!(!allowed(always_query, None, "user"));
+}
+
+#[test]
+fn group_file_must_be_absolute() {
+    let (sudoers, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer!["Defaults group_file = etc/group"],
+    );
+    assert!(sudoers.group_provider.is_none());
+    assert_eq!(
+        errors[0].message,
+        "cannot open group file 'etc/group': not an absolute path"
+    );
+}
+
+#[test]
+fn verifypw_listpw_test() {
+    let needs_passwd = |setting: &str, rules: &[&str]| {
+        let (sudoers, _) = analyze(
+            Path::new("/etc/fakesudoers"),
+            std::iter::once(setting)
+                .chain(rules.iter().copied())
+                .map(|text| Ok::<_, basic_parser::Status>(parse_line(text))),
+        );
+        let host = system::Hostname::fake("server");
+        let validate = sudoers.check_validate_permission(&Named("user"), &host);
+        let list = sudoers.check_list_permission(
+            &Named("user"),
+            &host,
+            ListRequest {
+                target_user: &Named("root"),
+                target_group: &Named("root"),
+            },
+        );
+        let needs_passwd = |judgement: Judgement| judgement.flags.map(|tag| tag.needs_passwd());
+
+        (needs_passwd(validate), needs_passwd(list))
+    };
+
+    let mixed = &[
+        "user ALL=(ALL:ALL) NOPASSWD: /bin/true",
+        "user ALL=(ALL:ALL) /bin/ls",
+    ];
+    let nopasswd = &["user ALL=(ALL:ALL) NOPASSWD: /bin/true, /bin/ls"];
+
+    // by default, `sudo -v` needs all entries to be NOPASSWD, `sudo -l` only one
+    assert_eq!(
+        needs_passwd("Defaults !use_pty", mixed),
+        (Some(true), Some(false))
+    );
+    assert_eq!(
+        needs_passwd("Defaults !use_pty", nopasswd),
+        (Some(false), Some(false))
+    );
+
+    assert_eq!(
+        needs_passwd("Defaults verifypw=any, listpw=all", mixed),
+        (Some(false), Some(true))
+    );
+    assert_eq!(
+        needs_passwd("Defaults verifypw=always, listpw=always", nopasswd),
+        (Some(true), Some(true))
+    );
+    assert_eq!(
+        needs_passwd("Defaults !verifypw, listpw=never", mixed),
+        (Some(false), Some(false))
+    );
+
+    // a user without any entries is not allowed at all
+    assert_eq!(
+        needs_passwd("Defaults verifypw=never", &["other ALL=(ALL:ALL) ALL"]),
+        (None, None)
+    );
+}
+
+#[test]
+fn default_scope_syntax() {
+    let scope = |text| match parse_line(text) {
+        Sudo::Decl(Directive::Defaults(_, scope)) => scope,
+        _ => panic!("incorrectly parsed"),
+    };
+
+    assert!(matches!(
+        scope("Defaults !env_editor"),
+        ConfigScope::Generic
+    ));
+    assert!(
+        matches!(scope("Defaults:user env_editor"), ConfigScope::User(users) if users.len() == 1)
+    );
+    assert!(
+        matches!(scope("Defaults@host1,host2 env_editor"), ConfigScope::Host(hosts) if hosts.len() == 2)
+    );
+    assert!(matches!(
+        scope("Defaults>ALL env_editor"),
+        ConfigScope::RunAs(_)
+    ));
+    assert!(matches!(
+        scope("Defaults!sudoedit env_editor"),
+        ConfigScope::Command(_)
+    ));
+    assert!(
+        matches!(scope("Defaults!/bin/ls,!SHELLS env_editor"), ConfigScope::Command(cmds) if cmds.len() == 2)
+    );
+
+    // no arguments are allowed for commands in a scope
+    assert!(try_parse_line("Defaults!/bin/ls -l env_editor").is_none());
+    assert!(try_parse_line("Defaults!ls env_editor").is_none());
+    assert!(try_parse_line("Defaults: env_editor").is_none());
+}
+
 #[test]
 #[should_panic]
 fn invalid_directive() {
@@ -375,16 +748,81 @@ fn gh674_at_include_quoted_backslash() {
 }
 
 #[test]
-fn gh676_percent_h_escape_unsupported() {
-    let (_, errs) = analyze(
-        Path::new("/etc/fakesudoers"),
-        sudoer!(r#"@includedir "/etc/%h" "#),
-    );
-    assert_eq!(errs.len(), 1);
+fn gh676_percent_h_escape() {
+    let hostname = system::Hostname::fake("ship.example.com");
+    assert_eq!(expand_percent_escapes("/etc/%h", &hostname), "/etc/ship");
     assert_eq!(
-        errs[0].message,
-        "cannot open sudoers file /etc/%h: percent escape %h in includedir is unsupported"
+        expand_percent_escapes("/etc/sudoers.d/%h.%h", &hostname),
+        "/etc/sudoers.d/ship.ship"
+    );
+    assert_eq!(expand_percent_escapes("/etc/%%h", &hostname), "/etc/%h");
+    assert_eq!(expand_percent_escapes("/etc/100%", &hostname), "/etc/100%");
+    assert_eq!(expand_percent_escapes("/etc/%u", &hostname), "/etc/%u");
+}
+
+#[test]
+fn includes_are_read_with_the_given_reader() {
+    let mut included = vec![];
+    let (sudoers, errors) = Sudoers::read_with_includes(
+        "@include sudoers2\n".as_bytes(),
+        "/etc/sudoers",
+        &mut |path| {
+            included.push(path.to_path_buf());
+            Ok(b"Defaults !env_reset\nthis is fine\n".to_vec())
+        },
     )
+    .unwrap();
+
+    assert_eq!(included, [PathBuf::from("/etc/sudoers2")]);
+    assert!(!sudoers.settings.flags.contains("env_reset"));
+    assert!(!errors.is_empty());
+    assert!(errors
+        .iter()
+        .all(|error| error.source.as_deref() == Some(Path::new("/etc/sudoers2"))));
+}
+
+#[test]
+fn retain_matching_rules() {
+    let extract = |user: Option<&'static str>, host: Option<&str>| {
+        let text = "Host_Alias WEB = web1, web2
+alice WEB = /usr/bin/ls : db1 = /usr/bin/cat
+bob, %alice ALL = /usr/bin/id
+ALL, !alice web2 = /usr/bin/true
+";
+        let (mut sudoers, _) = Sudoers::read(text.as_bytes(), "/etc/sudoers").unwrap();
+        let user = user.map(Named);
+        let host = host.map(system::Hostname::fake);
+        sudoers.retain_matching(user.as_ref(), host.as_ref());
+
+        let sudoers = sudoers.to_sudoers();
+        sudoers
+            .lines()
+            .skip(1)
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        extract(Some("alice"), Some("web1")),
+        ["alice WEB = /usr/bin/ls", "bob, %alice ALL = /usr/bin/id"]
+    );
+    assert_eq!(
+        extract(Some("alice"), None),
+        [
+            "alice WEB = /usr/bin/ls : db1 = /usr/bin/cat",
+            "bob, %alice ALL = /usr/bin/id"
+        ]
+    );
+    assert_eq!(
+        extract(None, Some("web2")),
+        [
+            "alice WEB = /usr/bin/ls",
+            "bob, %alice ALL = /usr/bin/id",
+            "ALL, !alice web2 = /usr/bin/true"
+        ]
+    );
+    assert_eq!(extract(Some("carol"), Some("db1")), Vec::<String>::new());
+    assert_eq!(extract(None, None).len(), 3);
 }
 
 #[test]
@@ -433,7 +871,7 @@ fn test_topo_sort(n: usize) {
             Def("MIES".to_string(), vec![x3]),
         ];
         let mut err = vec![];
-        let order = sanitize_alias_table(&table, &mut err);
+        let order = sanitize_alias_table(&table, &[], &mut err);
         assert!(err.is_empty());
         let mut seen = HashSet::new();
         for Def(id, defns) in order.iter().map(|&i| &table[i]) {
@@ -509,7 +947,7 @@ fn fuzz_topo_sort(siz: usize) {
             .collect();
 
         let mut err = vec![];
-        let order = sanitize_alias_table(&table, &mut err);
+        let order = sanitize_alias_table(&table, &[], &mut err);
         if !err.is_empty() {
             return;
         }
@@ -1,5 +1,6 @@
 //! Various tokens
 
+use crate::common::digest::DigestAlgorithm;
 use crate::common::{SudoPath, SudoString};
 
 use super::basic_parser::{Many, Token};
@@ -55,9 +56,17 @@ impl Token for Numeric {
     }
 }
 
-/// A hostname consists of alphanumeric characters and ".", "-",  "_"
+/// A hostname consists of alphanumeric characters and ".", "-",  "_"; a leading "+" denotes
+/// a netgroup of hosts
+#[derive(Clone, PartialEq)]
 pub struct Hostname(pub String);
 
+impl Hostname {
+    pub fn netgroup(&self) -> Option<&str> {
+        self.0.strip_prefix('+')
+    }
+}
+
 impl std::ops::Deref for Hostname {
     type Target = String;
 
@@ -68,19 +77,28 @@ impl std::ops::Deref for Hostname {
 
 impl Token for Hostname {
     fn construct(text: String) -> Result<Self, String> {
+        if text == "+" {
+            return Err("expected netgroup name".to_string());
+        }
+
         Ok(Hostname(text))
     }
 
     fn accept(c: char) -> bool {
         c.is_ascii_alphanumeric() || ".-_".contains(c)
     }
+
+    fn accept_1st(c: char) -> bool {
+        c == '+' || Self::accept(c)
+    }
 }
 
 impl Many for Hostname {}
 
 /// This enum allows items to use the ALL wildcard or be specified with aliases, or directly.
 /// (Maybe this is better defined not as a Token but simply directly as an implementation of [crate::sudoers::basic_parser::Parse])
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug, Eq))]
 #[repr(u32)]
 pub enum Meta<T> {
     All = HARDENED_ENUM_VALUE_0,
@@ -152,9 +170,15 @@ impl Token for AliasName {
 
 /// A struct that represents valid command strings; this can contain escape sequences and are
 /// limited to 1024 characters.
-pub type Command = (glob::Pattern, Option<Box<[String]>>);
+pub type SimpleCommand = (glob::Pattern, Option<Box<[String]>>);
 
-impl Token for Command {
+/// A command, together with the digests that its executable should match (if any are given,
+/// one of them has to match).
+pub type Command = (SimpleCommand, Box<[Digest]>);
+
+impl Many for Command {}
+
+impl Token for SimpleCommand {
     const MAX_LEN: usize = 1024;
 
     fn construct(s: String) -> Result<Self, String> {
@@ -200,9 +224,9 @@ impl Token for Command {
         Ok((cvt_err(glob::Pattern::new(&cmd))?, argpat))
     }
 
-    // all commands start with "/" except "sudoedit"
+    // "sudoedit", "list" and digests are handled by the parser for `Meta<Command>`
     fn accept_1st(c: char) -> bool {
-        c == '/' || c == 's'
+        c == '/'
     }
 
     fn accept(c: char) -> bool {
@@ -215,7 +239,168 @@ impl Token for Command {
     }
 }
 
-impl Many for Command {}
+/// Construct the command specification for `sudoedit`; the arguments, if any, are the files
+/// that may be edited and have to be absolute paths. These may contain wildcards, which are
+/// matched against the file names given by the user.
+pub fn sudoedit(paths: Vec<String>) -> Result<SimpleCommand, String> {
+    for path in &paths {
+        if !path.starts_with('/') {
+            return Err(format!("sudoedit: '{path}' is not an absolute path"));
+        }
+        glob::Pattern::new(path).map_err(|err| format!("wildcard pattern error {err}"))?;
+    }
+
+    let argpat = if paths.is_empty() {
+        // if no files are mentioned, any file may be edited
+        None
+    } else {
+        Some(paths.into_boxed_slice())
+    };
+
+    Ok((glob::Pattern::new("sudoedit").unwrap(), argpat))
+}
+
+/// Construct the command specification for the `list` pseudo-command, which permits listing the
+/// privileges of other users with `sudo -l -U`.
+pub fn list() -> SimpleCommand {
+    (glob::Pattern::new("list").unwrap(), Some(Box::default()))
+}
+
+/// The (whitespace separated) list of files that follows the "sudoedit" keyword.
+pub struct EditPaths(pub Vec<String>);
+
+impl Token for EditPaths {
+    const MAX_LEN: usize = SimpleCommand::MAX_LEN;
+
+    fn construct(s: String) -> Result<Self, String> {
+        Ok(EditPaths(s.split_whitespace().map(String::from).collect()))
+    }
+
+    fn accept_1st(c: char) -> bool {
+        c == '/'
+    }
+
+    fn accept(c: char) -> bool {
+        SimpleCommand::accept(c)
+    }
+
+    const ALLOW_ESCAPE: bool = true;
+    fn escaped(c: char) -> bool {
+        SimpleCommand::escaped(c)
+    }
+}
+
+/// A command without arguments, as used in the scope of a `Defaults!` directive: either a path
+/// to a binary or "sudoedit". Any arguments given to the command will match.
+pub struct CommandPath(pub SimpleCommand);
+
+impl Token for CommandPath {
+    const MAX_LEN: usize = SimpleCommand::MAX_LEN;
+
+    fn construct(s: String) -> Result<Self, String> {
+        if s == "sudoedit" {
+            sudoedit(Vec::new()).map(CommandPath)
+        } else if s.starts_with('/') {
+            SimpleCommand::construct(s).map(CommandPath)
+        } else {
+            Err(format!("expected command but found {s}"))
+        }
+    }
+
+    fn accept_1st(c: char) -> bool {
+        c == '/' || c.is_ascii_lowercase()
+    }
+
+    fn accept(c: char) -> bool {
+        SimpleCommand::accept(c) && !c.is_whitespace()
+    }
+
+    const ALLOW_ESCAPE: bool = true;
+    fn escaped(c: char) -> bool {
+        SimpleCommand::escaped(c)
+    }
+}
+
+impl Many for CommandPath {}
+
+/// A digest of an executable, e.g. "sha256:" followed by the digest in hexadecimal or base64.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Digest(pub DigestAlgorithm, pub Box<[u8]>);
+
+impl Digest {
+    pub fn decode(algorithm: DigestAlgorithm, text: &str) -> Result<Self, String> {
+        let len = algorithm.output_len();
+        let value = if text.len() == 2 * len {
+            decode_hex(text)
+        } else {
+            decode_base64(text)
+        };
+
+        match value {
+            Some(value) if value.len() == len => Ok(Digest(algorithm, value.into())),
+            _ => Err(format!("invalid {algorithm} digest")),
+        }
+    }
+}
+
+impl std::fmt::Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.0)?;
+        self.1.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(
+        digits
+            .chunks_exact(2)
+            .map(|pair| (pair[0] << 4) | pair[1])
+            .collect(),
+    )
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    let mut result = Vec::new();
+    for c in text.chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            result.push((bits >> count) as u8);
+        }
+    }
+
+    Some(result)
+}
+
+/// The text of a digest, which is in hexadecimal or base64 notation.
+pub struct DigestText(pub String);
+
+impl Token for DigestText {
+    fn construct(text: String) -> Result<Self, String> {
+        Ok(DigestText(text))
+    }
+
+    fn accept(c: char) -> bool {
+        c.is_ascii_alphanumeric() || "+/=".contains(c)
+    }
+}
 
 pub struct DefaultName(pub String);
 
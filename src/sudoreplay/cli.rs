@@ -0,0 +1,226 @@
+#[derive(Debug, PartialEq)]
+pub(crate) struct SudoReplayOptions {
+    pub(crate) directory: Option<String>,
+    pub(crate) max_wait: Option<f64>,
+    pub(crate) speed: f64,
+    pub(crate) session: Option<String>,
+    pub(crate) action: SudoReplayAction,
+}
+
+impl Default for SudoReplayOptions {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            max_wait: None,
+            speed: 1.0,
+            session: None,
+            action: SudoReplayAction::Replay,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum SudoReplayAction {
+    Help,
+    Version,
+    List,
+    Replay,
+}
+
+type OptionSetter = fn(&mut SudoReplayOptions, Option<String>) -> Result<(), String>;
+
+struct SudoReplayOption {
+    short: char,
+    long: &'static str,
+    takes_argument: bool,
+    set: OptionSetter,
+}
+
+/// Parse a positive number of seconds or speed factor.
+fn parse_positive(argument: Option<String>, option: char) -> Result<f64, String> {
+    let argument = argument.ok_or(format!("option requires an argument -- '{option}'"))?;
+    match argument.parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+        _ => Err(format!(
+            "invalid argument for option '{option}': {argument}"
+        )),
+    }
+}
+
+impl SudoReplayOptions {
+    const SUDOREPLAY_OPTIONS: &'static [SudoReplayOption] = &[
+        SudoReplayOption {
+            short: 'd',
+            long: "directory",
+            takes_argument: true,
+            set: |options, argument| {
+                options.directory = Some(argument.ok_or("option requires an argument -- 'd'")?);
+                Ok(())
+            },
+        },
+        SudoReplayOption {
+            short: 'h',
+            long: "help",
+            takes_argument: false,
+            set: |options, _| {
+                options.action = SudoReplayAction::Help;
+                Ok(())
+            },
+        },
+        SudoReplayOption {
+            short: 'l',
+            long: "list",
+            takes_argument: false,
+            set: |options, _| {
+                options.action = SudoReplayAction::List;
+                Ok(())
+            },
+        },
+        SudoReplayOption {
+            short: 'm',
+            long: "max-wait",
+            takes_argument: true,
+            set: |options, argument| {
+                options.max_wait = Some(parse_positive(argument, 'm')?);
+                Ok(())
+            },
+        },
+        SudoReplayOption {
+            short: 's',
+            long: "speed",
+            takes_argument: true,
+            set: |options, argument| {
+                options.speed = parse_positive(argument, 's')?;
+                Ok(())
+            },
+        },
+        SudoReplayOption {
+            short: 'V',
+            long: "version",
+            takes_argument: false,
+            set: |options, _| {
+                options.action = SudoReplayAction::Version;
+                Ok(())
+            },
+        },
+    ];
+
+    pub(crate) fn from_env() -> Result<SudoReplayOptions, String> {
+        let args = std::env::args().collect();
+
+        Self::parse_arguments(args)
+    }
+
+    /// parse sudoreplay arguments into SudoReplayOptions struct
+    pub(crate) fn parse_arguments(arguments: Vec<String>) -> Result<SudoReplayOptions, String> {
+        let mut options: SudoReplayOptions = SudoReplayOptions::default();
+        let mut arg_iter = arguments.into_iter().skip(1);
+
+        while let Some(arg) = arg_iter.next() {
+            // if the argument starts with -- it must be a full length option name
+            if arg.starts_with("--") {
+                // parse assignments like '--speed=2'
+                let (key, value) = match arg.split_once('=') {
+                    Some((key, value)) => (key, Some(value.to_string())),
+                    None => (arg.as_str(), None),
+                };
+                // lookup the option by name
+                let Some(option) = Self::SUDOREPLAY_OPTIONS
+                    .iter()
+                    .find(|o| o.long == &key[2..])
+                else {
+                    Err(format!("unrecognized option '{}'", arg))?
+                };
+                if option.takes_argument {
+                    // the value is either part of the assignment or the next argument
+                    let value = value.or_else(|| arg_iter.next());
+                    (option.set)(&mut options, value)?;
+                } else if value.is_some() {
+                    Err(format!("'--{}' does not take any arguments", option.long))?;
+                } else {
+                    (option.set)(&mut options, None)?;
+                }
+            } else if arg.starts_with('-') {
+                // flags can be grouped, so we loop over the characters
+                for (n, char) in arg.trim_start_matches('-').chars().enumerate() {
+                    // lookup the option
+                    let Some(option) = Self::SUDOREPLAY_OPTIONS.iter().find(|o| o.short == char)
+                    else {
+                        Err(format!("unrecognized option '{}'", char))?
+                    };
+                    // try to parse an argument when one is necessary, either the rest of the
+                    // current flag group or the next argument
+                    if option.takes_argument {
+                        let rest = arg[(n + 2)..].trim().to_string();
+                        let next_arg = if rest.is_empty() {
+                            arg_iter.next()
+                        } else {
+                            Some(rest)
+                        };
+                        (option.set)(&mut options, next_arg)?;
+                        // stop looping over flags if the current flag takes an argument
+                        break;
+                    } else {
+                        (option.set)(&mut options, None)?;
+                    }
+                }
+            } else if options.session.is_none() {
+                options.session = Some(arg);
+            } else {
+                Err(format!("unexpected argument '{arg}'"))?;
+            }
+        }
+
+        if options.action == SudoReplayAction::Replay && options.session.is_none() {
+            Err("no session ID specified")?;
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SudoReplayAction, SudoReplayOptions};
+
+    fn parse(args: &[&str]) -> Result<SudoReplayOptions, String> {
+        SudoReplayOptions::parse_arguments(
+            std::iter::once("sudoreplay")
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn replay_session() {
+        assert_eq!(
+            parse(&["-s2", "--max-wait", "0.5", "-d", "/tmp/io", "00000A"]),
+            Ok(SudoReplayOptions {
+                directory: Some("/tmp/io".to_string()),
+                max_wait: Some(0.5),
+                speed: 2.0,
+                session: Some("00000A".to_string()),
+                action: SudoReplayAction::Replay,
+            })
+        );
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["-s", "0", "000001"]).is_err());
+        assert!(parse(&["--speed=fast", "000001"]).is_err());
+        assert!(parse(&["000001", "000002"]).is_err());
+    }
+
+    #[test]
+    fn list_sessions() {
+        assert_eq!(
+            parse(&["--directory=/tmp/io", "-l"]),
+            Ok(SudoReplayOptions {
+                directory: Some("/tmp/io".to_string()),
+                action: SudoReplayAction::List,
+                ..Default::default()
+            })
+        );
+        assert!(parse(&["--list=yes"]).is_err());
+        assert!(parse(&["-x"]).is_err());
+    }
+}
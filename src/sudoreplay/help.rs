@@ -0,0 +1,17 @@
+pub(crate) const USAGE_MSG: &str = "usage: sudoreplay [-h] [-d dir] [-m num] [-s num] ID
+       sudoreplay [-h] [-d dir] -l";
+
+const DESCRIPTOR: &str = "sudoreplay - replay sudo session logs";
+
+const HELP_MSG: &str = "Options:
+  -d, --directory=dir    specify directory for session logs
+  -h, --help             display help message and exit
+  -l, --list             list available session IDs
+  -m, --max-wait=num     max number of seconds to wait between events
+  -s, --speed=num        speed up or slow down output
+  -V, --version          display version information and exit
+";
+
+pub(crate) fn long_help_message() -> String {
+    format!("{USAGE_MSG}\n\n{DESCRIPTOR}\n\n{HELP_MSG}")
+}
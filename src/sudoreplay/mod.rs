@@ -0,0 +1,179 @@
+#![forbid(unsafe_code)]
+mod cli;
+mod help;
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    iolog::{list_sessions, session_dir, IoStream, SessionInfo, TimingEvent, TimingRecord},
+    system::time::format_local_time,
+};
+
+use self::cli::{SudoReplayAction, SudoReplayOptions};
+use self::help::{long_help_message, USAGE_MSG};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The default value of the `iolog_dir` setting.
+const DEFAULT_IOLOG_DIR: &str = "/var/log/sudo-io";
+
+/// Writes text from a session log with its control characters escaped, so that it cannot contain
+/// terminal escape sequences
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            if c.is_control() {
+                write!(f, "{}", c.escape_default())?;
+            } else {
+                write!(f, "{c}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+macro_rules! io_msg {
+    ($err:expr, $($tt:tt)*) => {
+        io::Error::new($err.kind(), format!("{}: {}", format_args!($($tt)*), $err))
+    };
+}
+
+pub fn main() {
+    let options = match SudoReplayOptions::from_env() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln_ignore_io_error!("sudoreplay: {error}\n{USAGE_MSG}");
+            std::process::exit(1);
+        }
+    };
+
+    let dir = PathBuf::from(options.directory.as_deref().unwrap_or(DEFAULT_IOLOG_DIR));
+
+    let result = match options.action {
+        SudoReplayAction::Help => {
+            println_ignore_io_error!("{}", long_help_message());
+            std::process::exit(0);
+        }
+        SudoReplayAction::Version => {
+            println_ignore_io_error!("sudoreplay version {VERSION}");
+            std::process::exit(0);
+        }
+        SudoReplayAction::List => list(&dir),
+        SudoReplayAction::Replay => replay(&dir, &options),
+    };
+
+    if let Err(error) = result {
+        eprintln_ignore_io_error!("sudoreplay: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn list(dir: &Path) -> io::Result<()> {
+    let sessions =
+        list_sessions(dir).map_err(|err| io_msg!(err, "unable to open {}", dir.display()))?;
+
+    let mut stdout = io::stdout().lock();
+    for (id, info) in sessions {
+        let SessionInfo {
+            start_time,
+            user,
+            runas_user,
+            runas_group,
+            tty,
+            cwd,
+            command,
+            ..
+        } = info;
+
+        write!(
+            stdout,
+            "{} : {} : TTY={} ; CWD={} ; USER={} ; ",
+            format_local_time(start_time),
+            Escaped(&user),
+            Escaped(&tty),
+            Escaped(&cwd),
+            Escaped(&runas_user),
+        )?;
+        if !runas_group.is_empty() {
+            write!(stdout, "GROUP={} ; ", Escaped(&runas_group))?;
+        }
+        writeln!(stdout, "TSID={id} ; COMMAND={}", Escaped(&command))?;
+    }
+
+    Ok(())
+}
+
+fn replay(dir: &Path, options: &SudoReplayOptions) -> io::Result<()> {
+    let id = options.session.as_deref().unwrap_or_default();
+    let session = session_dir(dir, id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid session id: {}", Escaped(id)),
+        )
+    })?;
+
+    let info =
+        SessionInfo::read(&session).map_err(|err| io_msg!(err, "unable to read session {id}"))?;
+    let timing = File::open(session.join("timing"))
+        .map_err(|err| io_msg!(err, "unable to open {}", session.join("timing").display()))?;
+
+    // The output streams that were recorded; input is not replayed.
+    let mut streams = IoStream::ALL.map(|stream| {
+        (!stream.is_input())
+            .then(|| File::open(session.join(stream.file_name())).ok())
+            .flatten()
+    });
+
+    let max_wait = options.max_wait.map(Duration::from_secs_f64);
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "Replaying sudo session: {}", Escaped(&info.command))?;
+    stdout.flush()?;
+
+    let mut buf = Vec::new();
+    for line in BufReader::new(timing).lines() {
+        let Some(TimingRecord { delay, event }) = TimingRecord::parse(&line?) else {
+            continue;
+        };
+
+        let TimingEvent::Io(stream, len) = event else {
+            continue;
+        };
+        let Some(file) = &mut streams[stream as usize] else {
+            continue;
+        };
+
+        let delay = delay.div_f64(options.speed);
+        thread::sleep(max_wait.map_or(delay, |max_wait| delay.min(max_wait)));
+
+        buf.clear();
+        file.take(len as u64).read_to_end(&mut buf)?;
+        stdout.write_all(&buf)?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Escaped;
+
+    #[test]
+    fn control_characters_are_escaped() {
+        assert_eq!(
+            Escaped("/bin/echo \u{1b}[2J\ttab ünïcode").to_string(),
+            "/bin/echo \\u{1b}[2J\\ttab ünïcode"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    ffi::{CString, OsStr},
+    fs::{File, Metadata, OpenOptions},
+    io,
+    mem::MaybeUninit,
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    },
+    path::{Component, Path, PathBuf},
+};
+
+use crate::cutils::cerr;
+
+/// An opened directory; files in it are opened, renamed and removed relative to it, so that
+/// these operations are not affected by changes to the path that led to it.
+pub(crate) struct Directory {
+    dir: File,
+}
+
+fn c_name(name: &OsStr) -> io::Result<CString> {
+    CString::new(name.as_bytes()).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+impl Directory {
+    /// Open the directory at `path` without following a symbolic link in any of its components;
+    /// `check` is called for every directory on the way, starting with the root directory (or the
+    /// current directory for relative paths), and can abort the walk by returning an error.
+    pub fn open_nofollow(
+        path: &Path,
+        mut check: impl FnMut(&Path, &Metadata) -> io::Result<()>,
+    ) -> io::Result<Self> {
+        let mut current = PathBuf::from(if path.is_absolute() { "/" } else { "." });
+        let mut dir = Directory {
+            dir: OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_DIRECTORY)
+                .open(&current)?,
+        };
+        check(&current, &dir.metadata()?)?;
+
+        for component in path.components() {
+            let name = match component {
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => continue,
+                Component::ParentDir => OsStr::new(".."),
+                Component::Normal(name) => name,
+            };
+            let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW;
+            dir = match dir.open_file(name, flags, 0) {
+                Ok(file) => Directory { dir: file },
+                // with O_DIRECTORY, a symbolic link is reported as not being a directory
+                Err(err) if err.raw_os_error() == Some(libc::ENOTDIR) && dir.is_symlink(name) => {
+                    return Err(io::Error::from_raw_os_error(libc::ELOOP))
+                }
+                Err(err) => return Err(err),
+            };
+            current.push(name);
+            check(&current, &dir.metadata()?)?;
+        }
+
+        Ok(dir)
+    }
+
+    fn is_symlink(&self, name: &OsStr) -> bool {
+        let Ok(name) = c_name(name) else {
+            return false;
+        };
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+        // SAFETY: `name` is a valid C string, and `stat` has room for the result
+        let result = cerr(unsafe {
+            libc::fstatat(
+                self.dir.as_raw_fd(),
+                name.as_ptr(),
+                stat.as_mut_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        });
+
+        // SAFETY: fstatat has filled in `stat` if it succeeded
+        result.is_ok() && unsafe { stat.assume_init() }.st_mode & libc::S_IFMT == libc::S_IFLNK
+    }
+
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.dir.metadata()
+    }
+
+    /// Open the file `name` in this directory, using the flags and mode of `open(2)`
+    pub fn open_file(
+        &self,
+        name: &OsStr,
+        flags: libc::c_int,
+        mode: libc::mode_t,
+    ) -> io::Result<File> {
+        let name = c_name(name)?;
+        // SAFETY: `name` is a valid C string, and the file descriptor of the directory is open
+        let fd = cerr(unsafe {
+            libc::openat(
+                self.dir.as_raw_fd(),
+                name.as_ptr(),
+                flags | libc::O_CLOEXEC,
+                libc::c_uint::from(mode),
+            )
+        })?;
+
+        // SAFETY: this file descriptor was just created and is not owned by anything else
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+
+    /// Rename the file `from` in this directory to `to`, replacing it if it already exists
+    pub fn rename(&self, from: &OsStr, to: &OsStr) -> io::Result<()> {
+        let (from, to) = (c_name(from)?, c_name(to)?);
+        let fd = self.dir.as_raw_fd();
+        // SAFETY: both names are valid C strings, and the file descriptor of the directory is open
+        cerr(unsafe { libc::renameat(fd, from.as_ptr(), fd, to.as_ptr()) })?;
+
+        Ok(())
+    }
+
+    /// Remove the file `name` from this directory
+    pub fn remove_file(&self, name: &OsStr) -> io::Result<()> {
+        let name = c_name(name)?;
+        // SAFETY: `name` is a valid C string, and the file descriptor of the directory is open
+        cerr(unsafe { libc::unlinkat(self.dir.as_raw_fd(), name.as_ptr(), 0) })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::Directory;
+
+    #[test]
+    fn does_not_follow_symlinks() {
+        let tmp = crate::system::file::create_temporary_dir("/tmp/sudo-rs-test").unwrap();
+        std::fs::create_dir(tmp.join("real")).unwrap();
+        std::os::unix::fs::symlink("real", tmp.join("link")).unwrap();
+
+        let mut visited = Vec::new();
+        let dir = Directory::open_nofollow(&tmp.join("real"), |path, meta| {
+            assert!(meta.is_dir());
+            visited.push(path.to_owned());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(visited.first().unwrap(), "/");
+        assert_eq!(visited.last().unwrap(), &tmp.join("real"));
+
+        let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL;
+        drop(dir.open_file(OsStr::new("new"), flags, 0o600).unwrap());
+        dir.rename(OsStr::new("new"), OsStr::new("renamed"))
+            .unwrap();
+        assert!(tmp.join("real/renamed").is_file());
+        dir.remove_file(OsStr::new("renamed")).unwrap();
+
+        let err = Directory::open_nofollow(&tmp.join("link"), |_, _| Ok(())).err();
+        assert_eq!(err.unwrap().raw_os_error(), Some(libc::ELOOP));
+
+        std::fs::remove_dir_all(tmp).unwrap();
+    }
+}
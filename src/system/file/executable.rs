@@ -0,0 +1,176 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Seek},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::fs::{FileExt, OpenOptionsExt},
+    },
+    path::Path,
+};
+#[cfg(target_os = "linux")]
+use std::{os::unix::process::CommandExt, process::Command};
+
+use crate::common::digest::DigestAlgorithm;
+use crate::cutils::cerr;
+
+/// An opened executable; by executing it through its file descriptor, the file that gets
+/// executed is the one that was checked, even if the file system is changed in the meantime.
+#[derive(Debug)]
+pub struct Executable {
+    file: File,
+    is_script: bool,
+    digests: RefCell<HashMap<DigestAlgorithm, Option<Box<[u8]>>>>,
+}
+
+impl Executable {
+    /// Open the file at `path`; this fails if it is not a regular file. Opening does not block
+    /// and does not follow a symbolic link, so that no device or FIFO gets opened.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+            .open(path)?;
+
+        if !file.metadata()?.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a regular file",
+            ));
+        }
+
+        let mut magic = [0; 2];
+        let is_script = file.read_at(&mut magic, 0)? == magic.len() && &magic == b"#!";
+
+        Ok(Executable {
+            file,
+            is_script,
+            digests: Default::default(),
+        })
+    }
+
+    /// Compute the digest of the contents of the executable; `None` if it cannot be read.
+    pub fn digest(&self, algorithm: DigestAlgorithm) -> Option<Box<[u8]>> {
+        self.digests
+            .borrow_mut()
+            .entry(algorithm)
+            .or_insert_with(|| {
+                let mut file = &self.file;
+                file.rewind().ok()?;
+                algorithm.digest(file).ok()
+            })
+            .clone()
+    }
+
+    /// Whether the contents of this executable have been inspected by computing a digest
+    pub fn is_digested(&self) -> bool {
+        !self.digests.borrow().is_empty()
+    }
+
+    /// A command that executes this file, through a path that refers to it for as long as it is
+    /// open. A script is run by passing that path to its interpreter, so then the file has to
+    /// remain open after executing; it is only made inheritable right before the command itself is
+    /// executed, so that no other child process of sudo gets to read it.
+    #[cfg(target_os = "linux")]
+    pub fn command(&self) -> Command {
+        let fd = self.file.as_raw_fd();
+        let mut command = Command::new(format!("/proc/self/fd/{fd}"));
+        if self.is_script {
+            // SAFETY: the closure only calls fcntl, which is async-signal-safe
+            unsafe {
+                command.pre_exec(move || {
+                    // SAFETY: fcntl with F_GETFD/F_SETFD does not access memory
+                    let flags = cerr(libc::fcntl(fd, libc::F_GETFD))?;
+                    cerr(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC))?;
+                    Ok(())
+                });
+            }
+        }
+
+        command
+    }
+}
+
+impl AsRawFd for Executable {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::Executable;
+    use crate::common::digest::DigestAlgorithm::Sha256;
+
+    fn is_cloexec(executable: &Executable) -> bool {
+        let flags = unsafe { libc::fcntl(executable.as_raw_fd(), libc::F_GETFD) };
+        flags & libc::FD_CLOEXEC != 0
+    }
+
+    #[test]
+    fn digest_is_computed_from_open_file() {
+        let dir = crate::system::file::create_temporary_dir("/tmp/sudo-rs-test").unwrap();
+        let path = dir.join("binary");
+        std::fs::write(&path, "abc").unwrap();
+
+        let executable = Executable::open(&path).unwrap();
+        assert!(!executable.is_digested());
+        assert!(is_cloexec(&executable));
+
+        // replacing the file does not affect the opened executable
+        std::fs::remove_file(&path).unwrap();
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(b"def")
+            .unwrap();
+
+        let digest = executable.digest(Sha256).unwrap();
+        assert_eq!(digest, Sha256.digest(&b"abc"[..]).unwrap());
+        assert_eq!(executable.digest(Sha256), Some(digest));
+        assert!(executable.is_digested());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scripts_are_only_inherited_by_the_command() {
+        let dir = crate::system::file::create_temporary_dir("/tmp/sudo-rs-test").unwrap();
+        let path = dir.join("script");
+        std::fs::write(&path, "#!/bin/sh\nexit 3\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700)).unwrap();
+
+        let executable = Executable::open(&path).unwrap();
+        assert!(is_cloexec(&executable));
+
+        let status = executable.command().status().unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(is_cloexec(&executable));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_regular_files_are_opened() {
+        let dir = crate::system::file::create_temporary_dir("/tmp/sudo-rs-test").unwrap();
+        let fifo = dir.join("fifo");
+        let path = std::ffi::CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o700) }, 0);
+
+        // a fifo without a writer neither blocks nor gets opened
+        assert!(Executable::open(&fifo).is_err());
+        assert!(Executable::open("/dev/null".as_ref()).is_err());
+        assert!(Executable::open(&dir).is_err());
+
+        let link = dir.join("link");
+        std::os::unix::fs::symlink("/bin/sh", &link).unwrap();
+        assert!(Executable::open(&link).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,32 @@
 mod chown;
+mod dir;
+mod executable;
 mod lock;
 
 pub(crate) use chown::Chown;
+pub(crate) use dir::Directory;
+pub(crate) use executable::Executable;
 pub(crate) use lock::FileLock;
+
+use std::{
+    ffi::{CString, OsString},
+    io,
+    os::unix::prelude::OsStringExt,
+    path::PathBuf,
+};
+
+/// Create a fresh directory whose name starts with `prefix`, which is only accessible by the
+/// current (effective) user.
+pub(crate) fn create_temporary_dir(prefix: &str) -> io::Result<PathBuf> {
+    let template = CString::new(format!("{prefix}-XXXXXX"))?;
+
+    let ptr = unsafe { libc::mkdtemp(template.into_raw()) };
+
+    if ptr.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+
+    let path = OsString::from_vec(unsafe { CString::from_raw(ptr) }.into_bytes()).into();
+
+    Ok(path)
+}
@@ -24,6 +24,9 @@ pub trait UnixUser {
     fn in_group_by_gid(&self, _gid: GroupId) -> bool {
         false
     }
+    fn in_netgroup(&self, _netgroup: &CStr) -> bool {
+        false
+    }
 }
 
 pub trait UnixGroup {
@@ -51,6 +54,9 @@ impl UnixUser for super::User {
     fn in_group_by_gid(&self, gid: GroupId) -> bool {
         self.groups.contains(&gid)
     }
+    fn in_netgroup(&self, netgroup: &CStr) -> bool {
+        super::innetgr(netgroup, None, Some(self.name.as_cstr()))
+    }
 }
 
 impl UnixGroup for super::Group {
@@ -103,5 +109,6 @@ mod test {
         assert!(!().has_uid(0));
         assert!(!().is_root());
         assert!(!().in_group_by_name(cstr!("root")));
+        assert!(!().in_netgroup(cstr!("root")));
     }
 }
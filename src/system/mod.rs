@@ -3,12 +3,13 @@ use core::fmt;
 use std::{
     collections::BTreeSet,
     ffi::{c_uint, CStr, CString},
+    fs::File,
     io,
-    mem::MaybeUninit,
+    mem::{self, MaybeUninit},
     ops,
     os::{
-        fd::AsRawFd,
-        unix::{self, prelude::OsStrExt},
+        fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+        unix::{self, net::UnixStream, prelude::OsStrExt},
     },
     path::{Path, PathBuf},
     str::FromStr,
@@ -42,6 +43,8 @@ pub mod term;
 
 pub mod wait;
 
+pub(crate) mod seccomp;
+
 pub(crate) fn can_execute<P: AsRef<Path>>(path: P) -> bool {
     let Ok(path) = CString::new(path.as_ref().as_os_str().as_bytes()) else {
         return false;
@@ -58,12 +61,14 @@ pub(crate) fn _exit(status: libc::c_int) -> ! {
 /// and the IO streams.
 pub(crate) struct FileCloser {
     fds: BTreeSet<c_uint>,
+    min_fd: c_uint,
 }
 
 impl FileCloser {
     pub(crate) const fn new() -> Self {
         Self {
             fds: BTreeSet::new(),
+            min_fd: STDERR_FILENO as c_uint + 1,
         }
     }
 
@@ -71,45 +76,153 @@ impl FileCloser {
         self.fds.insert(fd.as_raw_fd() as c_uint);
     }
 
-    /// Close every file descriptor that is not one of the IO streams or one of the file
-    /// descriptors passed via [`FileCloser::except`].
-    pub(crate) fn close_the_universe(self) -> io::Result<()> {
-        let mut fds = self.fds.into_iter();
-
-        let Some(mut curr_fd) = fds.next() else {
-            return close_range(STDERR_FILENO as c_uint + 1, c_uint::MAX);
-        };
+    /// Leave the file descriptors below `fd` open as well; `fd` must be larger than the file
+    /// descriptors of the IO streams.
+    pub(crate) fn close_from(&mut self, fd: c_uint) {
+        debug_assert!(fd > STDERR_FILENO as c_uint);
+        self.min_fd = fd;
+    }
 
-        if let Some(max_fd) = curr_fd.checked_sub(1) {
-            close_range(STDERR_FILENO as c_uint + 1, max_fd)?;
-        }
+    /// Close every file descriptor that is not one of the IO streams, below the limit set via
+    /// [`FileCloser::close_from`] or one of the file descriptors passed via [`FileCloser::except`].
+    pub(crate) fn close_the_universe(self) -> io::Result<()> {
+        let mut min_fd = self.min_fd;
 
-        for next_fd in fds {
-            if let Some(min_fd) = curr_fd.checked_add(1) {
-                if let Some(max_fd) = next_fd.checked_sub(1) {
-                    close_range(min_fd, max_fd)?;
-                }
+        for &fd in self.fds.range(self.min_fd..) {
+            if let Some(max_fd) = fd.checked_sub(1) {
+                close_range(min_fd, max_fd, 0)?;
             }
 
-            curr_fd = next_fd;
-        }
-
-        if let Some(min_fd) = curr_fd.checked_add(1) {
-            close_range(min_fd, c_uint::MAX)?;
+            let Some(next_fd) = fd.checked_add(1) else {
+                return Ok(());
+            };
+            min_fd = next_fd;
         }
 
-        Ok(())
+        close_range(min_fd, c_uint::MAX, 0)
     }
 }
 
-fn close_range(min_fd: c_uint, max_fd: c_uint) -> io::Result<()> {
+/// Mark every file descriptor that is not one of the IO streams as close-on-exec. Unlike
+/// [`FileCloser::close_the_universe`], this leaves the file descriptors usable until the next
+/// exec, e.g. in a `pre_exec` closure of a [`std::process::Command`].
+pub(crate) fn cloexec_the_universe() -> io::Result<()> {
+    close_range(
+        STDERR_FILENO as c_uint + 1,
+        c_uint::MAX,
+        libc::CLOSE_RANGE_CLOEXEC as c_uint,
+    )
+}
+
+fn close_range(min_fd: c_uint, max_fd: c_uint, flags: c_uint) -> io::Result<()> {
     if min_fd <= max_fd {
-        cerr(unsafe { libc::syscall(libc::SYS_close_range, min_fd, max_fd, 0 as c_uint) })?;
+        cerr(unsafe { libc::syscall(libc::SYS_close_range, min_fd, max_fd, flags) })?;
     }
 
     Ok(())
 }
 
+/// Create a pipe, returning its read and write ends (in that order). Both ends are closed on exec.
+pub(crate) fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    // SAFETY: `pipe2` writes two file descriptors into an array of length 2
+    cerr(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) })?;
+    // SAFETY: these file descriptors were just created and are not owned by anything else
+    let (rx, tx) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+    Ok((rx, tx))
+}
+
+/// Make reads and writes on `fd` fail with `WouldBlock` instead of blocking.
+pub(crate) fn set_nonblocking<F: AsRawFd>(fd: &F) -> io::Result<()> {
+    let fd = fd.as_raw_fd();
+    // SAFETY: fcntl with F_GETFL/F_SETFL does not access memory
+    let flags = cerr(unsafe { libc::fcntl(fd, libc::F_GETFL) })?;
+    cerr(unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) })?;
+
+    Ok(())
+}
+
+/// Send a copy of `fd` to the process at the other end of `socket`.
+pub(crate) fn send_fd(socket: &UnixStream, fd: BorrowedFd) -> io::Result<()> {
+    let raw_fd = fd.as_raw_fd();
+    let mut byte = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: byte.len(),
+    };
+    // use `u64`s to get a control buffer that is suitably aligned for a `cmsghdr`
+    let mut control = [0u64; 4];
+    // SAFETY: CMSG_SPACE only computes a size
+    let control_len = unsafe { libc::CMSG_SPACE(mem::size_of_val(&raw_fd) as _) } as usize;
+    debug_assert!(control_len <= mem::size_of_val(&control));
+
+    // SAFETY: an all-zero `msghdr` is valid
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = control_len as _;
+
+    // SAFETY: `msg_control` points to a buffer large enough for a single header holding one file
+    // descriptor
+    unsafe {
+        let header = libc::CMSG_FIRSTHDR(&msg);
+        (*header).cmsg_level = libc::SOL_SOCKET;
+        (*header).cmsg_type = libc::SCM_RIGHTS;
+        (*header).cmsg_len = libc::CMSG_LEN(mem::size_of_val(&raw_fd) as _) as _;
+        libc::CMSG_DATA(header)
+            .cast::<libc::c_int>()
+            .write_unaligned(raw_fd);
+    }
+
+    // SAFETY: `msg` only refers to buffers that are alive for the duration of the call
+    cerr(unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) })?;
+
+    Ok(())
+}
+
+/// Receive a file descriptor sent using [`send_fd`]; it will be closed on exec.
+pub(crate) fn receive_fd(socket: &UnixStream) -> io::Result<OwnedFd> {
+    let mut byte = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: byte.len(),
+    };
+    let mut control = [0u64; 4];
+
+    // SAFETY: an all-zero `msghdr` is valid
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    // SAFETY: `msg` only refers to buffers that are alive for the duration of the call
+    let received =
+        cerr(unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) })?;
+
+    // SAFETY: the kernel has filled in the control buffer, which only contains complete headers
+    unsafe {
+        let header = libc::CMSG_FIRSTHDR(&msg);
+        if received == 0
+            || header.is_null()
+            || (*header).cmsg_level != libc::SOL_SOCKET
+            || (*header).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "no file descriptor was received",
+            ));
+        }
+        let raw_fd = libc::CMSG_DATA(header)
+            .cast::<libc::c_int>()
+            .read_unaligned();
+
+        Ok(OwnedFd::from_raw_fd(raw_fd))
+    }
+}
+
 pub(crate) enum ForkResult {
     // Parent process branch with the child process' PID.
     Parent(ProcessId),
@@ -178,6 +291,11 @@ impl ops::Deref for Hostname {
 impl Hostname {
     #[cfg(test)]
     pub fn fake(hostname: &str) -> Self {
+        Self::from_name(hostname)
+    }
+
+    /// A host that is named, rather than the one we are running on
+    pub fn from_name(hostname: &str) -> Self {
         Self {
             inner: hostname.to_string(),
         }
@@ -208,6 +326,43 @@ impl Hostname {
             }
         }
     }
+
+    /// Check whether this host is a member of a netgroup, either by its full name or by its
+    /// unqualified name
+    pub fn in_netgroup(&self, netgroup: &CStr) -> bool {
+        let short_name = self.inner.split('.').next().unwrap_or_default();
+        [&self.inner[..], short_name]
+            .into_iter()
+            .filter_map(|name| CString::new(name).ok())
+            .any(|name| innetgr(netgroup, Some(&name), None))
+    }
+}
+
+extern "C" {
+    #[link_name = "innetgr"]
+    fn libc_innetgr(
+        netgroup: *const libc::c_char,
+        host: *const libc::c_char,
+        user: *const libc::c_char,
+        domain: *const libc::c_char,
+    ) -> libc::c_int;
+}
+
+/// Check whether a host and/or user is a member of a netgroup (see `man 3 innetgr`); the NIS
+/// domain is not taken into account.
+pub(crate) fn innetgr(netgroup: &CStr, host: Option<&CStr>, user: Option<&CStr>) -> bool {
+    let as_ptr = |s: Option<&CStr>| s.map_or(std::ptr::null(), CStr::as_ptr);
+
+    // SAFETY: all arguments are either valid C strings or null pointers, which the function
+    // interprets as wildcards
+    unsafe {
+        libc_innetgr(
+            netgroup.as_ptr(),
+            as_ptr(host),
+            as_ptr(user),
+            std::ptr::null(),
+        ) == 1
+    }
 }
 
 pub fn syslog(priority: libc::c_int, facility: libc::c_int, message: &CStr) {
@@ -251,6 +406,50 @@ pub fn set_target_user(
     }
 }
 
+/// Run `func` with the effective user id, effective group id and supplementary groups set to
+/// those of `user` and `gid`, so that any file system access it does is checked against their
+/// permissions. The real and saved user ids are left alone, which allows restoring the original
+/// credentials afterwards; this therefore requires the effective user to be root.
+pub(crate) fn with_identity<T>(
+    user: &User,
+    gid: GroupId,
+    func: impl FnOnce() -> T,
+) -> io::Result<T> {
+    let orig_uid = User::effective_uid();
+    let orig_gid = User::effective_gid();
+    let orig_groups = {
+        // SAFETY: with a size of 0, getgroups only returns the number of groups
+        let len = cerr(unsafe { libc::getgroups(0, std::ptr::null_mut()) })?;
+        let mut groups = vec![0; len as usize];
+        // SAFETY: `groups` has room for `len` group ids
+        let len = cerr(unsafe { libc::getgroups(len, groups.as_mut_ptr()) })?;
+        groups.truncate(len as usize);
+        groups
+    };
+
+    let mut groups = user.groups.clone();
+    if !groups.contains(&gid) {
+        groups.push(gid);
+    }
+
+    // SAFETY: `groups` contains `groups.len()` group ids; setegid and seteuid do not access memory
+    cerr(unsafe { libc::setgroups(groups.len(), groups.as_ptr()) })?;
+    let switched =
+        cerr(unsafe { libc::setegid(gid) }).and_then(|_| cerr(unsafe { libc::seteuid(user.uid) }));
+
+    let result = switched.as_ref().is_ok().then(func);
+
+    // failing to restore our credentials would leave the process in an unpredictable state
+    // SAFETY: as above, `orig_groups` contains `orig_groups.len()` group ids
+    cerr(unsafe { libc::seteuid(orig_uid) }).expect("could not restore effective user id");
+    cerr(unsafe { libc::setegid(orig_gid) }).expect("could not restore effective group id");
+    cerr(unsafe { libc::setgroups(orig_groups.len(), orig_groups.as_ptr()) })
+        .expect("could not restore supplementary groups");
+
+    switched?;
+    Ok(result.expect("the function was run"))
+}
+
 /// Send a signal to a process with the specified ID.
 pub fn kill(pid: ProcessId, signal: SignalNumber) -> io::Result<()> {
     // SAFETY: This function cannot cause UB even if `pid` is not a valid process ID or if
@@ -652,6 +851,7 @@ pub fn make_zeroed_sigaction() -> libc::sigaction {
 #[cfg(test)]
 mod tests {
     use std::{
+        ffi::c_uint,
         io::{self, Read, Write},
         os::{fd::AsRawFd, unix::net::UnixStream},
         process::exit,
@@ -847,6 +1047,31 @@ mod tests {
         assert_eq!(status.exit_status(), Some(0));
     }
 
+    #[test]
+    fn close_from() {
+        let ForkResult::Parent(child_pid) = fork().unwrap() else {
+            let low = std::fs::File::open(std::env::temp_dir()).unwrap();
+            let excepted = std::fs::File::open(std::env::temp_dir()).unwrap();
+            let high = std::fs::File::open(std::env::temp_dir()).unwrap();
+
+            let mut closer = super::FileCloser::new();
+            closer.close_from(excepted.as_raw_fd() as c_uint);
+            closer.except(&excepted);
+            closer.except(&io::stdout());
+            closer.close_the_universe().unwrap();
+
+            assert!(!is_closed(&low));
+            assert!(!is_closed(&excepted));
+            assert!(is_closed(&high));
+            assert!(!is_closed(&io::stdout()));
+
+            exit(0)
+        };
+
+        let (_, status) = child_pid.wait(WaitOptions::new()).unwrap();
+        assert_eq!(status.exit_status(), Some(0));
+    }
+
     #[test]
     fn except_stdio_is_fine() {
         let ForkResult::Parent(child_pid) = fork().unwrap() else {
@@ -868,4 +1093,27 @@ mod tests {
         let (_, status) = child_pid.wait(WaitOptions::new()).unwrap();
         assert_eq!(status.exit_status(), Some(0));
     }
+
+    #[test]
+    fn with_identity_restores_credentials() {
+        if User::effective_uid() != 0 {
+            return;
+        }
+
+        // changing credentials affects all threads, so do this in a separate process
+        let ForkResult::Parent(child_pid) = fork().unwrap() else {
+            let daemon = User::from_uid(1).unwrap().unwrap();
+            let uids = super::with_identity(&daemon, daemon.gid, || {
+                (User::effective_uid(), User::effective_gid())
+            })
+            .unwrap();
+
+            let ok =
+                uids == (1, daemon.gid) && User::effective_uid() == 0 && User::effective_gid() == 0;
+            exit(if ok { 0 } else { 1 })
+        };
+
+        let (_, status) = child_pid.wait(WaitOptions::new()).unwrap();
+        assert_eq!(status.exit_status(), Some(0));
+    }
 }
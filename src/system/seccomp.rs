@@ -0,0 +1,176 @@
+//! A seccomp filter that lets a supervisor decide whether a process may execute other programs.
+use std::{
+    ffi::c_ulong,
+    io,
+    mem::MaybeUninit,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use libc::{
+    seccomp_notif, seccomp_notif_resp, sock_filter, sock_fprog, BPF_ABS, BPF_JEQ, BPF_JMP, BPF_K,
+    BPF_LD, BPF_RET, BPF_W, EACCES, PR_SET_NO_NEW_PRIVS, SECCOMP_FILTER_FLAG_NEW_LISTENER,
+    SECCOMP_RET_ALLOW, SECCOMP_RET_DATA, SECCOMP_RET_ERRNO, SECCOMP_SET_MODE_FILTER,
+    SECCOMP_USER_NOTIF_FLAG_CONTINUE,
+};
+
+use crate::cutils::cerr;
+
+// These are not (yet) provided by the libc crate.
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+// _IOWR('!', 0, struct seccomp_notif)
+const SECCOMP_IOCTL_NOTIF_RECV: c_ulong = 0xc050_2100;
+// _IOWR('!', 1, struct seccomp_notif_resp)
+const SECCOMP_IOCTL_NOTIF_SEND: c_ulong = 0xc018_2101;
+
+// The offsets of the `nr` and `arch` fields of `struct seccomp_data`.
+const NR_OFFSET: u32 = 0;
+const ARCH_OFFSET: u32 = 4;
+
+/// The `AUDIT_ARCH_*` value of the native system call ABI.
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "x86")]
+const AUDIT_ARCH: Option<u32> = Some(0x4000_0003);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+#[cfg(target_arch = "arm")]
+const AUDIT_ARCH: Option<u32> = Some(0x4000_0028);
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00f3);
+#[cfg(all(target_arch = "powerpc64", target_endian = "little"))]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_0015);
+#[cfg(target_arch = "s390x")]
+const AUDIT_ARCH: Option<u32> = Some(0x8000_0016);
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64",
+    target_arch = "arm",
+    target_arch = "riscv64",
+    all(target_arch = "powerpc64", target_endian = "little"),
+    target_arch = "s390x"
+)))]
+const AUDIT_ARCH: Option<u32> = None;
+
+/// The system calls that execute a program.
+#[cfg(target_arch = "x86_64")]
+const EXEC_SYSCALLS: &[u32] = &[
+    libc::SYS_execve as u32,
+    libc::SYS_execveat as u32,
+    // the x32 ABI uses the same architecture value, but has its own system call numbers
+    0x4000_0000 + 520,
+    0x4000_0000 + 545,
+];
+#[cfg(not(target_arch = "x86_64"))]
+const EXEC_SYSCALLS: &[u32] = &[libc::SYS_execve as u32, libc::SYS_execveat as u32];
+
+const fn statement(code: u32, k: u32) -> sock_filter {
+    sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+const fn jump_if_equal(k: u32, jt: u8) -> sock_filter {
+    sock_filter {
+        code: (BPF_JMP | BPF_JEQ | BPF_K) as u16,
+        jt,
+        jf: 0,
+        k,
+    }
+}
+
+/// Install a seccomp filter on the calling process that suspends every attempt to execute a
+/// program until a supervisor responds to it, and return the file descriptor the supervisor
+/// should listen on. System calls made using a foreign ABI are denied.
+///
+/// This also sets the `no_new_privs` attribute of the process, so the programs it executes
+/// cannot gain privileges (e.g. by being setuid).
+pub(crate) fn install_exec_filter() -> io::Result<OwnedFd> {
+    let Some(arch) = AUDIT_ARCH else {
+        return Err(io::ErrorKind::Unsupported.into());
+    };
+
+    let mut filter = vec![
+        statement(BPF_LD | BPF_W | BPF_ABS, ARCH_OFFSET),
+        jump_if_equal(arch, 1),
+        statement(
+            BPF_RET | BPF_K,
+            SECCOMP_RET_ERRNO | (EACCES as u32 & SECCOMP_RET_DATA),
+        ),
+        statement(BPF_LD | BPF_W | BPF_ABS, NR_OFFSET),
+    ];
+    // every comparison jumps over the ones following it and the `SECCOMP_RET_ALLOW`
+    let count = EXEC_SYSCALLS.len();
+    for (index, &nr) in EXEC_SYSCALLS.iter().enumerate() {
+        filter.push(jump_if_equal(nr, (count - index) as u8));
+    }
+    filter.push(statement(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+    filter.push(statement(BPF_RET | BPF_K, SECCOMP_RET_USER_NOTIF));
+
+    let program = sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_mut_ptr(),
+    };
+
+    // SAFETY: setting `no_new_privs` does not access memory
+    cerr(unsafe { libc::prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) })?;
+    // SAFETY: `program` points to a valid filter that outlives this call
+    let listener = cerr(unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            SECCOMP_SET_MODE_FILTER,
+            SECCOMP_FILTER_FLAG_NEW_LISTENER,
+            &program as *const sock_fprog,
+        )
+    })?;
+
+    // SAFETY: the kernel just created this file descriptor for us
+    Ok(unsafe { OwnedFd::from_raw_fd(listener as _) })
+}
+
+/// Wait for a process to try to execute a program, returning the id of its request; it has to
+/// be answered using [`respond_to_exec_request`].
+pub(crate) fn receive_exec_request(listener: &OwnedFd) -> io::Result<u64> {
+    // the kernel requires the notification to be zeroed
+    let mut notif = MaybeUninit::<seccomp_notif>::zeroed();
+    // SAFETY: `notif` is large enough to hold a `seccomp_notif`
+    cerr(unsafe {
+        libc::ioctl(
+            listener.as_raw_fd(),
+            SECCOMP_IOCTL_NOTIF_RECV as _,
+            notif.as_mut_ptr(),
+        )
+    })?;
+    // SAFETY: the kernel has filled in the notification
+    let notif = unsafe { notif.assume_init() };
+
+    Ok(notif.id)
+}
+
+/// Let the process that made the request with the given `id` execute the program, or make the
+/// system call fail with `EACCES`. Fails with `NotFound` if the process is no longer waiting.
+pub(crate) fn respond_to_exec_request(listener: &OwnedFd, id: u64, allow: bool) -> io::Result<()> {
+    let response = seccomp_notif_resp {
+        id,
+        val: 0,
+        error: if allow { 0 } else { -EACCES },
+        flags: if allow {
+            SECCOMP_USER_NOTIF_FLAG_CONTINUE as u32
+        } else {
+            0
+        },
+    };
+    // SAFETY: `response` is a valid `seccomp_notif_resp`
+    cerr(unsafe {
+        libc::ioctl(
+            listener.as_raw_fd(),
+            SECCOMP_IOCTL_NOTIF_SEND as _,
+            &response as *const seccomp_notif_resp,
+        )
+    })?;
+
+    Ok(())
+}
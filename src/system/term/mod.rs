@@ -196,6 +196,16 @@ pub(crate) struct TermSize {
     raw: winsize,
 }
 
+impl TermSize {
+    pub(crate) fn rows(&self) -> u16 {
+        self.raw.ws_row
+    }
+
+    pub(crate) fn cols(&self) -> u16 {
+        self.raw.ws_col
+    }
+}
+
 impl PartialEq for TermSize {
     fn eq(&self, other: &Self) -> bool {
         self.raw.ws_col == other.raw.ws_col && self.raw.ws_row == other.raw.ws_row
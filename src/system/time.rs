@@ -122,6 +122,31 @@ impl Sub<Duration> for Duration {
     }
 }
 
+/// Format a number of seconds since the Unix epoch as a local time, such as "Jan  1 12:00:00 2024".
+pub fn format_local_time(secs: i64) -> String {
+    let time = secs as libc::time_t;
+    let mut tm = MaybeUninit::<libc::tm>::uninit();
+    // SAFETY: `localtime_r` only writes to the `tm` that it was passed
+    if unsafe { libc::localtime_r(&time, tm.as_mut_ptr()) }.is_null() {
+        return secs.to_string();
+    }
+    // SAFETY: `localtime_r` succeeded, so it has initialized `tm`
+    let tm = unsafe { tm.assume_init() };
+
+    let mut buf = [0u8; 64];
+    // SAFETY: the format is NUL-terminated, and `strftime` writes at most `buf.len()` bytes
+    let len = unsafe {
+        libc::strftime(
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            b"%b %e %H:%M:%S %Y\0".as_ptr().cast(),
+            &tm,
+        )
+    };
+
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
 impl From<libc::timespec> for SystemTime {
     fn from(value: libc::timespec) -> Self {
         SystemTime::new(value.tv_sec as _, value.tv_nsec as _)
@@ -40,7 +40,7 @@ impl SessionRecordFile {
         SessionRecordFile::new(uid, secure_open_cookie_file(&path)?, timeout)
     }
 
-    const FILE_VERSION: u16 = 1;
+    const FILE_VERSION: u16 = 3;
     const MAGIC_NUM: u16 = 0x50D0;
     const VERSION_OFFSET: u64 = Self::MAGIC_NUM.to_le_bytes().len() as u64;
     const FIRST_RECORD_OFFSET: u64 =
@@ -326,8 +326,34 @@ pub enum CreateResult {
     Created { time: SystemTime },
 }
 
+/// How widely a successful authentication is shared, as configured by `timestamp_type`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampType {
+    /// A single record for all sessions of the user
+    Global,
+    /// A record for the parent process, i.e. usually the shell that sudo was started from
+    Ppid,
+    /// A record for the terminal session, or for the parent process if there is no terminal
+    #[default]
+    Tty,
+    /// A record that is tied to the terminal itself; without a terminal nothing is recorded.
+    /// Linux does not offer to store this in the kernel, so it is kept in the record file.
+    Kernel,
+}
+
+impl TimestampType {
+    /// All the timestamp types, e.g. for invalidating the records of every type
+    pub const ALL: [TimestampType; 4] = [
+        TimestampType::Global,
+        TimestampType::Ppid,
+        TimestampType::Tty,
+        TimestampType::Kernel,
+    ];
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RecordScope {
+    Global,
     Tty {
         tty_device: libc::dev_t,
         session_pid: libc::pid_t,
@@ -337,6 +363,13 @@ pub enum RecordScope {
         group_pid: libc::pid_t,
         init_time: SystemTime,
     },
+    /// Like `Tty`, but only used for `timestamp_type=kernel`, so that the records of the two
+    /// types are kept apart
+    Kernel {
+        tty_device: libc::dev_t,
+        session_pid: libc::pid_t,
+        init_time: SystemTime,
+    },
 }
 
 impl RecordScope {
@@ -363,6 +396,21 @@ impl RecordScope {
                 target.write_all(&b)?;
                 init_time.encode(target)?;
             }
+            RecordScope::Global => {
+                target.write_all(&[3u8])?;
+            }
+            RecordScope::Kernel {
+                tty_device,
+                session_pid,
+                init_time,
+            } => {
+                target.write_all(&[4u8])?;
+                let b = tty_device.to_le_bytes();
+                target.write_all(&b)?;
+                let b = session_pid.to_le_bytes();
+                target.write_all(&b)?;
+                init_time.encode(target)?;
+            }
         }
 
         Ok(())
@@ -372,7 +420,7 @@ impl RecordScope {
         let mut buf = [0; 1];
         from.read_exact(&mut buf)?;
         match buf[0] {
-            1 => {
+            discriminator @ (1 | 4) => {
                 let mut buf = [0; std::mem::size_of::<libc::dev_t>()];
                 from.read_exact(&mut buf)?;
                 let tty_device = libc::dev_t::from_le_bytes(buf);
@@ -380,10 +428,18 @@ impl RecordScope {
                 from.read_exact(&mut buf)?;
                 let session_pid = libc::pid_t::from_le_bytes(buf);
                 let init_time = SystemTime::decode(from)?;
-                Ok(RecordScope::Tty {
-                    tty_device,
-                    session_pid,
-                    init_time,
+                Ok(if discriminator == 1 {
+                    RecordScope::Tty {
+                        tty_device,
+                        session_pid,
+                        init_time,
+                    }
+                } else {
+                    RecordScope::Kernel {
+                        tty_device,
+                        session_pid,
+                        init_time,
+                    }
                 })
             }
             2 => {
@@ -396,6 +452,7 @@ impl RecordScope {
                     init_time,
                 })
             }
+            3 => Ok(RecordScope::Global),
             x => Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 format!("Unexpected scope variant discriminator: {x}"),
@@ -403,22 +460,41 @@ impl RecordScope {
         }
     }
 
-    /// Tries to determine a record match scope for the current context.
+    /// Tries to determine a record match scope of the given type for the current context.
     /// This should never produce an error since any actual error should just be
     /// ignored and no session record file should be used in that case.
-    pub fn for_process(process: &Process) -> Option<RecordScope> {
-        let tty = Process::tty_device_id(WithProcess::Current);
-        if let Ok(Some(tty_device)) = tty {
+    pub fn for_process(process: &Process, timestamp_type: TimestampType) -> Option<RecordScope> {
+        if timestamp_type == TimestampType::Global {
+            return Some(RecordScope::Global);
+        }
+
+        let tty = match timestamp_type {
+            TimestampType::Ppid => None,
+            _ => Process::tty_device_id(WithProcess::Current).ok().flatten(),
+        };
+        if let Some(tty_device) = tty {
             if let Ok(init_time) = Process::starting_time(WithProcess::Other(process.session_id)) {
-                Some(RecordScope::Tty {
-                    tty_device,
-                    session_pid: process.session_id,
-                    init_time,
+                let session_pid = process.session_id;
+                Some(if timestamp_type == TimestampType::Kernel {
+                    RecordScope::Kernel {
+                        tty_device,
+                        session_pid,
+                        init_time,
+                    }
+                } else {
+                    RecordScope::Tty {
+                        tty_device,
+                        session_pid,
+                        init_time,
+                    }
                 })
             } else {
                 auth_warn!("Could not get terminal foreground process starting time");
                 None
             }
+        } else if timestamp_type == TimestampType::Kernel {
+            // records are only kept for terminals
+            None
         } else if let Some(parent_pid) = process.parent_pid {
             if let Ok(init_time) = Process::starting_time(WithProcess::Other(parent_pid)) {
                 Some(RecordScope::Ppid {
@@ -605,6 +681,24 @@ mod tests {
         let bytes = ppid_sample.as_bytes().unwrap();
         let decoded = SessionRecord::from_bytes(&bytes).unwrap();
         assert_eq!(ppid_sample, decoded);
+
+        let global_sample = SessionRecord::new(RecordScope::Global, 456).unwrap();
+        let bytes = global_sample.as_bytes().unwrap();
+        let decoded = SessionRecord::from_bytes(&bytes).unwrap();
+        assert_eq!(global_sample, decoded);
+
+        let kernel_sample = SessionRecord::new(
+            RecordScope::Kernel {
+                tty_device: 10,
+                session_pid: 42,
+                init_time: SystemTime::now().unwrap(),
+            },
+            789,
+        )
+        .unwrap();
+        let bytes = kernel_sample.as_bytes().unwrap();
+        let decoded = SessionRecord::from_bytes(&bytes).unwrap();
+        assert_eq!(kernel_sample, decoded);
     }
 
     #[test]
@@ -635,6 +729,14 @@ mod tests {
             },
             675
         ));
+        assert!(!tty_sample.matches(
+            &RecordScope::Kernel {
+                tty_device: 12,
+                session_pid: 1234,
+                init_time
+            },
+            675
+        ));
 
         // make sure time is different
         std::thread::sleep(std::time::Duration::from_millis(1));
@@ -685,29 +787,35 @@ mod tests {
     #[test]
     fn session_record_file_header_checks() {
         // valid header should remain valid
-        let c = tempfile_with_data(&[0xD0, 0x50, 0x01, 0x00]).unwrap();
+        let c = tempfile_with_data(&[0xD0, 0x50, 0x03, 0x00]).unwrap();
         let timeout = Duration::seconds(30);
         assert!(SessionRecordFile::new(TEST_USER_ID, c.try_clone().unwrap(), timeout).is_ok());
         let v = data_from_tempfile(c).unwrap();
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x03, 0x00]);
 
         // invalid headers should be corrected
         let c = tempfile_with_data(&[0xAB, 0xBA]).unwrap();
         assert!(SessionRecordFile::new(TEST_USER_ID, c.try_clone().unwrap(), timeout).is_ok());
         let v = data_from_tempfile(c).unwrap();
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x03, 0x00]);
 
         // empty header should be filled in
         let c = tempfile_with_data(&[]).unwrap();
         assert!(SessionRecordFile::new(TEST_USER_ID, c.try_clone().unwrap(), timeout).is_ok());
         let v = data_from_tempfile(c).unwrap();
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x03, 0x00]);
 
         // invalid version should reset file
         let c = tempfile_with_data(&[0xD0, 0x50, 0xAB, 0xBA, 0x0, 0x0]).unwrap();
         assert!(SessionRecordFile::new(TEST_USER_ID, c.try_clone().unwrap(), timeout).is_ok());
         let v = data_from_tempfile(c).unwrap();
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x03, 0x00]);
+
+        // files of the previous version should be reset
+        let c = tempfile_with_data(&[0xD0, 0x50, 0x01, 0x00, 0x1, 0x0]).unwrap();
+        assert!(SessionRecordFile::new(TEST_USER_ID, c.try_clone().unwrap(), timeout).is_ok());
+        let v = data_from_tempfile(c).unwrap();
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x03, 0x00]);
     }
 
     #[test]
@@ -747,6 +855,6 @@ mod tests {
 
         // after all this the data should be just an empty header
         let data = data_from_tempfile(c).unwrap();
-        assert_eq!(&data, &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&data, &[0xD0, 0x50, 0x03, 0x00]);
     }
 }
@@ -6,6 +6,7 @@ pub(crate) struct VisudoOptions {
     pub(crate) strict: bool,
     pub(crate) owner: bool,
     pub(crate) perms: bool,
+    pub(crate) format: OutputFormat,
     pub(crate) action: VisudoAction,
 }
 
@@ -18,6 +19,7 @@ impl Default for VisudoOptions {
             strict: false,
             owner: false,
             perms: false,
+            format: OutputFormat::Text,
             action: VisudoAction::Run,
         }
     }
@@ -28,13 +30,23 @@ pub(crate) enum VisudoAction {
     Help,
     Version,
     Check,
+    /// write the policy as JSON to the given file, or to stdout for `-`
+    Export(String),
     Run,
 }
 
+/// How `visudo --check` reports its findings
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum OutputFormat {
+    Text,
+    /// one JSON object per line
+    Json,
+}
+
 type OptionSetter = fn(&mut VisudoOptions, Option<String>) -> Result<(), String>;
 
 struct VisudoOption {
-    short: char,
+    short: Option<char>,
     long: &'static str,
     takes_argument: bool,
     set: OptionSetter,
@@ -43,7 +55,7 @@ struct VisudoOption {
 impl VisudoOptions {
     const VISUDO_OPTIONS: &'static [VisudoOption] = &[
         VisudoOption {
-            short: 'c',
+            short: Some('c'),
             long: "check",
             takes_argument: false,
             set: |options, _| {
@@ -52,7 +64,7 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 'f',
+            short: Some('f'),
             long: "file",
             takes_argument: true,
             set: |options, argument| {
@@ -61,7 +73,21 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 'h',
+            short: None,
+            long: "format",
+            takes_argument: true,
+            set: |options, argument| {
+                options.format = match argument.as_deref() {
+                    Some("text") => OutputFormat::Text,
+                    Some("json") => OutputFormat::Json,
+                    Some(format) => Err(format!("unsupported output format '{format}'"))?,
+                    None => Err("option '--format' requires an argument")?,
+                };
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: Some('h'),
             long: "help",
             takes_argument: false,
             set: |options, _| {
@@ -70,16 +96,16 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 'I',
+            short: Some('I'),
             long: "no-includes",
             takes_argument: false,
             set: |options, _| {
-                options.includes = true;
+                options.includes = false;
                 Ok(())
             },
         },
         VisudoOption {
-            short: 'q',
+            short: Some('q'),
             long: "quiet",
             takes_argument: false,
             set: |options, _| {
@@ -88,7 +114,7 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 's',
+            short: Some('s'),
             long: "strict",
             takes_argument: false,
             set: |options, _| {
@@ -97,7 +123,7 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 'V',
+            short: Some('V'),
             long: "version",
             takes_argument: false,
             set: |options, _| {
@@ -106,7 +132,17 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 'O',
+            short: Some('x'),
+            long: "export",
+            takes_argument: true,
+            set: |options, argument| {
+                let output = argument.ok_or("option requires an argument -- 'x'")?;
+                options.action = VisudoAction::Export(output);
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: Some('O'),
             long: "owner",
             takes_argument: false,
             set: |options, _| {
@@ -115,7 +151,7 @@ impl VisudoOptions {
             },
         },
         VisudoOption {
-            short: 'P',
+            short: Some('P'),
             long: "perms",
             takes_argument: false,
             set: |options, _| {
@@ -173,7 +209,9 @@ impl VisudoOptions {
                 // flags can be grouped, so we loop over the characters
                 for (n, char) in arg.trim_start_matches('-').chars().enumerate() {
                     // lookup the option
-                    if let Some(option) = Self::VISUDO_OPTIONS.iter().find(|o| o.short == char) {
+                    if let Some(option) =
+                        Self::VISUDO_OPTIONS.iter().find(|o| o.short == Some(char))
+                    {
                         // try to parse an argument when one is necessary, either the rest of the current flag group or the next argument
                         if option.takes_argument {
                             let rest = arg[(n + 2)..].trim().to_string();
@@ -1,15 +1,17 @@
-pub(crate) const USAGE_MSG: &str = "usage: visudo [-chqsV] [[-f] sudoers ]";
+pub(crate) const USAGE_MSG: &str = "usage: visudo [-chqsV] [-x output_file] [[-f] sudoers ]";
 
 const DESCRIPTOR: &str = "visudo - safely edit the sudoers file";
 
 const HELP_MSG: &str = "Options:
   -c, --check              check-only mode
   -f, --file=sudoers       specify sudoers file location
+      --format=text|json   output format of check-only mode
   -h, --help               display help message and exit
   -I, --no-includes        do not edit include files
   -q, --quiet              less verbose (quiet) syntax error messages
   -s, --strict             strict syntax checking
   -V, --version            display version information and exit
+  -x, --export=output_file write sudoers in JSON format to output_file
 ";
 
 pub(crate) fn long_help_message() -> String {
@@ -2,26 +2,27 @@ mod cli;
 mod help;
 
 use std::{
-    ffi::{CString, OsString},
+    ffi::{OsStr, OsString},
     fs::{File, Permissions},
     io::{self, Read, Seek, Write},
-    os::unix::prelude::{MetadataExt, OsStringExt, PermissionsExt},
+    ops::Range,
+    os::unix::prelude::{MetadataExt, PermissionsExt},
     path::{Path, PathBuf},
     process::Command,
 };
 
 use crate::{
+    common::{json::Json, resolve::editor_path_fallback},
     sudo::diagnostic,
-    sudoers::Sudoers,
+    sudoers::{Lint, PreJudgementPolicy, Sudoers},
     system::{
-        can_execute,
-        file::{Chown, FileLock},
+        file::{create_temporary_dir, Chown, FileLock},
         signal::{consts::*, register_handlers, SignalStream},
-        User,
+        Hostname, User,
     },
 };
 
-use self::cli::{VisudoAction, VisudoOptions};
+use self::cli::{OutputFormat, VisudoAction, VisudoOptions};
 use self::help::{long_help_message, USAGE_MSG};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -41,7 +42,8 @@ pub fn main() {
         }
     };
 
-    let cmd = match options.action {
+    let file = options.file.as_deref();
+    let result = match options.action {
         VisudoAction::Help => {
             println_ignore_io_error!("{}", long_help_message());
             std::process::exit(0);
@@ -50,11 +52,26 @@ pub fn main() {
             println_ignore_io_error!("visudo version {VERSION}");
             std::process::exit(0);
         }
-        VisudoAction::Check => check,
-        VisudoAction::Run => run,
+        VisudoAction::Check => match options.format {
+            OutputFormat::Text => check(file, options.perms, options.owner, options.strict),
+            OutputFormat::Json => std::process::exit(check_json(
+                file,
+                options.perms,
+                options.owner,
+                options.strict,
+            )),
+        },
+        VisudoAction::Export(ref output) => export(file, output),
+        VisudoAction::Run => run(
+            file,
+            options.perms,
+            options.owner,
+            options.includes,
+            options.strict,
+        ),
     };
 
-    match cmd(options.file.as_deref(), options.perms, options.owner) {
+    match result {
         Ok(()) => {}
         Err(error) => {
             eprintln_ignore_io_error!("visudo: {error}");
@@ -63,7 +80,36 @@ pub fn main() {
     }
 }
 
-fn check(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
+/// Exit codes of `visudo --check --format=json`, besides 0 for a valid sudoers file
+const EXIT_SYNTAX_ERROR: i32 = 1;
+const EXIT_PERMISSIONS_ERROR: i32 = 2;
+const EXIT_IO_ERROR: i32 = 3;
+
+/// Why a sudoers file could not be checked
+enum CheckError {
+    /// the file has the wrong mode or owner
+    Permissions(String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for CheckError {
+    fn from(err: io::Error) -> Self {
+        CheckError::Io(err)
+    }
+}
+
+impl From<CheckError> for io::Error {
+    fn from(err: CheckError) -> Self {
+        match err {
+            CheckError::Permissions(message) => io::Error::new(io::ErrorKind::Other, message),
+            CheckError::Io(err) => err,
+        }
+    }
+}
+
+/// Open the sudoers file to check it; the mode and owner of `/etc/sudoers` are always verified,
+/// those of another file only if `perms` or `owner` is set
+fn open_for_check(file_arg: Option<&str>, perms: bool, owner: bool) -> Result<File, CheckError> {
     let sudoers_path = Path::new(file_arg.unwrap_or("/etc/sudoers"));
 
     let sudoers_file = File::open(sudoers_path)
@@ -76,13 +122,10 @@ fn check(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
         let mode = metadata.permissions().mode() & 0o777;
 
         if mode != 0o440 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "{}: bad permissions, should be mode 0440, but found {mode:04o}",
-                    sudoers_path.display()
-                ),
-            ));
+            return Err(CheckError::Permissions(format!(
+                "{}: bad permissions, should be mode 0440, but found {mode:04o}",
+                sudoers_path.display()
+            )));
         }
     }
 
@@ -90,22 +133,23 @@ fn check(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
         let owner = (metadata.uid(), metadata.gid());
 
         if owner != (0, 0) {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "{}: wrong owner (uid, gid) should be (0, 0), but found {owner:?}",
-                    sudoers_path.display()
-                ),
-            ));
+            return Err(CheckError::Permissions(format!(
+                "{}: wrong owner (uid, gid) should be (0, 0), but found {owner:?}",
+                sudoers_path.display()
+            )));
         }
     }
 
-    let (_sudoers, errors) = Sudoers::read(&sudoers_file, sudoers_path)?;
+    Ok(sudoers_file)
+}
 
-    if errors.is_empty() {
-        writeln!(io::stdout(), "{}: parsed OK", sudoers_path.display())?;
-        return Ok(());
-    }
+fn check(file_arg: Option<&str>, perms: bool, owner: bool, strict: bool) -> io::Result<()> {
+    let sudoers_path = Path::new(file_arg.unwrap_or("/etc/sudoers"));
+    let sudoers_file = open_for_check(file_arg, perms, owner)?;
+
+    let (sudoers, errors) = Sudoers::read(&sudoers_file, sudoers_path)?;
+    let Lint { problems, warnings } = lint_valid(&sudoers, &errors);
+    let has_errors = !errors.is_empty() || (strict && !problems.is_empty());
 
     for crate::sudoers::Error {
         message,
@@ -117,10 +161,160 @@ fn check(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
         diagnostic::diagnostic!("syntax error: {message}", path @ location);
     }
 
+    for (severity, findings) in [(strict, problems), (false, warnings)] {
+        let severity = if severity { "error" } else { "warning" };
+        for crate::sudoers::Error {
+            message,
+            source,
+            location,
+        } in findings
+        {
+            let path = source.as_deref().unwrap_or(sudoers_path);
+            diagnostic::diagnostic!("{severity}: {message}", path @ location);
+        }
+    }
+
+    if !has_errors {
+        writeln!(io::stdout(), "{}: parsed OK", sudoers_path.display())?;
+        return Ok(());
+    }
+
     Err(io::Error::new(io::ErrorKind::Other, "invalid sudoers file"))
 }
 
-fn run(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
+/// Like `check`, but every diagnostic is written to stdout as a JSON object on a line of its own,
+/// followed by a summary that lists the files that were parsed. Returns the exit code.
+fn check_json(file_arg: Option<&str>, perms: bool, owner: bool, strict: bool) -> i32 {
+    let sudoers_path = Path::new(file_arg.unwrap_or("/etc/sudoers"));
+
+    let mut files = Vec::new();
+    let outcome = open_for_check(file_arg, perms, owner).and_then(|sudoers_file| {
+        files.push(sudoers_path.to_path_buf());
+        let mut read_include = |path: &Path| {
+            let contents = Sudoers::read_include(path)?;
+            files.push(path.to_path_buf());
+            Ok(contents)
+        };
+        Ok(Sudoers::read_with_includes(
+            sudoers_file,
+            sudoers_path,
+            &mut read_include,
+        )?)
+    });
+
+    let mut diagnostics = Vec::new();
+    let error = |message: String| crate::sudoers::Error {
+        source: Some(sudoers_path.to_path_buf()),
+        location: None,
+        message,
+    };
+    let (status, exit_code) = match outcome {
+        Ok((sudoers, errors)) => {
+            let Lint { problems, warnings } = lint_valid(&sudoers, &errors);
+            let failed = !errors.is_empty() || (strict && !problems.is_empty());
+            let severity = if strict { "error" } else { "warning" };
+
+            diagnostics.extend(errors.into_iter().map(|error| ("error", error)));
+            diagnostics.extend(problems.into_iter().map(|problem| (severity, problem)));
+            diagnostics.extend(warnings.into_iter().map(|warning| ("warning", warning)));
+
+            if failed {
+                ("syntax_error", EXIT_SYNTAX_ERROR)
+            } else {
+                ("ok", 0)
+            }
+        }
+        Err(CheckError::Permissions(message)) => {
+            diagnostics.push(("error", error(message)));
+            ("permissions_error", EXIT_PERMISSIONS_ERROR)
+        }
+        Err(CheckError::Io(err)) => {
+            diagnostics.push(("error", error(err.to_string())));
+            ("io_error", EXIT_IO_ERROR)
+        }
+    };
+
+    let count = |wanted: &str| {
+        diagnostics
+            .iter()
+            .filter(|(severity, _)| *severity == wanted)
+            .count()
+    };
+    let summary = Json::object([
+        ("type", "summary".into()),
+        ("status", status.into()),
+        (
+            "files",
+            Json::from(
+                files
+                    .iter()
+                    .map(|path| path_to_json(path))
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        ("errors", count("error").into()),
+        ("warnings", count("warning").into()),
+    ]);
+
+    for (severity, error) in &diagnostics {
+        let location = error.location.as_ref().map(|location| location.start);
+        let diagnostic = Json::object([
+            ("type", "diagnostic".into()),
+            ("file", error.source.as_deref().map(path_to_json).into()),
+            ("line", location.map(|(line, _)| line).into()),
+            ("column", location.map(|(_, column)| column).into()),
+            ("severity", (*severity).into()),
+            ("message", error.message.as_str().into()),
+        ]);
+        println_ignore_io_error!("{diagnostic}");
+    }
+    println_ignore_io_error!("{summary}");
+
+    exit_code
+}
+
+/// Write the policy in `file_arg`, and the files it includes, as JSON to `output`; `-` stands
+/// for stdout. Nothing is written if the policy has syntax errors.
+fn export(file_arg: Option<&str>, output: &str) -> io::Result<()> {
+    let sudoers_path = Path::new(file_arg.unwrap_or("/etc/sudoers"));
+    let sudoers_file = File::open(sudoers_path)
+        .map_err(|err| io_msg!(err, "unable to open {}", sudoers_path.display()))?;
+
+    let (sudoers, errors) = Sudoers::read(&sudoers_file, sudoers_path)?;
+
+    if !errors.is_empty() {
+        for crate::sudoers::Error {
+            message,
+            source,
+            location,
+        } in errors
+        {
+            let path = source.as_deref().unwrap_or(sudoers_path);
+            diagnostic::diagnostic!("syntax error: {message}", path @ location);
+        }
+
+        return Err(io::Error::new(io::ErrorKind::Other, "invalid sudoers file"));
+    }
+
+    let json = format!("{:#}\n", sudoers.to_json());
+    if output == "-" {
+        io::stdout().write_all(json.as_bytes())
+    } else {
+        std::fs::write(output, json).map_err(|err| io_msg!(err, "unable to write {output}"))
+    }
+}
+
+fn path_to_json(path: &Path) -> Json {
+    path.to_string_lossy().into_owned().into()
+}
+
+fn run(
+    file_arg: Option<&str>,
+    perms: bool,
+    owner: bool,
+    includes: bool,
+    strict: bool,
+) -> io::Result<()> {
     let sudoers_path = Path::new(file_arg.unwrap_or("/etc/sudoers"));
 
     let (sudoers_file, existed) = if sudoers_path.exists() {
@@ -138,13 +332,7 @@ fn run(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
         (file, false)
     };
 
-    let lock = FileLock::exclusive(&sudoers_file, true).map_err(|err| {
-        if err.kind() == io::ErrorKind::WouldBlock {
-            io_msg!(err, "{} busy, try again later", sudoers_path.display())
-        } else {
-            err
-        }
-    })?;
+    let lock = lock_sudoers_file(&sudoers_file, sudoers_path)?;
 
     if perms || file_arg.is_none() {
         sudoers_file.set_permissions(Permissions::from_mode(0o440))?;
@@ -158,8 +346,7 @@ fn run(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
 
     let handlers = register_handlers([SIGTERM, SIGHUP, SIGINT, SIGQUIT])?;
 
-    let tmp_dir = create_temporary_dir()?;
-    let tmp_path = tmp_dir.join("sudoers");
+    let tmp_dir = create_temporary_dir("/tmp/sudoers")?;
 
     {
         let tmp_dir = tmp_dir.clone();
@@ -174,51 +361,105 @@ fn run(file_arg: Option<&str>, perms: bool, owner: bool) -> io::Result<()> {
         });
     }
 
-    let tmp_file = File::options()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&tmp_path)?;
-
-    tmp_file.set_permissions(Permissions::from_mode(0o700))?;
-
-    let result = edit_sudoers_file(
-        existed,
-        sudoers_file,
-        sudoers_path,
-        lock,
-        tmp_file,
-        &tmp_path,
-    );
+    let result = SudoersFile::new(sudoers_path, sudoers_file, lock, &tmp_dir.join("sudoers"))
+        .and_then(|sudoers| edit_sudoers_file(existed, sudoers, &tmp_dir, includes, strict));
 
     std::fs::remove_dir_all(tmp_dir)?;
 
     result
 }
 
+fn lock_sudoers_file(file: &File, path: &Path) -> io::Result<FileLock> {
+    FileLock::exclusive(file, true).map_err(|err| {
+        if err.kind() == io::ErrorKind::WouldBlock {
+            io_msg!(err, "{} busy, try again later", path.display())
+        } else {
+            err
+        }
+    })
+}
+
+/// A sudoers file (either the one that visudo was started for, or one that it includes) that is
+/// locked while a temporary copy of it is being edited
+struct SudoersFile {
+    path: PathBuf,
+    // declared before `file`, so that the lock is released before the file is closed
+    lock: FileLock,
+    file: File,
+    contents: Vec<u8>,
+    tmp_path: PathBuf,
+}
+
+impl SudoersFile {
+    /// Make a temporary copy at `tmp_path` of the (already locked) `file`
+    fn new(path: &Path, mut file: File, lock: FileLock, tmp_path: &Path) -> io::Result<Self> {
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut tmp_file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(tmp_path)?;
+        tmp_file.set_permissions(Permissions::from_mode(0o700))?;
+        tmp_file.write_all(&contents)?;
+
+        Ok(SudoersFile {
+            path: path.to_path_buf(),
+            lock,
+            file,
+            contents,
+            tmp_path: tmp_path.to_path_buf(),
+        })
+    }
+
+    /// Lock a file that is included by the sudoers file and make a temporary copy of it in
+    /// `tmp_dir`
+    fn open_include(path: &Path, tmp_dir: &Path, index: usize) -> io::Result<Self> {
+        let file = File::options().read(true).write(true).open(path)?;
+        let lock = lock_sudoers_file(&file, path)?;
+        let name = path.file_name().unwrap_or(OsStr::new("sudoers"));
+        let mut tmp_name = OsString::from(format!("{index}-"));
+        tmp_name.push(name);
+
+        SudoersFile::new(path, file, lock, &tmp_dir.join(tmp_name))
+    }
+
+    /// Write the edited copy back if it was changed
+    fn save(mut self) -> io::Result<()> {
+        let tmp_contents = std::fs::read(&self.tmp_path)?;
+        // Only write to the sudoers file if the contents changed.
+        if tmp_contents == self.contents {
+            writeln!(
+                io::stderr(),
+                "visudo: {} unchanged",
+                self.tmp_path.display()
+            )?;
+        } else {
+            self.file.rewind()?;
+            self.file.write_all(&tmp_contents)?;
+            let new_size = self.file.stream_position()?;
+            self.file.set_len(new_size)?;
+        }
+
+        self.lock.unlock()
+    }
+}
+
 fn edit_sudoers_file(
     existed: bool,
-    mut sudoers_file: File,
-    sudoers_path: &Path,
-    lock: FileLock,
-    mut tmp_file: File,
-    tmp_path: &Path,
+    sudoers: SudoersFile,
+    tmp_dir: &Path,
+    includes: bool,
+    strict: bool,
 ) -> io::Result<()> {
     let mut editor_path = None;
-    let mut sudoers_contents = Vec::new();
     if existed {
-        // If the sudoers file existed, read its contents and write them into the temporary file.
-        sudoers_file.read_to_end(&mut sudoers_contents)?;
-        // Rewind the sudoers file so it can be written later.
-        sudoers_file.rewind()?;
-        // Write to the temporary file.
-        tmp_file.write_all(&sudoers_contents)?;
-
-        let (sudoers, errors) = Sudoers::read(sudoers_contents.as_slice(), sudoers_path)?;
+        let (sudoers, errors) = Sudoers::read(sudoers.contents.as_slice(), &sudoers.path)?;
 
-        if errors.is_empty() {
-            editor_path = sudoers.solve_editor_path();
+        if let (true, Some(user)) = (errors.is_empty(), User::real().ok().flatten()) {
+            editor_path = sudoers.solve_editor_path(&user, &Hostname::resolve());
         }
     }
 
@@ -227,24 +468,50 @@ fn edit_sudoers_file(
         None => editor_path_fallback()?,
     };
 
+    let mut files = vec![sudoers];
+    // the files in `files` that need to be (re-)edited
+    let mut to_edit = vec![0];
+
+    // lock the included files before anything is edited
+    if includes {
+        let (_, _, included) = check_edited_files(&files)?;
+        to_edit.extend(add_includes(&mut files, included, tmp_dir)?);
+    }
+
     let mut stderr = io::stderr();
     loop {
-        Command::new(&editor_path)
-            .arg("--")
-            .arg(tmp_path)
-            .spawn()?
-            .wait_with_output()?;
-
-        let (_sudoers, errors) = File::open(tmp_path)
-            .and_then(|reader| Sudoers::read(reader, tmp_path))
-            .map_err(|err| {
-                io_msg!(
-                    err,
-                    "unable to re-open temporary file ({}), {} unchanged",
-                    tmp_path.display(),
-                    sudoers_path.display()
-                )
-            })?;
+        for &index in &to_edit {
+            let file = &files[index];
+            if index > 0 {
+                prompt_for_edit(&file.path)?;
+            }
+
+            Command::new(&editor_path)
+                .arg("--")
+                .arg(&file.tmp_path)
+                .spawn()?
+                .wait_with_output()?;
+        }
+
+        let (mut errors, Lint { problems, warnings }, new_includes) = check_edited_files(&files)?;
+
+        // files that are included for the first time are edited before the result is judged
+        if includes && !new_includes.is_empty() {
+            to_edit = add_includes(&mut files, new_includes, tmp_dir)?.collect();
+            continue;
+        }
+
+        let syntax_errors = errors.len();
+        let warnings = if strict {
+            errors.extend(problems);
+            warnings
+        } else {
+            problems.into_iter().chain(warnings).collect()
+        };
+
+        for warning in &warnings {
+            writeln!(stderr, "warning: {}", located(warning))?;
+        }
 
         if errors.is_empty() {
             break;
@@ -252,8 +519,12 @@ fn edit_sudoers_file(
 
         writeln!(stderr, "The provided sudoers file format is not recognized or contains syntax errors. Please review:\n")?;
 
-        for crate::sudoers::Error { message, .. } in errors {
-            writeln!(stderr, "syntax error: {message}")?;
+        for (index, error) in errors.iter().enumerate() {
+            if index < syntax_errors {
+                writeln!(stderr, "syntax error: {}", error.message)?;
+            } else {
+                writeln!(stderr, "error: {}", located(error))?;
+            }
         }
 
         writeln!(stderr)?;
@@ -281,45 +552,112 @@ fn edit_sudoers_file(
                 input => writeln!(stderr, "Invalid option: {:?}\n", std::str::from_utf8(input))?,
             }
         }
-    }
 
-    let tmp_contents = std::fs::read(tmp_path)?;
-    // Only write to the sudoers file if the contents changed.
-    if tmp_contents == sudoers_contents {
-        writeln!(stderr, "visudo: {} unchanged", tmp_path.display())?;
-    } else {
-        sudoers_file.write_all(&tmp_contents)?;
-        let new_size = sudoers_file.stream_position()?;
-        sudoers_file.set_len(new_size)?;
+        // edit the files that contain errors again; or the sudoers file itself, if the errors
+        // cannot be attributed to a file that is being edited
+        to_edit = (0..files.len())
+            .filter(|&index| {
+                errors
+                    .iter()
+                    .any(|error| error.source.as_deref() == Some(files[index].path.as_path()))
+            })
+            .collect();
+        if to_edit.is_empty() {
+            to_edit.push(0);
+        }
     }
 
-    lock.unlock()?;
+    for file in files {
+        file.save()?;
+    }
 
     Ok(())
 }
 
-fn editor_path_fallback() -> io::Result<PathBuf> {
-    let path = Path::new("/usr/bin/editor");
-    if can_execute(path) {
-        return Ok(path.to_owned());
+/// Lock and copy the included files at `paths`, returning the range of `files` they were added at
+fn add_includes(
+    files: &mut Vec<SudoersFile>,
+    paths: Vec<PathBuf>,
+    tmp_dir: &Path,
+) -> io::Result<Range<usize>> {
+    let start = files.len();
+    for path in paths {
+        let include = SudoersFile::open_include(&path, tmp_dir, files.len())?;
+        files.push(include);
     }
 
-    Err(io::Error::new(
-        io::ErrorKind::NotFound,
-        "cannot find text editor",
-    ))
+    Ok(start..files.len())
 }
 
-fn create_temporary_dir() -> io::Result<PathBuf> {
-    let template = cstr!("/tmp/sudoers-XXXXXX").to_owned();
+/// Ask for confirmation before an included file is edited; like ogvisudo, any answer (and the
+/// absence of one) counts as a yes.
+fn prompt_for_edit(path: &Path) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "press return to edit {}: ", path.display())?;
+    stdout.flush()?;
 
-    let ptr = unsafe { libc::mkdtemp(template.into_raw()) };
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
 
-    if ptr.is_null() {
-        return Err(io::Error::last_os_error());
+    Ok(())
+}
+
+/// Look for semantic problems; unless there are syntax errors, as the lines that could not be
+/// parsed would lead to spurious findings
+fn lint_valid(sudoers: &Sudoers, errors: &[crate::sudoers::Error]) -> Lint {
+    if errors.is_empty() {
+        sudoers.lint()
+    } else {
+        Lint::default()
+    }
+}
+
+/// Prefix the message of a finding with the file and line it is about
+fn located(error: &crate::sudoers::Error) -> String {
+    match error {
+        crate::sudoers::Error {
+            source: Some(path),
+            location: Some(Range {
+                start: (line, col), ..
+            }),
+            message,
+        } => format!("{}:{line}:{col}: {message}", path.display()),
+        crate::sudoers::Error { message, .. } => message.clone(),
     }
+}
+
+/// Check the edited copies of `files` as a whole. Included files that are not being edited are
+/// read from disk; those are also returned, in the order in which they are included.
+fn check_edited_files(
+    files: &[SudoersFile],
+) -> io::Result<(Vec<crate::sudoers::Error>, Lint, Vec<PathBuf>)> {
+    let sudoers = &files[0];
+    let mut new_includes = Vec::new();
+
+    let mut read_include = |path: &Path| {
+        if let Some(edited) = files.iter().find(|file| file.path == path) {
+            std::fs::read(&edited.tmp_path)
+        } else {
+            let contents = Sudoers::read_include(path)?;
+            if !new_includes.iter().any(|include| include == path) {
+                new_includes.push(path.to_path_buf());
+            }
+            Ok(contents)
+        }
+    };
+
+    let (edited, errors) = File::open(&sudoers.tmp_path)
+        .and_then(|reader| Sudoers::read_with_includes(reader, &sudoers.path, &mut read_include))
+        .map_err(|err| {
+            io_msg!(
+                err,
+                "unable to re-open temporary file ({}), {} unchanged",
+                sudoers.tmp_path.display(),
+                sudoers.path.display()
+            )
+        })?;
 
-    let path = OsString::from_vec(unsafe { CString::from_raw(ptr) }.into_bytes()).into();
+    let lint = lint_valid(&edited, &errors);
 
-    Ok(path)
+    Ok((errors, lint, new_includes))
 }
@@ -1,18 +1,24 @@
 mod child_process;
 mod cli;
 mod env_reset;
+mod flag_askpass;
+mod flag_background;
 mod flag_chdir;
+mod flag_close_from;
 mod flag_group;
 mod flag_help;
 mod flag_list;
 mod flag_login;
 mod flag_non_interactive;
+mod flag_preserve_env;
+mod flag_prompt;
 mod flag_shell;
 mod flag_user;
 mod flag_version;
 mod lecture;
 mod lecture_file;
 mod limits;
+mod mail;
 mod misc;
 mod nopasswd;
 mod pam;
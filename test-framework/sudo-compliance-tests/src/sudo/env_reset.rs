@@ -239,3 +239,67 @@ fn vars_whose_values_start_with_parentheses_are_removed() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn without_env_reset_vars_are_preserved() -> Result<()> {
+    let env = Env([SUDOERS_ROOT_ALL_NOPASSWD, "Defaults !env_reset"]).build()?;
+
+    let stdout = Command::new("env")
+        .args(["SHOULD_BE_PRESERVED=42", "sudo", "env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some("42"), sudo_env.get("SHOULD_BE_PRESERVED").copied());
+    assert_eq!(Some("root"), sudo_env.get("SUDO_USER").copied());
+
+    Ok(())
+}
+
+#[test]
+fn without_env_reset_env_delete_vars_are_removed() -> Result<()> {
+    let env = Env([
+        SUDOERS_ROOT_ALL_NOPASSWD,
+        "Defaults !env_reset",
+        "Defaults env_delete += SHOULD_BE_REMOVED",
+    ])
+    .build()?;
+
+    let stdout = Command::new("env")
+        .args([
+            "SHOULD_BE_REMOVED=1",
+            "SHOULD_BE_PRESERVED=42",
+            "sudo",
+            "env",
+        ])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert!(!sudo_env.contains_key("SHOULD_BE_REMOVED"));
+    assert_eq!(Some("42"), sudo_env.get("SHOULD_BE_PRESERVED").copied());
+
+    Ok(())
+}
+
+#[test]
+fn always_set_home_overrides_preserved_home() -> Result<()> {
+    let env = Env([
+        SUDOERS_ROOT_ALL_NOPASSWD,
+        "Defaults !env_reset",
+        "Defaults always_set_home",
+    ])
+    .user(USERNAME)
+    .build()?;
+
+    let stdout = Command::new("env")
+        .args(["HOME=/tmp", "sudo", "-u", USERNAME, "env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    let home = format!("/home/{USERNAME}");
+    assert_eq!(Some(home.as_str()), sudo_env.get("HOME").copied());
+
+    Ok(())
+}
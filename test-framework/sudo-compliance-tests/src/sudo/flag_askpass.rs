@@ -0,0 +1,138 @@
+use sudo_test::{Command, Env, TextFile, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const ASKPASS_PATH: &str = "/tmp/askpass";
+const SUDOERS: &str = "ALL ALL=(ALL:ALL) ALL";
+
+fn askpass(script: &str) -> TextFile {
+    TextFile(format!("#!/bin/sh\n{script}")).chmod("755")
+}
+
+#[test]
+fn password_is_read_from_askpass_program() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .file(ASKPASS_PATH, askpass(&format!("echo {PASSWORD}")))
+        .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("SUDO_ASKPASS={ASKPASS_PATH} sudo -A true"))
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    output.assert_success()
+}
+
+#[test]
+fn askpass_program_runs_as_invoking_user() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .file(
+            ASKPASS_PATH,
+            askpass(&format!("whoami > /tmp/askpass-user\necho {PASSWORD}")),
+        )
+        .build()?;
+
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("SUDO_ASKPASS={ASKPASS_PATH} sudo -A true"))
+        .as_user(USERNAME)
+        .output(&env)?
+        .assert_success()?;
+
+    let user = Command::new("cat")
+        .arg("/tmp/askpass-user")
+        .output(&env)?
+        .stdout()?;
+    assert_eq!(USERNAME, user);
+
+    Ok(())
+}
+
+#[test]
+fn askpass_program_does_not_inherit_file_descriptors() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .file(
+            ASKPASS_PATH,
+            askpass(&format!(
+                "[ -e /proc/$$/fd/7 ] && echo open > /tmp/askpass-fd || echo closed > /tmp/askpass-fd\necho {PASSWORD}"
+            )),
+        )
+        .build()?;
+
+    // sudo itself is started with file descriptor 7 open
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "exec 7</etc/hostname; SUDO_ASKPASS={ASKPASS_PATH} sudo -A true"
+        ))
+        .as_user(USERNAME)
+        .output(&env)?
+        .assert_success()?;
+
+    let fd = Command::new("cat")
+        .arg("/tmp/askpass-fd")
+        .output(&env)?
+        .stdout()?;
+    assert_eq!("closed", fd);
+
+    Ok(())
+}
+
+#[test]
+fn wrong_password_from_askpass_program() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .file(ASKPASS_PATH, askpass("echo incorrect-password"))
+        .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("SUDO_ASKPASS={ASKPASS_PATH} sudo -A true"))
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    assert!(!output.status().success());
+    assert_contains!(output.stderr(), "3 incorrect");
+
+    Ok(())
+}
+
+#[test]
+fn askpass_from_sudo_conf() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .file(ASKPASS_PATH, askpass(&format!("echo {PASSWORD}")))
+        .file("/etc/sudo.conf", format!("Path askpass {ASKPASS_PATH}"))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-A", "true"])
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    output.assert_success()
+}
+
+#[test]
+fn no_askpass_program_specified() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-A", "true"])
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    assert!(!output.status().success());
+    assert_contains!(
+        output.stderr(),
+        "no askpass program specified, try setting SUDO_ASKPASS"
+    );
+
+    Ok(())
+}
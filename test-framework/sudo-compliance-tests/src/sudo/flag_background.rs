@@ -0,0 +1,40 @@
+use crate::{Result, SUDOERS_ALL_ALL_NOPASSWD};
+use sudo_test::{Command, Env, TextFile};
+
+#[test]
+fn returns_before_the_command_finishes() -> Result<()> {
+    let env = Env(TextFile(SUDOERS_ALL_ALL_NOPASSWD)).build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("sudo -b sh -c 'sleep 2; touch /tmp/done'; [ ! -f /tmp/done ] && sleep 4 && cat /tmp/done")
+        .output(&env)?;
+
+    output.assert_success()
+}
+
+#[test]
+fn stdin_is_not_inherited() -> Result<()> {
+    let env = Env(TextFile(SUDOERS_ALL_ALL_NOPASSWD)).build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("echo secret | sudo -b sh -c 'cat > /tmp/stdin'; sleep 2; cat /tmp/stdin")
+        .output(&env)?;
+
+    output.assert_success()?;
+    assert_eq!("", output.stdout()?);
+
+    Ok(())
+}
+
+#[test]
+fn cannot_be_combined_with_list() -> Result<()> {
+    let env = Env(TextFile(SUDOERS_ALL_ALL_NOPASSWD)).build()?;
+
+    let output = Command::new("sudo").args(["-b", "-l"]).output(&env)?;
+
+    assert!(!output.status().success());
+
+    Ok(())
+}
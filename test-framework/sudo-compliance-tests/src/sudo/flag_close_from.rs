@@ -0,0 +1,64 @@
+use crate::{Result, SUDOERS_ALL_ALL_NOPASSWD};
+use sudo_test::{Command, Env, TextFile};
+
+const LIST_FDS: &str = "sudo -C 5 sh -c 'ls /proc/$$/fd' 3</dev/null 4</dev/null 6</dev/null";
+
+#[test]
+fn not_allowed_without_closefrom_override() -> Result<()> {
+    let env = Env(TextFile(SUDOERS_ALL_ALL_NOPASSWD)).build()?;
+
+    let output = Command::new("sh").arg("-c").arg(LIST_FDS).output(&env)?;
+
+    assert!(!output.status().success());
+    assert_contains!(
+        output.stderr(),
+        "you are not permitted to use the -C option"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn keeps_file_descriptors_below_the_limit() -> Result<()> {
+    let env = Env(["Defaults closefrom_override", SUDOERS_ALL_ALL_NOPASSWD]).build()?;
+
+    let output = Command::new("sh").arg("-c").arg(LIST_FDS).output(&env)?;
+
+    let fds = output.stdout()?;
+    let fds = fds.lines().collect::<Vec<_>>();
+    assert!(fds.contains(&"3"));
+    assert!(fds.contains(&"4"));
+    assert!(!fds.contains(&"6"));
+
+    Ok(())
+}
+
+#[test]
+fn stray_file_descriptors_are_closed_by_default() -> Result<()> {
+    let env = Env(TextFile(SUDOERS_ALL_ALL_NOPASSWD)).build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("sudo sh -c 'ls /proc/$$/fd' 3</dev/null 4</dev/null")
+        .output(&env)?;
+
+    let fds = output.stdout()?;
+    let fds = fds.lines().collect::<Vec<_>>();
+    assert!(!fds.contains(&"3"));
+    assert!(!fds.contains(&"4"));
+
+    Ok(())
+}
+
+#[test]
+fn argument_must_be_at_least_three() -> Result<()> {
+    let env = Env(["Defaults closefrom_override", SUDOERS_ALL_ALL_NOPASSWD]).build()?;
+
+    let output = Command::new("sudo")
+        .args(["-C", "2", "true"])
+        .output(&env)?;
+
+    assert!(!output.status().success());
+
+    Ok(())
+}
@@ -91,7 +91,7 @@ fn flag_reset_timestamp() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "sudo: a password is required"
     } else {
-        "sudo: Authentication failed"
+        "sudo: a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -0,0 +1,216 @@
+use sudo_test::{Command, Env};
+
+use crate::{helpers, Result, SUDOERS_ROOT_ALL_NOPASSWD};
+
+const SUDOERS_ROOT_ENV_NOPASSWD: &str = "root ALL=(ALL:ALL) NOPASSWD: /usr/bin/env";
+
+#[test]
+fn preserves_everything_if_allowed() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ALL_NOPASSWD).build()?;
+
+    let stdout = Command::new("sh")
+        .args(["-c", "export AVOCADO=1; sudo -E env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+
+    Ok(())
+}
+
+#[test]
+fn long_form_preserves_everything() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ALL_NOPASSWD).build()?;
+
+    let stdout = Command::new("sh")
+        .args(["-c", "export AVOCADO=1; sudo --preserve-env env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+
+    Ok(())
+}
+
+#[test]
+fn env_delete_is_respected() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ALL_NOPASSWD).build()?;
+
+    let stdout = Command::new("sh")
+        .args(["-c", "export PYTHONPATH=/tmp; sudo -E env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert!(!sudo_env.contains_key("PYTHONPATH"));
+
+    Ok(())
+}
+
+#[test]
+fn target_user_identity_is_set() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ALL_NOPASSWD).build()?;
+
+    let stdout = Command::new("sh")
+        .args(["-c", "export USER=ghost LOGNAME=ghost; sudo -E env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some(&"root"), sudo_env.get("USER"));
+    assert_eq!(Some(&"root"), sudo_env.get("LOGNAME"));
+
+    Ok(())
+}
+
+#[test]
+fn requires_setenv() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ENV_NOPASSWD).build()?;
+
+    let output = Command::new("sudo").args(["-E", "env"]).output(&env)?;
+
+    assert!(!output.status().success());
+    assert_eq!(Some(1), output.status().code());
+    assert_contains!(
+        output.stderr(),
+        "sorry, you are not allowed to preserve the environment"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn setenv_tag_allows_it() -> Result<()> {
+    let env = Env("root ALL=(ALL:ALL) NOPASSWD: SETENV: /usr/bin/env").build()?;
+
+    let stdout = Command::new("sh")
+        .args(["-c", "export AVOCADO=1; sudo -E env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+
+    Ok(())
+}
+
+#[test]
+fn nosetenv_tag_overrides_all() -> Result<()> {
+    let env = Env("root ALL=(ALL:ALL) NOPASSWD: NOSETENV: ALL").build()?;
+
+    let output = Command::new("sudo").args(["-E", "env"]).output(&env)?;
+
+    assert!(!output.status().success());
+    assert_contains!(
+        output.stderr(),
+        "sorry, you are not allowed to preserve the environment"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn list_only_preserves_listed_vars() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ALL_NOPASSWD).build()?;
+
+    let stdout = Command::new("sh")
+        .args([
+            "-c",
+            "export AVOCADO=1 BANANA=2 CHERRY=3; sudo --preserve-env=AVOCADO,BANANA env",
+        ])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+    assert_eq!(Some(&"2"), sudo_env.get("BANANA"));
+    assert!(!sudo_env.contains_key("CHERRY"));
+
+    Ok(())
+}
+
+#[test]
+fn list_is_checked_against_env_keep() -> Result<()> {
+    let env = Env([SUDOERS_ROOT_ENV_NOPASSWD, "Defaults env_keep += AVOCADO"]).build()?;
+
+    let stdout = Command::new("sh")
+        .args(["-c", "export AVOCADO=1; sudo --preserve-env=AVOCADO env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+
+    let output = Command::new("sh")
+        .args(["-c", "export BANANA=2; sudo --preserve-env=BANANA env"])
+        .output(&env)?;
+    assert!(!output.status().success());
+    assert_contains!(
+        output.stderr(),
+        "sorry, you are not allowed to set the following environment variables: BANANA"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn var_assignment_is_passed_to_command() -> Result<()> {
+    let env = Env(SUDOERS_ROOT_ALL_NOPASSWD).build()?;
+
+    let stdout = Command::new("sudo")
+        .args(["AVOCADO=1", "BANANA=two words", "env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+    assert_eq!(Some(&"two words"), sudo_env.get("BANANA"));
+
+    Ok(())
+}
+
+#[test]
+fn var_assignment_requires_setenv_or_env_keep() -> Result<()> {
+    let env = Env([SUDOERS_ROOT_ENV_NOPASSWD, "Defaults env_keep += AVOCADO"]).build()?;
+
+    let stdout = Command::new("sudo")
+        .args(["AVOCADO=1", "env"])
+        .output(&env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+    assert_eq!(Some(&"1"), sudo_env.get("AVOCADO"));
+
+    let output = Command::new("sudo")
+        .args(["BANANA=2", "CHERRY=3", "env"])
+        .output(&env)?;
+    assert!(!output.status().success());
+    assert_eq!(Some(1), output.status().code());
+    assert_contains!(
+        output.stderr(),
+        "sorry, you are not allowed to set the following environment variables: BANANA CHERRY"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn var_assignment_cannot_override_secure_path() -> Result<()> {
+    let env = Env([
+        SUDOERS_ROOT_ENV_NOPASSWD,
+        "Defaults secure_path=/usr/bin:/bin",
+    ])
+    .build()?;
+
+    let output = Command::new("sudo")
+        .args(["PATH=/tmp", "env"])
+        .output(&env)?;
+
+    assert!(!output.status().success());
+    assert_contains!(
+        output.stderr(),
+        "sorry, you are not allowed to set the following environment variables: PATH"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const SUDOERS: &str = "ALL ALL=(ALL:ALL) ALL";
+
+fn prompt_shown(env: &Env, args: &[&str]) -> Result<String> {
+    let output = Command::new("sudo")
+        .arg("-S")
+        .args(args)
+        .arg("true")
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(env)?;
+
+    output.assert_success()?;
+    Ok(output.stderr().to_string())
+}
+
+#[test]
+fn replaces_password_prompt() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let stderr = prompt_shown(&env, &["-p", "secret please: "])?;
+    assert_eq!("secret please: ", stderr);
+
+    Ok(())
+}
+
+#[test]
+fn expands_escapes() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .hostname("container")
+        .build()?;
+
+    let stderr = prompt_shown(&env, &["-u", "root", "-p", "%p@%h (%U, 100%%): "])?;
+    assert_eq!(format!("{USERNAME}@container (root, 100%): "), stderr);
+
+    Ok(())
+}
+
+#[test]
+fn takes_precedence_over_passprompt() -> Result<()> {
+    let env = Env(["Defaults passprompt=\"from sudoers: \"", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let stderr = prompt_shown(&env, &["--prompt=from cli: "])?;
+    assert_eq!("from cli: ", stderr);
+
+    Ok(())
+}
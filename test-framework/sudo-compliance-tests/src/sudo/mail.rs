@@ -0,0 +1,186 @@
+//! Test the `mail_*` settings, using a stub mailer that records the mail it is given
+
+use sudo_test::{Command, Env, EnvBuilder, TextFile, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const MAILER_PATH: &str = "/usr/local/bin/stub-mailer";
+const MAIL_PATH: &str = "/tmp/mail";
+const MAILER_DEFAULTS: &str = "Defaults mailerpath=\"/usr/local/bin/stub-mailer\"";
+
+fn env_with_mailer(sudoers: impl Into<TextFile>) -> EnvBuilder {
+    let mut builder = Env(sudoers);
+    builder
+        .file(
+            MAILER_PATH,
+            TextFile(format!(
+                "#!/bin/sh\necho \"$@\" > {MAIL_PATH}\ncat >> {MAIL_PATH}"
+            ))
+            .chmod("755"),
+        )
+        .user(User(USERNAME).password(PASSWORD));
+    builder
+}
+
+/// the original sudo does not wait for the mailer, so give it some time to finish
+fn sent_mail(env: &Env) -> Result<Option<String>> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "for i in 1 2 3; do [ -f {MAIL_PATH} ] && break; sleep 1; done; cat {MAIL_PATH}"
+        ))
+        .output(env)?;
+
+    Ok(if output.status().success() {
+        Some(output.stdout()?)
+    } else {
+        None
+    })
+}
+
+#[test]
+fn mails_on_authentication_failure_with_mail_badpass() -> Result<()> {
+    let env = env_with_mailer([
+        MAILER_DEFAULTS,
+        "Defaults mail_badpass",
+        &format!("{USERNAME} ALL=(ALL:ALL) ALL"),
+    ])
+    .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("(for i in $(seq 1 3); do echo wrong-password; done) | sudo -S true")
+        .as_user(USERNAME)
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    let mail = sent_mail(&env)?.expect("no mail was sent");
+    assert!(mail.starts_with("-t"));
+    assert_contains!(mail, "To: root");
+    assert_contains!(mail, "SECURITY information for");
+    assert_contains!(mail, USERNAME);
+
+    Ok(())
+}
+
+#[test]
+fn no_mail_on_authentication_failure_by_default() -> Result<()> {
+    let env =
+        env_with_mailer([MAILER_DEFAULTS, &format!("{USERNAME} ALL=(ALL:ALL) ALL")]).build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("(for i in $(seq 1 3); do echo wrong-password; done) | sudo -S true")
+        .as_user(USERNAME)
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    assert_eq!(None, sent_mail(&env)?);
+
+    Ok(())
+}
+
+#[test]
+fn mail_badpass_for_the_invoking_user_applies_to_validate() -> Result<()> {
+    let env = env_with_mailer([
+        MAILER_DEFAULTS,
+        &format!("Defaults:{USERNAME} mail_badpass"),
+        &format!("{USERNAME} ALL=(ALL:ALL) ALL"),
+    ])
+    .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("(for i in $(seq 1 3); do echo wrong-password; done) | sudo -S -v")
+        .as_user(USERNAME)
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    let mail = sent_mail(&env)?.expect("no mail was sent");
+    assert_contains!(mail, "SECURITY information for");
+
+    Ok(())
+}
+
+#[test]
+fn mails_when_user_is_not_in_sudoers_on_validate() -> Result<()> {
+    let env = env_with_mailer([MAILER_DEFAULTS]).build()?;
+
+    let output = Command::new("sudo")
+        .args(["-S", "-v"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    let mail = sent_mail(&env)?.expect("no mail was sent");
+    assert_contains!(mail, "user NOT in sudoers");
+    assert_contains!(mail, USERNAME);
+
+    Ok(())
+}
+
+#[test]
+fn mails_when_user_is_not_in_sudoers() -> Result<()> {
+    let env =
+        env_with_mailer([MAILER_DEFAULTS, "Defaults mailto=\"admin@example.org\""]).build()?;
+
+    let output = Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    let mail = sent_mail(&env)?.expect("no mail was sent");
+    assert_contains!(mail, "To: admin@example.org");
+    assert_contains!(mail, "user NOT in sudoers");
+
+    Ok(())
+}
+
+#[test]
+fn mails_on_denied_command_with_mail_no_perms() -> Result<()> {
+    let rule = format!("{USERNAME} ALL=(ALL:ALL) /usr/bin/true");
+
+    let env = env_with_mailer([MAILER_DEFAULTS, &rule]).build()?;
+    let output = Command::new("sudo")
+        .args(["-S", "ls"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+    assert!(!output.status().success());
+    assert_eq!(None, sent_mail(&env)?);
+
+    let env = env_with_mailer([MAILER_DEFAULTS, "Defaults mail_no_perms", &rule]).build()?;
+    let output = Command::new("sudo")
+        .args(["-S", "ls"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    let mail = sent_mail(&env)?.expect("no mail was sent");
+    assert_contains!(mail, "command not allowed");
+    assert_contains!(mail, "ls");
+
+    Ok(())
+}
+
+#[test]
+fn missing_mailer_does_not_change_the_outcome() -> Result<()> {
+    let env = Env([
+        "Defaults mailerpath=\"/does/not/exist\"".to_string(),
+        format!("{USERNAME} ALL=(ALL:ALL) /usr/bin/true"),
+    ])
+    .user(User(USERNAME).password(PASSWORD))
+    .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+
+    output.assert_success()
+}
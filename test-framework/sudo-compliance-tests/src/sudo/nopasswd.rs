@@ -106,7 +106,6 @@ fn run_sudo_l_flag_without_pwd_if_one_nopasswd_is_set() -> Result<()> {
 }
 
 #[test]
-#[ignore = "gh439"]
 fn run_sudo_v_flag_without_pwd_if_nopasswd_is_set_for_all_users_entries() -> Result<()> {
     let env = Env(format!(
         "{USERNAME}    ALL=(ALL:ALL) NOPASSWD: /bin/true, /bin/ls"
@@ -122,7 +121,6 @@ fn run_sudo_v_flag_without_pwd_if_nopasswd_is_set_for_all_users_entries() -> Res
 }
 
 #[test]
-#[ignore = "gh439"]
 fn v_flag_without_pwd_fails_if_nopasswd_is_not_set_for_all_users_entries() -> Result<()> {
     let env = Env([
         "ALL ALL=(ALL:ALL) NOPASSWD: /bin/true, PASSWD: /bin/ls",
@@ -145,9 +143,10 @@ fn v_flag_without_pwd_fails_if_nopasswd_is_not_set_for_all_users_entries() -> Re
             format!("[sudo] password for {USERNAME}: \nsudo: no password was provided\nsudo: a password is required")
         );
     } else {
+        // no password is read from the empty stdin, so every attempt fails
         assert_contains!(
             stderr,
-            "[Sudo: authenticate] Password: sudo: Authentication failed, try again.\n[Sudo: authenticate] Password: sudo: Authentication failed, try again.\n[Sudo: authenticate] Password: sudo-rs: Maximum 3 incorrect authentication attempts"
+            "[sudo: authenticate] Password: sudo: Authentication failed, try again.\n[sudo: authenticate] Password: sudo: Authentication failed, try again.\n[sudo: authenticate] Password: sudo-rs: Maximum 3 incorrect authentication attempts"
         );
     }
 
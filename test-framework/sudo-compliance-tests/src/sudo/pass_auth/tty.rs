@@ -56,12 +56,10 @@ fn no_tty() -> Result<()> {
         .output(&env)?;
     assert_eq!(Some(1), output.status().code());
 
-    let diagnostic = if sudo_test::is_original_sudo() {
+    assert_contains!(
+        output.stderr(),
         "a terminal is required to read the password"
-    } else {
-        "Maximum 3 incorrect authentication attempts"
-    };
-    assert_contains!(output.stderr(), diagnostic);
+    );
 
     Ok(())
 }
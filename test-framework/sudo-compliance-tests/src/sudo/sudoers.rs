@@ -6,17 +6,27 @@ use crate::{Result, PASSWORD, SUDOERS_ROOT_ALL_NOPASSWD, USERNAME};
 
 mod cmnd;
 mod cmnd_alias;
+mod cmnd_digest;
 mod cwd;
+mod defaults_scope;
 mod env;
 mod host_alias;
 mod host_list;
 mod include;
 mod includedir;
+mod listpw;
+mod noexec;
+mod nonunix_group;
+mod passprompt;
+mod passwd_timeout;
 mod run_as;
 mod runas_alias;
 mod secure_path;
 mod timestamp_timeout;
+mod timestamp_type;
 mod user_list;
+mod verifypw;
+mod visiblepw;
 
 const KEYWORDS: &[&str] = &[
     "ALL",
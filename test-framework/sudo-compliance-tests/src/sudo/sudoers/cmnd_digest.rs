@@ -0,0 +1,92 @@
+//! Test digest specifications in the Cmnd_Spec component: <user> ALL=(ALL:ALL) sha256:<digest> <cmnd>
+
+use sudo_test::{Command, Env};
+
+use crate::Result;
+
+/// Replace the sudoers file by `rule`, in which "$DIGEST" is the sha256 digest of `binary`
+fn with_digest_of(binary: &str, rule: &str, env: &Env) -> Result<()> {
+    Command::new("sh")
+        .args([
+            "-c",
+            &format!(
+                "DIGEST=$(sha256sum {binary} | cut -d' ' -f1); echo \"{rule}\" > /etc/sudoers"
+            ),
+        ])
+        .output(env)?
+        .assert_success()
+}
+
+#[test]
+fn matching_digest_allows_command() -> Result<()> {
+    let env = Env("").build()?;
+    with_digest_of(
+        "/usr/bin/true",
+        "root ALL=(ALL:ALL) NOPASSWD: sha256:$DIGEST /usr/bin/true",
+        &env,
+    )?;
+
+    Command::new("sudo")
+        .arg("/usr/bin/true")
+        .output(&env)?
+        .assert_success()
+}
+
+#[test]
+fn mismatching_digest_forbids_command() -> Result<()> {
+    let env = Env("").build()?;
+    with_digest_of(
+        "/usr/bin/false",
+        "root ALL=(ALL:ALL) NOPASSWD: sha256:$DIGEST /usr/bin/true",
+        &env,
+    )?;
+
+    let output = Command::new("sudo").arg("/usr/bin/true").output(&env)?;
+
+    assert!(!output.status().success());
+    assert_eq!(Some(1), output.status().code());
+    if !sudo_test::is_original_sudo() {
+        assert_contains!(
+            output.stderr(),
+            "authentication failed: I'm sorry root. I'm afraid I can't do that"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn digest_applies_to_replaced_binary() -> Result<()> {
+    let env = Env("").build()?;
+    with_digest_of(
+        "/usr/bin/true",
+        "root ALL=(ALL:ALL) NOPASSWD: sha256:$DIGEST /usr/bin/true",
+        &env,
+    )?;
+
+    Command::new("cp")
+        .args(["/usr/bin/false", "/usr/bin/true"])
+        .output(&env)?
+        .assert_success()?;
+
+    let output = Command::new("sudo").arg("/usr/bin/true").output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+
+    Ok(())
+}
+
+#[test]
+fn digest_in_cmnd_alias() -> Result<()> {
+    let env = Env("").build()?;
+    with_digest_of(
+        "/usr/bin/true",
+        "Cmnd_Alias TRUE = sha256:$DIGEST /usr/bin/true\nroot ALL=(ALL:ALL) NOPASSWD: TRUE",
+        &env,
+    )?;
+
+    Command::new("sudo")
+        .arg("/usr/bin/true")
+        .output(&env)?
+        .assert_success()
+}
@@ -0,0 +1,170 @@
+//! Test `Defaults` that only apply to certain users, hosts, runas users and commands
+
+use sudo_test::{Command, Env, User};
+
+use crate::{helpers, Result, SUDOERS_ALL_ALL_NOPASSWD, USERNAME};
+
+const ENV_NAME: &str = "SHOULD_BE_PRESERVED";
+const ENV_VAL: &str = "42";
+
+fn preserved_var(env: &Env, user: &str, runas: &str) -> Result<Option<String>> {
+    let stdout = Command::new("env")
+        .arg(format!("{ENV_NAME}={ENV_VAL}"))
+        .args(["sudo", "-u", runas, "env"])
+        .as_user(user)
+        .output(env)?
+        .stdout()?;
+    let sudo_env = helpers::parse_env_output(&stdout)?;
+
+    Ok(sudo_env.get(ENV_NAME).map(|val| val.to_string()))
+}
+
+#[test]
+fn user_scope_applies_to_matching_user() -> Result<()> {
+    let env = Env([
+        SUDOERS_ALL_ALL_NOPASSWD,
+        &format!("Defaults:{USERNAME} env_keep += {ENV_NAME}"),
+    ])
+    .user(USERNAME)
+    .build()?;
+
+    assert_eq!(
+        Some(ENV_VAL),
+        preserved_var(&env, USERNAME, "root")?.as_deref()
+    );
+    assert_eq!(None, preserved_var(&env, "root", "root")?);
+
+    Ok(())
+}
+
+#[test]
+fn user_scope_applies_to_group_members() -> Result<()> {
+    let env = Env([
+        SUDOERS_ALL_ALL_NOPASSWD,
+        &format!("Defaults:%users env_keep += {ENV_NAME}"),
+    ])
+    .user(User(USERNAME).secondary_group("users"))
+    .build()?;
+
+    assert_eq!(
+        Some(ENV_VAL),
+        preserved_var(&env, USERNAME, "root")?.as_deref()
+    );
+    assert_eq!(None, preserved_var(&env, "root", "root")?);
+
+    Ok(())
+}
+
+#[test]
+fn host_scope_applies_to_matching_host() -> Result<()> {
+    let env = Env([
+        SUDOERS_ALL_ALL_NOPASSWD,
+        &format!("Defaults@container env_keep += {ENV_NAME}"),
+    ])
+    .hostname("container")
+    .build()?;
+
+    assert_eq!(
+        Some(ENV_VAL),
+        preserved_var(&env, "root", "root")?.as_deref()
+    );
+
+    let env = Env([
+        SUDOERS_ALL_ALL_NOPASSWD,
+        &format!("Defaults@remotehost env_keep += {ENV_NAME}"),
+    ])
+    .hostname("container")
+    .build()?;
+
+    assert_eq!(None, preserved_var(&env, "root", "root")?);
+
+    Ok(())
+}
+
+#[test]
+fn runas_scope_applies_to_matching_target_user() -> Result<()> {
+    let env = Env([
+        SUDOERS_ALL_ALL_NOPASSWD,
+        &format!("Defaults>{USERNAME} env_keep += {ENV_NAME}"),
+    ])
+    .user(USERNAME)
+    .build()?;
+
+    assert_eq!(
+        Some(ENV_VAL),
+        preserved_var(&env, "root", USERNAME)?.as_deref()
+    );
+    assert_eq!(None, preserved_var(&env, "root", "root")?);
+
+    Ok(())
+}
+
+#[test]
+fn command_scope_applies_to_matching_command() -> Result<()> {
+    let env = Env([
+        "Defaults!/usr/bin/sh noexec",
+        "root ALL=(ALL:ALL) NOPASSWD: ALL",
+    ])
+    .build()?;
+
+    let output = Command::new("sudo")
+        .args(["sh", "-c", "/usr/bin/true; echo $?"])
+        .output(&env)?;
+
+    assert_contains!(output.stderr(), "Permission denied");
+    assert_eq!(output.stdout()?, "126");
+
+    let output = Command::new("sudo")
+        .args(["bash", "-c", "/usr/bin/true; echo $?"])
+        .output(&env)?;
+
+    assert_eq!(output.stdout()?, "0");
+
+    Ok(())
+}
+
+#[test]
+fn scoped_settings_override_generic_ones() -> Result<()> {
+    let env = Env([
+        SUDOERS_ALL_ALL_NOPASSWD,
+        &format!("Defaults:root env_keep -= {ENV_NAME}"),
+        &format!("Defaults env_keep += {ENV_NAME}"),
+    ])
+    .build()?;
+
+    assert_eq!(None, preserved_var(&env, "root", "root")?);
+
+    Ok(())
+}
+
+#[test]
+fn space_before_scope_is_a_syntax_error() -> Result<()> {
+    let env = Env([SUDOERS_ALL_ALL_NOPASSWD, "Defaults :root env_keep += FOO"]).build()?;
+
+    let output = Command::new("sudo").arg("true").output(&env)?;
+
+    let diagnostic = if sudo_test::is_original_sudo() {
+        "syntax error"
+    } else {
+        "expected parameter"
+    };
+    assert_contains!(output.stderr(), diagnostic);
+
+    Ok(())
+}
+
+#[test]
+fn command_scope_does_not_accept_arguments() -> Result<()> {
+    let env = Env([SUDOERS_ALL_ALL_NOPASSWD, "Defaults!/usr/bin/sh -c noexec"]).build()?;
+
+    let output = Command::new("sudo").arg("true").output(&env)?;
+
+    let diagnostic = if sudo_test::is_original_sudo() {
+        "syntax error"
+    } else {
+        "expected parameter"
+    };
+    assert_contains!(output.stderr(), diagnostic);
+
+    Ok(())
+}
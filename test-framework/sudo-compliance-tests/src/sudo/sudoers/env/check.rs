@@ -69,7 +69,6 @@ fn if_value_starts_with_parentheses_variable_is_removed() -> Result<()> {
 }
 
 #[test]
-#[ignore = "gh344"]
 fn key_value_matches() -> Result<()> {
     super::key_value_matches(ENV_LIST)
 }
@@ -80,13 +79,11 @@ fn key_value_no_match() -> Result<()> {
 }
 
 #[test]
-#[ignore = "gh345"]
 fn key_value_syntax_needs_double_quotes() -> Result<()> {
     super::key_value_syntax_needs_double_quotes(ENV_LIST)
 }
 
 #[test]
-#[ignore = "gh346"]
 fn key_value_where_value_is_parentheses_glob() -> Result<()> {
     super::key_value_where_value_is_parentheses_glob(ENV_LIST)
 }
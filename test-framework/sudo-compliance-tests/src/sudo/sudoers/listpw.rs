@@ -0,0 +1,55 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const MIXED_RULES: &str = "ferris ALL=(ALL:ALL) NOPASSWD: /usr/bin/true, PASSWD: /usr/bin/ls";
+
+fn list_without_password(sudoers: &[&str]) -> Result<bool> {
+    let env = Env(sudoers.join("\n"))
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-n", "-l"])
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    Ok(output.status().success())
+}
+
+#[test]
+fn default_accepts_a_single_nopasswd_entry() -> Result<()> {
+    assert!(list_without_password(&[MIXED_RULES])?);
+
+    Ok(())
+}
+
+#[test]
+fn all_requires_every_entry_to_be_nopasswd() -> Result<()> {
+    assert!(!list_without_password(&[
+        MIXED_RULES,
+        "Defaults listpw=all"
+    ])?);
+
+    Ok(())
+}
+
+#[test]
+fn always_requires_password() -> Result<()> {
+    assert!(!list_without_password(&[
+        &format!("{USERNAME} ALL=(ALL:ALL) NOPASSWD: ALL"),
+        "Defaults listpw=always"
+    ])?);
+
+    Ok(())
+}
+
+#[test]
+fn never_requires_password() -> Result<()> {
+    assert!(list_without_password(&[
+        &format!("{USERNAME} ALL=(ALL:ALL) ALL"),
+        "Defaults listpw=never"
+    ])?);
+
+    Ok(())
+}
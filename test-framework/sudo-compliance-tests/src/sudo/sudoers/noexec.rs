@@ -0,0 +1,54 @@
+//! Test the NOEXEC tag and the `noexec` setting
+
+use sudo_test::{Command, Env};
+
+use crate::Result;
+
+#[test]
+fn noexec_tag_prevents_executing_other_commands() -> Result<()> {
+    let env = Env("root ALL=(ALL:ALL) NOPASSWD: NOEXEC: /usr/bin/sh").build()?;
+
+    let output = Command::new("sudo")
+        .args(["sh", "-c", "/usr/bin/true; echo $?"])
+        .output(&env)?;
+
+    assert_contains!(output.stderr(), "Permission denied");
+    assert_eq!(output.stdout()?, "126");
+
+    Ok(())
+}
+
+#[test]
+fn noexec_setting_prevents_executing_other_commands() -> Result<()> {
+    let env = Env([
+        "Defaults noexec",
+        "root ALL=(ALL:ALL) NOPASSWD: /usr/bin/sh",
+    ])
+    .build()?;
+
+    let output = Command::new("sudo")
+        .args(["sh", "-c", "/usr/bin/true; echo $?"])
+        .output(&env)?;
+
+    assert_contains!(output.stderr(), "Permission denied");
+    assert_eq!(output.stdout()?, "126");
+
+    Ok(())
+}
+
+#[test]
+fn exec_tag_overrides_noexec_setting() -> Result<()> {
+    let env = Env([
+        "Defaults noexec",
+        "root ALL=(ALL:ALL) NOPASSWD: EXEC: /usr/bin/sh",
+    ])
+    .build()?;
+
+    let output = Command::new("sudo")
+        .args(["sh", "-c", "/usr/bin/true; echo $?"])
+        .output(&env)?;
+
+    assert_eq!(output.stdout()?, "0");
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Test `%:group` entries, which are resolved through the groups in `group_file`
+
+use sudo_test::{Command, Env, TextFile};
+
+use crate::{Result, USERNAME};
+
+const GROUP_FILE: &str = "/etc/sudo-groups";
+
+fn run_true(env: &Env) -> Result<bool> {
+    let output = Command::new("sudo")
+        .args(["-n", "true"])
+        .as_user(USERNAME)
+        .output(env)?;
+
+    assert_not_contains!(output.stderr(), "panicked");
+
+    Ok(output.status().success())
+}
+
+#[test]
+fn nonunix_group_without_provider_does_not_match() -> Result<()> {
+    let env = Env("%:admins ALL=(ALL:ALL) NOPASSWD: ALL")
+        .user(USERNAME)
+        .build()?;
+
+    assert!(!run_true(&env)?);
+
+    Ok(())
+}
+
+#[test]
+fn nonunix_group_is_looked_up_in_group_file() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        // the original sudo uses a `group_plugin` instead
+        return Ok(());
+    }
+
+    let env = Env([
+        &format!("Defaults group_file=\"{GROUP_FILE}\""),
+        "%:admins ALL=(ALL:ALL) NOPASSWD: ALL",
+    ])
+    .file(GROUP_FILE, format!("admins:x:5000:{USERNAME}"))
+    .user(USERNAME)
+    .user("other")
+    .build()?;
+
+    assert!(run_true(&env)?);
+
+    let output = Command::new("sudo")
+        .args(["-n", "true"])
+        .as_user("other")
+        .output(&env)?;
+    assert!(!output.status().success());
+
+    Ok(())
+}
+
+#[test]
+fn unix_group_syntax_uses_group_file_with_always_query_group_plugin() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        // the original sudo uses a `group_plugin` instead
+        return Ok(());
+    }
+
+    let sudoers = [
+        &format!("Defaults group_file=\"{GROUP_FILE}\""),
+        "%admins ALL=(ALL:ALL) NOPASSWD: ALL",
+    ];
+    let group_file = format!("admins:x:5000:{USERNAME}");
+
+    let env = Env(sudoers)
+        .file(GROUP_FILE, group_file.as_str())
+        .user(USERNAME)
+        .build()?;
+    assert!(!run_true(&env)?);
+
+    let env = Env([sudoers[0], sudoers[1], "Defaults always_query_group_plugin"])
+        .file(GROUP_FILE, group_file.as_str())
+        .user(USERNAME)
+        .build()?;
+    assert!(run_true(&env)?);
+
+    Ok(())
+}
+
+#[test]
+fn insecure_group_file_is_not_used() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        return Ok(());
+    }
+
+    let env = Env([
+        &format!("Defaults group_file=\"{GROUP_FILE}\""),
+        "%:admins ALL=(ALL:ALL) NOPASSWD: ALL",
+    ])
+    .file(
+        GROUP_FILE,
+        TextFile(format!("admins:x:5000:{USERNAME}")).chmod("666"),
+    )
+    .user(USERNAME)
+    .build()?;
+
+    assert!(!run_true(&env)?);
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const SUDOERS: &str = "ALL ALL=(ALL:ALL) ALL";
+
+#[test]
+fn passprompt_replaces_password_prompt() -> Result<()> {
+    let env = Env(["Defaults passprompt=\"[sudo] password for %p: \"", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+
+    output.assert_success()?;
+    assert_eq!(format!("[sudo] password for {USERNAME}: "), output.stderr());
+
+    Ok(())
+}
+
+#[test]
+fn passprompt_override_is_accepted() -> Result<()> {
+    let env = Env([
+        "Defaults passprompt=\"%u's secret: \", passprompt_override",
+        SUDOERS,
+    ])
+    .user(User(USERNAME).password(PASSWORD))
+    .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+
+    output.assert_success()?;
+    assert_eq!(format!("{USERNAME}'s secret: "), output.stderr());
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const SUDOERS: &str = "ALL ALL=(ALL:ALL) ALL";
+
+#[test]
+fn prompt_is_abandoned_after_timeout() -> Result<()> {
+    // 0.05 minutes is 3 seconds
+    let env = Env(["Defaults passwd_timeout=0.05", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .arg("true")
+        .as_user(USERNAME)
+        .tty(true)
+        .output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+    // with a TTY, stderr is merged into stdout
+    assert_contains!(output.stdout_unchecked(), "timed out reading password");
+
+    Ok(())
+}
+
+#[test]
+fn timeout_does_not_apply_to_input_that_was_given_in_time() -> Result<()> {
+    let env = Env(["Defaults passwd_timeout=0.05", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?
+        .assert_success()
+}
@@ -49,7 +49,7 @@ Defaults timestamp_timeout=0.1"
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "incorrect authentication attempt"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -79,7 +79,7 @@ Defaults timestamp_timeout=0"
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "incorrect authentication attempt"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -0,0 +1,100 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const SUDOERS: &str = "ALL ALL=(ALL:ALL) ALL";
+
+#[test]
+fn global_is_shared_between_unrelated_processes() -> Result<()> {
+    let env = Env(["Defaults timestamp_type=global", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?
+        .assert_success()?;
+
+    Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .output(&env)?
+        .assert_success()
+}
+
+#[test]
+fn ppid_is_not_shared_between_sibling_shells() -> Result<()> {
+    let env = Env(["Defaults timestamp_type=ppid", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "sh -c 'echo {PASSWORD} | sudo -S true' || exit 2; sh -c 'sudo -S true < /dev/null'"
+        ))
+        .as_user(USERNAME)
+        .tty(true)
+        .output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+
+    Ok(())
+}
+
+#[test]
+fn ppid_is_shared_by_the_same_parent() -> Result<()> {
+    let env = Env(["Defaults timestamp_type=ppid", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "set -e; echo {PASSWORD} | sudo -S true; sudo -S true < /dev/null"
+        ))
+        .as_user(USERNAME)
+        .tty(true)
+        .output(&env)?
+        .assert_success()
+}
+
+#[test]
+fn kernel_does_not_cache_without_a_terminal() -> Result<()> {
+    let env = Env(["Defaults timestamp_type=kernel", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "echo {PASSWORD} | sudo -S true || exit 2; sudo -S true < /dev/null"
+        ))
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+
+    Ok(())
+}
+
+#[test]
+fn reset_invalidates_global_record() -> Result<()> {
+    let env = Env(["Defaults timestamp_type=global", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "echo {PASSWORD} | sudo -S true || exit 2; sudo -k; sudo -S true < /dev/null"
+        ))
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+
+    Ok(())
+}
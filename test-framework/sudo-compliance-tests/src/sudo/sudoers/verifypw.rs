@@ -0,0 +1,65 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const MIXED_RULES: &str = "ferris ALL=(ALL:ALL) NOPASSWD: /usr/bin/true, PASSWD: /usr/bin/ls";
+
+fn validate_without_password(sudoers: &[&str]) -> Result<bool> {
+    let env = Env(sudoers.join("\n"))
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-n", "-v"])
+        .as_user(USERNAME)
+        .output(&env)?;
+
+    Ok(output.status().success())
+}
+
+#[test]
+fn default_requires_password_unless_all_entries_are_nopasswd() -> Result<()> {
+    assert!(!validate_without_password(&[MIXED_RULES])?);
+
+    Ok(())
+}
+
+#[test]
+fn any_accepts_a_single_nopasswd_entry() -> Result<()> {
+    assert!(validate_without_password(&[
+        MIXED_RULES,
+        "Defaults verifypw=any"
+    ])?);
+
+    Ok(())
+}
+
+#[test]
+fn always_requires_password() -> Result<()> {
+    assert!(!validate_without_password(&[
+        &format!("{USERNAME} ALL=(ALL:ALL) NOPASSWD: ALL"),
+        "Defaults verifypw=always"
+    ])?);
+
+    Ok(())
+}
+
+#[test]
+fn never_requires_password() -> Result<()> {
+    assert!(validate_without_password(&[
+        &format!("{USERNAME} ALL=(ALL:ALL) ALL"),
+        "Defaults verifypw=never"
+    ])?);
+
+    Ok(())
+}
+
+#[test]
+fn negation_means_never() -> Result<()> {
+    assert!(validate_without_password(&[
+        &format!("{USERNAME} ALL=(ALL:ALL) ALL"),
+        "Defaults !verifypw"
+    ])?);
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use sudo_test::{Command, Env, User};
+
+use crate::{Result, PASSWORD, USERNAME};
+
+const SUDOERS: &str = "ALL ALL=(ALL:ALL) ALL";
+
+#[test]
+fn refuses_to_prompt_without_a_terminal() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .arg("true")
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+
+    assert!(!output.status().success());
+    assert_contains!(
+        output.stderr(),
+        "a terminal is required to read the password"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn visiblepw_reads_password_without_a_terminal() -> Result<()> {
+    let env = Env(["Defaults visiblepw", SUDOERS])
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .arg("true")
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+
+    output.assert_success()
+}
+
+#[test]
+fn stdin_flag_does_not_need_visiblepw() -> Result<()> {
+    let env = Env(SUDOERS)
+        .user(User(USERNAME).password(PASSWORD))
+        .build()?;
+
+    let output = Command::new("sudo")
+        .args(["-S", "true"])
+        .as_user(USERNAME)
+        .stdin(PASSWORD)
+        .output(&env)?;
+
+    output.assert_success()
+}
@@ -44,7 +44,7 @@ fn by_default_credential_caching_is_local() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "incorrect authentication attempt"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
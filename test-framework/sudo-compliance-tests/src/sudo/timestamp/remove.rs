@@ -87,7 +87,7 @@ fn also_works_locally() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "Authentication failed"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
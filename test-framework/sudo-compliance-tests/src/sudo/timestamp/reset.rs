@@ -25,7 +25,7 @@ fn it_works() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "incorrect authentication attempt"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -74,7 +74,7 @@ fn with_command_prompts_for_password() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "incorrect authentication attempt"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -149,7 +149,7 @@ fn with_command_does_not_cache_credentials() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "Authentication failed"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -41,7 +41,7 @@ fn prompts_for_password() -> Result<()> {
     let diagnostic = if sudo_test::is_original_sudo() {
         "a password is required"
     } else {
-        "incorrect authentication attempt"
+        "a terminal is required to read the password"
     };
     assert_contains!(output.stderr(), diagnostic);
 
@@ -5,7 +5,9 @@ use sudo_test::{Command, Env, TextFile};
 use crate::{Result, PANIC_EXIT_CODE, SUDOERS_ALL_ALL_NOPASSWD};
 
 mod flag_check;
+mod flag_export;
 mod flag_file;
+mod flag_format;
 mod flag_help;
 mod flag_no_includes;
 mod flag_owner;
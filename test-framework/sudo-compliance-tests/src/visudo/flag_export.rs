@@ -0,0 +1,77 @@
+use sudo_test::{Command, Env, TextFile};
+
+use crate::Result;
+
+const DEFAULT_CHMOD: &str = "440";
+
+#[test]
+fn exports_to_stdout() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        // the JSON format of sudo-rs differs from that of the original sudo
+        return Ok(());
+    }
+
+    let env = Env(
+        TextFile("Host_Alias WEB = web1\nroot WEB = (ALL) NOPASSWD: /usr/bin/true")
+            .chmod(DEFAULT_CHMOD),
+    )
+    .build()?;
+
+    let output = Command::new("visudo").args(["-x", "-"]).output(&env)?;
+    let json = output.stdout()?;
+
+    assert_contains!(
+        json,
+        r#""files": [
+        "/etc/sudoers"
+    ],"#
+    );
+    assert_contains!(json, r#""name": "WEB","#);
+    assert_contains!(json, r#""tags": "NOPASSWD:","#);
+    assert_contains!(json, r#""command": "/usr/bin/true""#);
+    assert_contains!(json, r#""line": 2"#);
+
+    Ok(())
+}
+
+#[test]
+fn exports_to_file() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        return Ok(());
+    }
+
+    let export_path = "/tmp/sudoers.json";
+    let env = Env(TextFile("root ALL=(ALL:ALL) ALL").chmod(DEFAULT_CHMOD)).build()?;
+
+    let output = Command::new("visudo")
+        .args(["--export", export_path])
+        .output(&env)?;
+    assert!(output.status().success());
+    assert!(output.stdout()?.is_empty());
+
+    let exported = Command::new("cat")
+        .arg(export_path)
+        .output(&env)?
+        .stdout()?;
+    assert_contains!(exported, r#""version": 1,"#);
+    assert_contains!(exported, r#""command": "ALL""#);
+
+    Ok(())
+}
+
+#[test]
+fn nothing_is_exported_on_syntax_error() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        return Ok(());
+    }
+
+    let env = Env(TextFile("this is fine").chmod(DEFAULT_CHMOD)).build()?;
+
+    let output = Command::new("visudo").args(["-x", "-"]).output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+    assert!(output.stdout_unchecked().is_empty());
+    assert_contains!(output.stderr(), "syntax error");
+
+    Ok(())
+}
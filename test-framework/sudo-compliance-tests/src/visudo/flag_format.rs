@@ -0,0 +1,77 @@
+use sudo_test::{Command, Env, TextFile};
+
+use crate::{Result, USERNAME};
+
+const DEFAULT_CHMOD: &str = "440";
+
+#[test]
+fn json_diagnostics() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        // `--format` is a sudo-rs extension
+        return Ok(());
+    }
+
+    let env = Env(TextFile("this is fine").chmod(DEFAULT_CHMOD)).build()?;
+
+    let output = Command::new("visudo")
+        .args(["--check", "--format=json"])
+        .output(&env)?;
+
+    assert_eq!(Some(1), output.status().code());
+    let lines = output.stdout_unchecked().lines().collect::<Vec<_>>();
+    assert_eq!(2, lines.len());
+    assert_contains!(
+        lines[0],
+        r#""type":"diagnostic","file":"/etc/sudoers","line":1,"column":9,"severity":"error""#
+    );
+    assert_eq!(
+        lines[1],
+        r#"{"type":"summary","status":"syntax_error","files":["/etc/sudoers"],"errors":1,"warnings":0}"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn json_summary_of_valid_file() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        return Ok(());
+    }
+
+    let env = Env(TextFile("ALL ALL=(ALL:ALL) ALL").chmod(DEFAULT_CHMOD)).build()?;
+
+    let output = Command::new("visudo")
+        .args(["--check", "--format=json"])
+        .output(&env)?;
+
+    assert_eq!(
+        output.stdout()?,
+        r#"{"type":"summary","status":"ok","files":["/etc/sudoers"],"errors":0,"warnings":0}"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn json_exit_codes() -> Result<()> {
+    if sudo_test::is_original_sudo() {
+        return Ok(());
+    }
+
+    let env = Env(TextFile("").chmod("444")).build()?;
+    let output = Command::new("visudo")
+        .args(["--check", "--format=json"])
+        .output(&env)?;
+    assert_eq!(Some(2), output.status().code());
+    assert_contains!(output.stdout_unchecked(), r#""status":"permissions_error""#);
+
+    let env = Env("").user(USERNAME).build()?;
+    let output = Command::new("visudo")
+        .args(["--check", "--format=json", "/tmp/does-not-exist"])
+        .as_user(USERNAME)
+        .output(&env)?;
+    assert_eq!(Some(3), output.status().code());
+    assert_contains!(output.stdout_unchecked(), r#""status":"io_error""#);
+
+    Ok(())
+}
@@ -6,7 +6,6 @@ use crate::{
 };
 
 #[test]
-#[ignore = "gh657"]
 fn undefined_alias() -> Result<()> {
     let env = Env(["# User_Alias ADMINS = root", "ADMINS ALL=(ALL:ALL) ALL"])
         .file(DEFAULT_EDITOR, TextFile(EDITOR_TRUE).chmod(CHMOD_EXEC))
@@ -64,3 +63,24 @@ fn alias_cycle() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn check_undefined_alias() -> Result<()> {
+    let env = Env(TextFile("ADMINS ALL=(ALL:ALL) ALL").chmod("440")).build()?;
+
+    let output = Command::new("visudo")
+        .args(["--check", "--strict"])
+        .output(&env)?;
+
+    let diagnostic = r#"User_Alias "ADMINS" referenced but not defined"#;
+
+    assert_eq!(Some(1), output.status().code());
+    assert_contains!(output.stderr(), diagnostic);
+
+    let output = Command::new("visudo").arg("--check").output(&env)?;
+
+    assert!(output.status().success());
+    assert_contains!(output.stderr(), diagnostic);
+
+    Ok(())
+}
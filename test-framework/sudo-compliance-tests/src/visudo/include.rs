@@ -6,7 +6,6 @@ use crate::{
 };
 
 #[test]
-#[ignore = "gh657"]
 fn prompt() -> Result<()> {
     let env = Env("@include sudoers2")
         .file("/etc/sudoers2", "")
@@ -21,7 +20,6 @@ fn prompt() -> Result<()> {
 }
 
 #[test]
-#[ignore = "gh657"]
 fn calls_editor_on_included_files() -> Result<()> {
     let env = Env("@include sudoers2")
         .file("/etc/sudoers2", "")
@@ -49,7 +47,6 @@ echo $@ >> {LOGS_PATH}"
 }
 
 #[test]
-#[ignore = "gh657"]
 fn closing_stdin_is_understood_as_yes_to_all() -> Result<()> {
     let env = Env("@include sudoers2
 @include sudoers3")
@@ -76,7 +73,6 @@ echo $@ >> {LOGS_PATH}"
 }
 
 #[test]
-#[ignore = "gh657"]
 fn edit_order_follows_include_order() -> Result<()> {
     let env = Env("# 1
 @include sudoers2
@@ -153,7 +149,6 @@ cat $2 >> {LOGS_PATH}"
 }
 
 #[test]
-#[ignore = "gh657"]
 fn does_edit_at_include_added_in_last_edit() -> Result<()> {
     let env = Env("# 1")
         .file("/etc/sudoers2", "# 2")
@@ -184,7 +179,6 @@ cat /tmp/scratchpad >> {LOGS_PATH}"
 }
 
 #[test]
-#[ignore = "gh657"]
 fn does_edit_at_include_removed_in_last_edit() -> Result<()> {
     let env = Env("# 1
 @include sudoers2")
@@ -216,7 +210,6 @@ cat /tmp/scratchpad >> {LOGS_PATH}"
 }
 
 #[test]
-#[ignore = "gh657"]
 fn edits_existing_at_includes_first_then_newly_added_at_includes() -> Result<()> {
     let env = Env("# 1
 @include sudoers2")
@@ -249,7 +242,7 @@ cat /tmp/scratchpad >> {LOGS_PATH}"
 }
 
 #[test]
-fn does_not_edit_files_in_includedir_directories() -> Result<()> {
+fn edits_files_in_includedir_directories() -> Result<()> {
     let env = Env("# 1
 @includedir /etc/sudoers.d")
     .file("/etc/sudoers.d/a", "# 2")
@@ -272,7 +265,67 @@ cat $2 >> {LOGS_PATH}"
         .filter(|line| line.starts_with('#'))
         .collect::<Vec<_>>();
 
-    assert_eq!(["# 1"], &*comments);
+    // ogvisudo only edits files that are included with `@include`
+    if sudo_test::is_original_sudo() {
+        assert_eq!(["# 1"], &*comments);
+    } else {
+        assert_eq!(["# 1", "# 2"], &*comments);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn changes_to_included_files_are_saved() -> Result<()> {
+    let env = Env("@include sudoers2")
+        .file("/etc/sudoers2", "# 2")
+        .file(
+            DEFAULT_EDITOR,
+            TextFile(
+                "#!/bin/sh
+case $2 in *sudoers2) echo '# edited' >> $2;; esac",
+            )
+            .chmod(CHMOD_EXEC),
+        )
+        .build()?;
+
+    Command::new("visudo").output(&env)?.assert_success()?;
+
+    let contents = Command::new("cat")
+        .arg("/etc/sudoers2")
+        .output(&env)?
+        .stdout()?;
+
+    assert_eq!("# 2\n# edited", contents);
+
+    Ok(())
+}
+
+#[test]
+fn included_file_with_syntax_errors_is_not_saved() -> Result<()> {
+    let env = Env("@include sudoers2")
+        .file("/etc/sudoers2", "# 2")
+        .file(
+            DEFAULT_EDITOR,
+            TextFile(
+                "#!/bin/sh
+case $2 in *sudoers2) echo 'this is fine' >> $2;; esac",
+            )
+            .chmod(CHMOD_EXEC),
+        )
+        .build()?;
+
+    let output = Command::new("visudo").stdin("\nx").output(&env)?;
+
+    output.assert_success()?;
+    assert_contains!(output.stderr(), "syntax error");
+
+    let contents = Command::new("cat")
+        .arg("/etc/sudoers2")
+        .output(&env)?
+        .stdout()?;
+
+    assert_eq!("# 2", contents);
 
     Ok(())
 }
@@ -36,8 +36,12 @@ mkdir -p "$target_dir_sudo/bin"
 mkdir -p "$target_dir_sudo/share/man/man8"
 cp "$PROJECT_DIR/target/release/sudo" "$target_dir_sudo/bin/sudo"
 cp "$PROJECT_DIR/target/release/visudo" "$target_dir_sudo/bin/visudo"
+cp "$PROJECT_DIR/target/release/sudoreplay" "$target_dir_sudo/bin/sudoreplay"
+cp "$PROJECT_DIR/target/release/cvtsudoers" "$target_dir_sudo/bin/cvtsudoers"
 cp "$PROJECT_DIR/target/docs/man/sudo.8" "$target_dir_sudo/share/man/man8/sudo.8"
 cp "$PROJECT_DIR/target/docs/man/visudo.8" "$target_dir_sudo/share/man/man8/visudo.8"
+cp "$PROJECT_DIR/target/docs/man/sudoreplay.8" "$target_dir_sudo/share/man/man8/sudoreplay.8"
+cp "$PROJECT_DIR/target/docs/man/cvtsudoers.8" "$target_dir_sudo/share/man/man8/cvtsudoers.8"
 mkdir -p "$target_dir_sudo/share/doc/sudo-rs/sudo"
 cp "$PROJECT_DIR/README.md" "$target_dir_sudo/share/doc/sudo-rs/sudo/README.md"
 cp "$PROJECT_DIR/CHANGELOG.md" "$target_dir_sudo/share/doc/sudo-rs/sudo/CHANGELOG.md"
@@ -52,6 +56,8 @@ set -x
 chown -R root:root "$target_dir_sudo"
 chmod +xs "$target_dir_sudo/bin/sudo"
 chmod +x "$target_dir_sudo/bin/visudo"
+chmod +x "$target_dir_sudo/bin/sudoreplay"
+chmod +x "$target_dir_sudo/bin/cvtsudoers"
 (cd $target_dir_sudo && tar --mtime="UTC $DATE 00:00:00" --use-compress-program='gzip -9n' -cpvf "$target_sudo" *)
 EOF
 
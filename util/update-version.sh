@@ -16,6 +16,8 @@ echo "Updating version in man pages"
 sed -i 's/^title: SU(1) sudo-rs .*/title: SU(1) sudo-rs '"$NEW_VERSION"' | sudo-rs/' "$PROJECT_DIR"/docs/man/su.1.md
 sed -i 's/^title: SUDO(8) sudo-rs .*/title: SUDO(8) sudo-rs '"$NEW_VERSION"' | sudo-rs/' "$PROJECT_DIR"/docs/man/sudo.8.md
 sed -i 's/^title: VISUDO(8) sudo-rs .*/title: VISUDO(8) sudo-rs '"$NEW_VERSION"' | sudo-rs/' "$PROJECT_DIR"/docs/man/visudo.8.md
+sed -i 's/^title: SUDOREPLAY(8) sudo-rs .*/title: SUDOREPLAY(8) sudo-rs '"$NEW_VERSION"' | sudo-rs/' "$PROJECT_DIR"/docs/man/sudoreplay.8.md
+sed -i 's/^title: CVTSUDOERS(8) sudo-rs .*/title: CVTSUDOERS(8) sudo-rs '"$NEW_VERSION"' | sudo-rs/' "$PROJECT_DIR"/docs/man/cvtsudoers.8.md
 
 echo "Rebuilding project"
 (cd $PROJECT_DIR && cargo build --release)